@@ -36,6 +36,7 @@ pub const MISC_ERROR: &str = include_str!("./public/html/misc/error.html");
 pub const MISC_NOTIFICATIONS: &str = include_str!("./public/html/misc/notifications.html");
 pub const MISC_MARKDOWN: &str = include_str!("./public/html/misc/markdown.html");
 pub const MISC_REQUESTS: &str = include_str!("./public/html/misc/requests.html");
+pub const MISC_SEARCH: &str = include_str!("./public/html/misc/search.html");
 
 pub const AUTH_BASE: &str = include_str!("./public/html/auth/base.html");
 pub const AUTH_LOGIN: &str = include_str!("./public/html/auth/login.html");
@@ -76,6 +77,7 @@ pub const TIMELINES_ALL_QUESTIONS: &str =
 
 pub const MOD_AUDIT_LOG: &str = include_str!("./public/html/mod/audit_log.html");
 pub const MOD_REPORTS: &str = include_str!("./public/html/mod/reports.html");
+pub const MOD_POST_REVISIONS: &str = include_str!("./public/html/mod/post_revisions.html");
 pub const MOD_FILE_REPORT: &str = include_str!("./public/html/mod/file_report.html");
 pub const MOD_IP_BANS: &str = include_str!("./public/html/mod/ip_bans.html");
 pub const MOD_PROFILE: &str = include_str!("./public/html/mod/profile.html");
@@ -182,6 +184,7 @@ pub(crate) async fn write_assets(config: &Config) -> PathBufD {
     write_template!(html_path->"misc/notifications.html"(crate::assets::MISC_NOTIFICATIONS) --config=config);
     write_template!(html_path->"misc/markdown.html"(crate::assets::MISC_MARKDOWN) --config=config);
     write_template!(html_path->"misc/requests.html"(crate::assets::MISC_REQUESTS) --config=config);
+    write_template!(html_path->"misc/search.html"(crate::assets::MISC_SEARCH) --config=config);
 
     write_template!(html_path->"auth/base.html"(crate::assets::AUTH_BASE) -d "auth" --config=config);
     write_template!(html_path->"auth/login.html"(crate::assets::AUTH_LOGIN) --config=config);
@@ -217,6 +220,7 @@ pub(crate) async fn write_assets(config: &Config) -> PathBufD {
 
     write_template!(html_path->"mod/audit_log.html"(crate::assets::MOD_AUDIT_LOG) -d "mod" --config=config);
     write_template!(html_path->"mod/reports.html"(crate::assets::MOD_REPORTS) --config=config);
+    write_template!(html_path->"mod/post_revisions.html"(crate::assets::MOD_POST_REVISIONS) --config=config);
     write_template!(html_path->"mod/file_report.html"(crate::assets::MOD_FILE_REPORT) --config=config);
     write_template!(html_path->"mod/ip_bans.html"(crate::assets::MOD_IP_BANS) --config=config);
     write_template!(html_path->"mod/profile.html"(crate::assets::MOD_PROFILE) --config=config);
@@ -75,10 +75,7 @@ pub fn save_avif_buffer(path: &str, bytes: Vec<u8>) -> std::io::Result<()> {
         .write_to(&mut writer, image::ImageFormat::Avif)
         .is_err()
     {
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "Image conversion failed",
-        ));
+        return Err(std::io::Error::other("Image conversion failed"));
     };
 
     Ok(())
@@ -57,13 +57,15 @@ macro_rules! create_dir_if_not_exists {
 macro_rules! get_user_from_token {
     ($jar:ident, $db:expr) => {{
         if let Some(token) = $jar.get("__Secure-atto-token") {
-            match $db
-                .get_user_by_token(&tetratto_shared::hash::hash(
-                    token.to_string().replace("__Secure-atto-token=", ""),
-                ))
-                .await
-            {
+            let token =
+                tetratto_shared::hash::hash(token.to_string().replace("__Secure-atto-token=", ""));
+
+            match $db.get_user_by_token(&token).await {
                 Ok(ua) => {
+                    if let Err(e) = $db.touch_user_token(&ua, &token).await {
+                        tracing::warn!("failed to update session: {e}");
+                    }
+
                     if ua.permissions.check_banned() {
                         Some(tetratto_core::model::auth::User::banned())
                     } else {
@@ -12,9 +12,16 @@ use axum::{Extension, Router};
 use reqwest::Client;
 use tera::{Tera, Value};
 use tower_http::trace::{self, TraceLayer};
-use tracing::{Level, info};
-
-use std::{collections::HashMap, env::var, process::exit, sync::Arc};
+use tracing::{Level, info, warn};
+
+use std::{
+    collections::HashMap,
+    env::{args, var},
+    net::SocketAddr,
+    process::exit,
+    sync::Arc,
+    time::Duration,
+};
 use tokio::sync::RwLock;
 
 pub(crate) type State = Arc<RwLock<(DataManager, Tera, Client)>>;
@@ -49,8 +56,45 @@ async fn main() {
 
     // ...
     let database = DataManager::new(config.clone()).await.unwrap();
+
+    if args().any(|x| x == "--dry-run") {
+        // list pending migrations without touching the database
+        let pending = database.get_pending_migrations().await.unwrap();
+
+        if pending.is_empty() {
+            info!("database schema is up to date");
+        }
+
+        for migration in pending {
+            info!(
+                "pending migration {} ({})",
+                migration.version, migration.name
+            );
+        }
+
+        return;
+    }
+
     database.init().await.unwrap();
 
+    // permanently delete content (and old exports) once its window passes
+    let purge_database = database.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(3600));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = purge_database.purge_deleted().await {
+                warn!("failed to purge deleted content: {e}");
+            }
+
+            if let Err(e) = purge_database.purge_expired_exports().await {
+                warn!("failed to purge expired exports: {e}");
+            }
+        }
+    });
+
     let mut tera = match Tera::new(&format!("{html_path}/**/*")) {
         Ok(t) => t,
         Err(e) => {
@@ -86,5 +130,10 @@ async fn main() {
 
     info!("🐇 tetratto.");
     info!("listening on http://0.0.0.0:{}", config.port);
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
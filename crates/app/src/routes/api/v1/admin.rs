@@ -0,0 +1,53 @@
+use crate::{State, get_user_from_token, routes::api::v1::ApiJson};
+use axum::{Extension, extract::Path, response::IntoResponse};
+use axum_extra::extract::CookieJar;
+use tetratto_core::model::{ApiReturn, Error, permissions::FinePermission, reactions::AssetType};
+
+/// Recompute every denormalized counter on the instance.
+pub async fn reconcile_counters_request(
+    jar: CookieJar,
+    Extension(data): Extension<State>,
+) -> impl IntoResponse {
+    let data = &(data.read().await).0;
+    let user = match get_user_from_token!(jar, data) {
+        Some(ua) => ua,
+        None => return ApiJson(Error::NotAllowed.into()),
+    };
+
+    if !user.permissions.check(FinePermission::ADMINISTRATOR) {
+        return ApiJson(Error::NotAllowed.into());
+    }
+
+    match data.reconcile_counters().await {
+        Ok(changes) => ApiJson(ApiReturn::ok(
+            format!("Counters reconciled ({} changed)", changes.len()),
+            changes,
+        )),
+        Err(e) => ApiJson(e.into()),
+    }
+}
+
+/// Recompute the denormalized counters of a single asset.
+pub async fn reconcile_asset_counters_request(
+    jar: CookieJar,
+    Extension(data): Extension<State>,
+    Path((asset_type, id)): Path<(AssetType, usize)>,
+) -> impl IntoResponse {
+    let data = &(data.read().await).0;
+    let user = match get_user_from_token!(jar, data) {
+        Some(ua) => ua,
+        None => return ApiJson(Error::NotAllowed.into()),
+    };
+
+    if !user.permissions.check(FinePermission::ADMINISTRATOR) {
+        return ApiJson(Error::NotAllowed.into());
+    }
+
+    match data.reconcile_asset_counters(asset_type, id).await {
+        Ok(changes) => ApiJson(ApiReturn::ok(
+            format!("Counters reconciled ({} changed)", changes.len()),
+            changes,
+        )),
+        Err(e) => ApiJson(e.into()),
+    }
+}
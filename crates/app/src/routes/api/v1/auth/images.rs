@@ -1,5 +1,5 @@
 use axum::{
-    Extension, Json,
+    Extension,
     body::Body,
     extract::{Path, Query},
     response::IntoResponse,
@@ -9,7 +9,7 @@ use pathbufd::{PathBufD, pathd};
 use serde::Deserialize;
 use std::{
     fs::{File, exists},
-    io::Read,
+    io::{BufReader, Read},
 };
 use tetratto_core::model::{ApiReturn, Error};
 
@@ -17,12 +17,13 @@ use crate::{
     State,
     avif::{Image, save_avif_buffer},
     get_user_from_token,
+    routes::api::v1::ApiJson,
 };
 
 pub fn read_image(path: PathBufD) -> Vec<u8> {
     let mut bytes = Vec::new();
 
-    for byte in File::open(path).unwrap().bytes() {
+    for byte in BufReader::new(File::open(path).unwrap()).bytes() {
         bytes.push(byte.unwrap())
     }
 
@@ -162,14 +163,14 @@ pub async fn upload_avatar_request(
     let data = &(data.read().await).0;
     let auth_user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     let path = pathd!("{}/avatars/{}.avif", data.0.dirs.media, &auth_user.id);
 
     // check file size
     if img.0.len() > MAXIUMUM_FILE_SIZE {
-        return Json(Error::DataTooLong("image".to_string()).into());
+        return ApiJson(Error::DataTooLong("image".to_string()).into());
     }
 
     // upload image
@@ -180,12 +181,11 @@ pub async fn upload_avatar_request(
     }
 
     match save_avif_buffer(&path, bytes) {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Avatar uploaded. It might take a bit to update".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(Error::MiscError(e.to_string()).into()),
+        Ok(_) => ApiJson(ApiReturn::ok(
+            "Avatar uploaded. It might take a bit to update".to_string(),
+            (),
+        )),
+        Err(e) => ApiJson(Error::MiscError(e.to_string()).into()),
     }
 }
 
@@ -199,14 +199,14 @@ pub async fn upload_banner_request(
     let data = &(data.read().await).0;
     let auth_user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     let path = pathd!("{}/banners/{}.avif", data.0.dirs.media, &auth_user.id);
 
     // check file size
     if img.0.len() > MAXIUMUM_FILE_SIZE {
-        return Json(Error::DataTooLong("image".to_string()).into());
+        return ApiJson(Error::DataTooLong("image".to_string()).into());
     }
 
     // upload image
@@ -217,11 +217,10 @@ pub async fn upload_banner_request(
     }
 
     match save_avif_buffer(&path, bytes) {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Banner uploaded. It might take a bit to update".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(Error::MiscError(e.to_string()).into()),
+        Ok(_) => ApiJson(ApiReturn::ok(
+            "Banner uploaded. It might take a bit to update".to_string(),
+            (),
+        )),
+        Err(e) => ApiJson(Error::MiscError(e.to_string()).into()),
     }
 }
@@ -1,7 +1,7 @@
 use crate::{
     State, get_user_from_token,
     model::{ApiReturn, Error},
-    routes::api::v1::CreateIpBan,
+    routes::api::v1::{ApiJson, CreateIpBan},
 };
 use axum::{Extension, Json, extract::Path, response::IntoResponse};
 use axum_extra::extract::CookieJar;
@@ -17,20 +17,16 @@ pub async fn create_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     if !user.permissions.check(FinePermission::MANAGE_BANS) {
-        return Json(Error::NotAllowed.into());
+        return ApiJson(Error::NotAllowed.into());
     }
 
     match data.create_ipban(IpBan::new(ip, user.id, req.reason)).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "IP ban created".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("IP ban created".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -43,19 +39,15 @@ pub async fn delete_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     if !user.permissions.check(FinePermission::MANAGE_BANS) {
-        return Json(Error::NotAllowed.into());
+        return ApiJson(Error::NotAllowed.into());
     }
 
     match data.delete_ipban(&ip, user).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "IP ban deleted".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("IP ban deleted".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
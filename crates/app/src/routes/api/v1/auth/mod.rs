@@ -4,14 +4,14 @@ pub mod profile;
 pub mod social;
 pub mod user_warnings;
 
-use super::{LoginProps, RegisterProps};
+use super::{ApiJson, LoginProps, RegisterProps};
 use crate::{
     State, get_user_from_token,
     model::{ApiReturn, Error, auth::User},
 };
 use axum::{
     extract::Query,
-    http::{HeaderMap, HeaderValue},
+    http::{HeaderMap, HeaderValue, header::USER_AGENT},
     response::{IntoResponse, Redirect},
     Extension, Json,
 };
@@ -21,6 +21,15 @@ use tetratto_shared::hash::hash;
 
 use cf_turnstile::{SiteVerifyRequest, TurnstileClient};
 
+/// Get the user agent of a request (or an empty string if it doesn't have one).
+fn user_agent(headers: &HeaderMap) -> String {
+    headers
+        .get(USER_AGENT)
+        .and_then(|x| x.to_str().ok())
+        .unwrap_or("")
+        .to_string()
+}
+
 /// `/api/v1/auth/register`
 pub async fn register_request(
     headers: HeaderMap,
@@ -52,7 +61,7 @@ pub async fn register_request(
 
     // check for ip ban
     if data.get_ipban_by_ip(&real_ip).await.is_ok() {
-        return (None, Json(Error::NotAllowed.into()));
+        return (None, ApiJson(Error::NotAllowed.into()));
     }
 
     // check captcha
@@ -66,21 +75,26 @@ pub async fn register_request(
         .await
     {
         Ok(v) => v,
-        Err(e) => return (None, Json(Error::MiscError(e.to_string()).into())),
+        Err(e) => return (None, ApiJson(Error::MiscError(e.to_string()).into())),
     };
 
     if !validated.success | !props.policy_consent {
         return (
             None,
-            Json(Error::MiscError("Captcha failed".to_string()).into()),
+            ApiJson(Error::MiscError("Captcha failed".to_string()).into()),
         );
     }
 
     // ...
-    let mut user = User::new(props.username, props.password);
+    let mut user = User::new(
+        props.username,
+        props.password,
+        &data.0.security.password_hashing,
+    )
+    .await;
     user.settings.policy_consent = true;
 
-    let (initial_token, t) = User::create_token(&real_ip);
+    let (initial_token, t) = User::create_token(&real_ip, &user_agent(&headers));
     user.tokens.push(t);
 
     // return
@@ -94,13 +108,9 @@ pub async fn register_request(
                     60 * 60 * 24 * 365
                 ),
             )]),
-            Json(ApiReturn {
-                ok: true,
-                message: initial_token,
-                payload: (),
-            }),
+            ApiJson(ApiReturn::ok(initial_token, ())),
         ),
-        Err(e) => (None, Json(e.into())),
+        Err(e) => (None, ApiJson(e.into())),
     }
 }
 
@@ -115,7 +125,7 @@ pub async fn login_request(
     // let user = get_user_from_token!(jar, data);
 
     // if user.is_some() {
-    //     return (None, Json(Error::AlreadyAuthenticated.into()));
+    //     return (None, ApiJson(Error::AlreadyAuthenticated.into()));
     // }
 
     // get real ip
@@ -128,31 +138,36 @@ pub async fn login_request(
 
     // check for ip ban
     if data.get_ipban_by_ip(&real_ip).await.is_ok() {
-        return (None, Json(Error::NotAllowed.into()));
+        return (None, ApiJson(Error::NotAllowed.into()));
     }
 
     // verify password
     let user = match data.get_user_by_username(&props.username).await {
         Ok(ua) => ua,
-        Err(_) => return (None, Json(Error::IncorrectPassword.into())),
+        Err(_) => return (None, ApiJson(Error::IncorrectPassword.into())),
     };
 
-    if !user.check_password(props.password) {
-        return (None, Json(Error::IncorrectPassword.into()));
+    if !user.check_password(props.password.clone()).await {
+        return (None, ApiJson(Error::IncorrectPassword.into()));
     }
 
     // verify totp code
     if !data.check_totp(&user, &props.totp) {
-        return (None, Json(Error::NotAllowed.into()));
+        return (None, ApiJson(Error::NotAllowed.into()));
+    }
+
+    // rehash password if it was hashed with old parameters
+    if let Err(e) = data.upgrade_user_password(&user, props.password).await {
+        tracing::warn!("failed to rehash password: {e}");
     }
 
     // update tokens
     let mut new_tokens = user.tokens.clone();
-    let (unhashed_token_id, token) = User::create_token(&real_ip);
+    let (unhashed_token_id, token) = User::create_token(&real_ip, &user_agent(&headers));
     new_tokens.push(token);
 
     if let Err(e) = data.update_user_tokens(user.id, new_tokens).await {
-        return (None, Json(e.into()));
+        return (None, ApiJson(e.into()));
     }
 
     // ...
@@ -165,11 +180,7 @@ pub async fn login_request(
                 60 * 60 * 24 * 365
             ),
         )]),
-        Json(ApiReturn {
-            ok: true,
-            message: unhashed_token_id,
-            payload: (),
-        }),
+        ApiJson(ApiReturn::ok(unhashed_token_id, ())),
     )
 }
 
@@ -181,7 +192,7 @@ pub async fn logout_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return (None, Json(Error::NotAllowed.into())),
+        None => return (None, ApiJson(Error::NotAllowed.into())),
     };
 
     // update tokens
@@ -191,16 +202,8 @@ pub async fn logout_request(
         .to_string()
         .replace("__Secure-atto-token=", "");
 
-    let mut new_tokens = user.tokens.clone();
-    new_tokens.remove(
-        new_tokens
-            .iter()
-            .position(|t| t.1 == hash(token.to_string()))
-            .unwrap(),
-    );
-
-    if let Err(e) = data.update_user_tokens(user.id, new_tokens).await {
-        return (None, Json(e.into()));
+    if let Err(e) = data.delete_user_token(user.id, &hash(token)).await {
+        return (None, ApiJson(e.into()));
     }
 
     // ...
@@ -212,11 +215,7 @@ pub async fn logout_request(
                 "refresh",
             ),
         )]),
-        Json(ApiReturn {
-            ok: true,
-            message: "Goodbye!".to_string(),
-            payload: (),
-        }),
+        ApiJson(ApiReturn::ok("Goodbye!".to_string(), ())),
     )
 }
 
@@ -2,20 +2,23 @@ use crate::{
     get_user_from_token,
     model::{ApiReturn, Error},
     routes::api::v1::{
-        DeleteUser, DisableTotp, UpdateUserIsVerified, UpdateUserPassword, UpdateUserRole,
-        UpdateUserUsername,
+        ApiJson, DeleteUser, DisableTotp, UpdateSessionLabel, UpdateUserIsVerified,
+        UpdateUserPassword, UpdateUserRole, UpdateUserUsername,
     },
     State,
 };
 use axum::{
     Extension, Json,
+    body::Body,
     extract::Path,
     response::{IntoResponse, Redirect},
 };
-use axum_extra::extract::CookieJar;
+use axum_extra::extract::{CookieJar, Multipart};
+use tetratto_shared::hash::hash;
 use tetratto_core::{
+    database::RunningExport,
     model::{
-        auth::{Token, UserSettings},
+        auth::{Session, Token, UserSettings},
         permissions::FinePermission,
     },
     DataManager,
@@ -69,11 +72,11 @@ pub async fn update_user_settings_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     if user.id != id && !user.permissions.check(FinePermission::MANAGE_USERS) {
-        return Json(Error::NotAllowed.into());
+        return ApiJson(Error::NotAllowed.into());
     }
 
     // check percentage themes
@@ -87,12 +90,8 @@ pub async fn update_user_settings_request(
 
     // ...
     match data.update_user_settings(id, req).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Settings updated".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("Settings updated".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -106,23 +105,19 @@ pub async fn update_user_password_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     if user.id != id && !user.permissions.check(FinePermission::MANAGE_USERS) {
-        return Json(Error::NotAllowed.into());
+        return ApiJson(Error::NotAllowed.into());
     }
 
     match data
         .update_user_password(id, req.from, req.to, user, false)
         .await
     {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Password updated".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("Password updated".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -135,24 +130,20 @@ pub async fn update_user_username_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     if user.id != id && !user.permissions.check(FinePermission::MANAGE_USERS) {
-        return Json(Error::NotAllowed.into());
+        return ApiJson(Error::NotAllowed.into());
     }
 
     if data.get_user_by_username(&req.to).await.is_ok() {
-        return Json(Error::UsernameInUse.into());
+        return ApiJson(Error::UsernameInUse.into());
     }
 
     match data.update_user_username(id, req.to, user).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Username updated".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("Username updated".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -166,20 +157,135 @@ pub async fn update_user_tokens_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     if user.id != id && !user.permissions.check(FinePermission::MANAGE_USERS) {
-        return Json(Error::NotAllowed.into());
+        return ApiJson(Error::NotAllowed.into());
     }
 
     match data.update_user_tokens(id, req).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Tokens updated".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("Tokens updated".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
+    }
+}
+
+/// Get the hashed token of the session making a request.
+fn current_token(jar: &CookieJar) -> String {
+    match jar.get("__Secure-atto-token") {
+        Some(token) => hash(token.to_string().replace("__Secure-atto-token=", "")),
+        None => String::new(),
+    }
+}
+
+/// Get the sessions of the given user.
+pub async fn get_user_sessions_request(
+    jar: CookieJar,
+    Path(id): Path<usize>,
+    Extension(data): Extension<State>,
+) -> impl IntoResponse {
+    let data = &(data.read().await).0;
+    let user = match get_user_from_token!(jar, data) {
+        Some(ua) => ua,
+        None => return ApiJson(Error::NotAllowed.into()),
+    };
+
+    let tokens = if user.id == id {
+        user.tokens
+    } else if user.permissions.check(FinePermission::MANAGE_USERS) {
+        match data.get_user_by_id(id).await {
+            Ok(ua) => ua.tokens,
+            Err(e) => return ApiJson(e.into()),
+        }
+    } else {
+        return ApiJson(Error::NotAllowed.into());
+    };
+
+    let current = current_token(&jar);
+
+    ApiJson(ApiReturn::ok(
+        "Sessions".to_string(),
+        tokens
+            .into_iter()
+            .map(|token| Session {
+                current: token.token == current,
+                token,
+            })
+            .collect::<Vec<Session>>(),
+    ))
+}
+
+/// Log out every session of the given user, except the one making the request.
+pub async fn delete_other_sessions_request(
+    jar: CookieJar,
+    Path(id): Path<usize>,
+    Extension(data): Extension<State>,
+) -> impl IntoResponse {
+    let data = &(data.read().await).0;
+    let user = match get_user_from_token!(jar, data) {
+        Some(ua) => ua,
+        None => return ApiJson(Error::NotAllowed.into()),
+    };
+
+    if user.id != id && !user.permissions.check(FinePermission::MANAGE_USERS) {
+        return ApiJson(Error::NotAllowed.into());
+    }
+
+    // the requesting session only belongs to the user if they're managing themselves
+    let keep = if user.id == id {
+        current_token(&jar)
+    } else {
+        String::new()
+    };
+
+    match data.delete_user_tokens_except(id, &keep).await {
+        Ok(_) => ApiJson(ApiReturn::ok("Sessions logged out".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
+    }
+}
+
+/// Log out the given session of the given user.
+pub async fn delete_session_request(
+    jar: CookieJar,
+    Path((id, session)): Path<(usize, String)>,
+    Extension(data): Extension<State>,
+) -> impl IntoResponse {
+    let data = &(data.read().await).0;
+    let user = match get_user_from_token!(jar, data) {
+        Some(ua) => ua,
+        None => return ApiJson(Error::NotAllowed.into()),
+    };
+
+    if user.id != id && !user.permissions.check(FinePermission::MANAGE_USERS) {
+        return ApiJson(Error::NotAllowed.into());
+    }
+
+    match data.delete_user_token(id, &session).await {
+        Ok(_) => ApiJson(ApiReturn::ok("Session logged out".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
+    }
+}
+
+/// Update the label of the given session of the given user.
+pub async fn update_session_label_request(
+    jar: CookieJar,
+    Path((id, session)): Path<(usize, String)>,
+    Extension(data): Extension<State>,
+    Json(req): Json<UpdateSessionLabel>,
+) -> impl IntoResponse {
+    let data = &(data.read().await).0;
+    let user = match get_user_from_token!(jar, data) {
+        Some(ua) => ua,
+        None => return ApiJson(Error::NotAllowed.into()),
+    };
+
+    if user.id != id && !user.permissions.check(FinePermission::MANAGE_USERS) {
+        return ApiJson(Error::NotAllowed.into());
+    }
+
+    match data.update_user_token_label(id, &session, &req.label).await {
+        Ok(_) => ApiJson(ApiReturn::ok("Session updated".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -193,19 +299,15 @@ pub async fn update_user_is_verified_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     match data
         .update_user_verified_status(id, req.is_verified, user)
         .await
     {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Verified status updated".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("Verified status updated".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -219,16 +321,12 @@ pub async fn update_user_role_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     match data.update_user_role(id, req.role, user).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "User updated".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("User updated".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -237,16 +335,106 @@ pub async fn seen_request(jar: CookieJar, Extension(data): Extension<State>) ->
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     match data.seen_user(&user).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "User updated".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("User updated".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
+    }
+}
+
+/// Start generating an export of the current user's data.
+///
+/// The user is sent a notification once the archive can be downloaded.
+pub async fn create_export_request(
+    jar: CookieJar,
+    Extension(data): Extension<State>,
+) -> impl IntoResponse {
+    let data = &(data.read().await).0;
+    let user = match get_user_from_token!(jar, data) {
+        Some(ua) => ua,
+        None => return ApiJson(Error::NotAllowed.into()),
+    };
+
+    let running = match RunningExport::claim(user.id) {
+        Ok(x) => x,
+        Err(e) => return ApiJson(e.into()),
+    };
+
+    let data = data.clone();
+    tokio::spawn(async move {
+        if let Err(e) = data.create_user_export(&user, running).await {
+            tracing::warn!("failed to export user {}: {e}", user.id);
+        }
+    });
+
+    ApiJson(ApiReturn::ok("Export started".to_string(), ()))
+}
+
+/// Download the current user's finished data export.
+pub async fn export_request(
+    jar: CookieJar,
+    Extension(data): Extension<State>,
+) -> Result<impl IntoResponse, ApiJson<()>> {
+    let data = &(data.read().await).0;
+    let user = match get_user_from_token!(jar, data) {
+        Some(ua) => ua,
+        None => return Err(ApiJson(Error::NotAllowed.into())),
+    };
+
+    match data.get_user_export(user.id).await {
+        Ok(archive) => Ok((
+            [
+                ("Content-Type", "application/zip".to_string()),
+                (
+                    "Content-Disposition",
+                    format!("attachment; filename=\"{}.zip\"", user.username),
+                ),
+            ],
+            Body::from(archive),
+        )),
+        Err(e) => Err(ApiJson(e.into())),
+    }
+}
+
+/// Recreate an account from an export archive.
+///
+/// Expects a multipart form with the `archive` and the `password` of the new account.
+pub async fn import_request(
+    jar: CookieJar,
+    Extension(data): Extension<State>,
+    mut multipart: Multipart,
+) -> impl IntoResponse {
+    let data = &(data.read().await).0;
+    let user = match get_user_from_token!(jar, data) {
+        Some(ua) => ua,
+        None => return ApiJson(Error::NotAllowed.into()),
+    };
+
+    if !user.permissions.check(FinePermission::MANAGE_USERS) {
+        return ApiJson(Error::NotAllowed.into());
+    }
+
+    let mut archive = None;
+    let mut password = String::new();
+
+    while let Ok(Some(field)) = multipart.next_field().await {
+        match field.name() {
+            Some("archive") => archive = field.bytes().await.ok(),
+            Some("password") => password = field.text().await.unwrap_or_default(),
+            _ => continue,
+        }
+    }
+
+    let archive = match archive {
+        Some(a) => a,
+        None => return ApiJson(Error::MiscError("Missing archive".to_string()).into()),
+    };
+
+    match data.import_user_data(&archive, password).await {
+        Ok(summary) => ApiJson(ApiReturn::ok("User imported".to_string(), summary)),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -260,23 +448,19 @@ pub async fn delete_user_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     if user.id != id && !user.permissions.check(FinePermission::MANAGE_USERS) {
-        return Json(Error::NotAllowed.into());
+        return ApiJson(Error::NotAllowed.into());
     }
 
     match data
         .delete_user(id, &req.password, user.permissions.check_manager())
         .await
     {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "User deleted".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("User deleted".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -289,16 +473,12 @@ pub async fn enable_totp_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     match data.enable_totp(id, user).await {
-        Ok(x) => Json(ApiReturn {
-            ok: true,
-            message: "TOTP enabled".to_string(),
-            payload: Some(x),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(x) => ApiJson(ApiReturn::ok("TOTP enabled".to_string(), Some(x))),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -312,31 +492,27 @@ pub async fn disable_totp_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     if user.id != id && !user.permissions.check(FinePermission::MANAGE_USERS) {
-        return Json(Error::NotAllowed.into());
+        return ApiJson(Error::NotAllowed.into());
     }
 
     // check totp code
     let other_user = match data.get_user_by_id(id).await {
         Ok(u) => u,
-        Err(e) => return Json(e.into()),
+        Err(e) => return ApiJson(e.into()),
     };
 
     if !data.check_totp(&other_user, &req.totp) {
-        return Json(Error::NotAllowed.into());
+        return ApiJson(Error::NotAllowed.into());
     }
 
     // ...
     match data.update_user_totp(id, "", &Vec::new()).await {
-        Ok(()) => Json(ApiReturn {
-            ok: true,
-            message: "TOTP disabled".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(()) => ApiJson(ApiReturn::ok("TOTP disabled".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -350,32 +526,31 @@ pub async fn refresh_totp_codes_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     if user.id != id && !user.permissions.check(FinePermission::MANAGE_USERS) {
-        return Json(Error::NotAllowed.into());
+        return ApiJson(Error::NotAllowed.into());
     }
 
     // check totp code
     let other_user = match data.get_user_by_id(id).await {
         Ok(u) => u,
-        Err(e) => return Json(e.into()),
+        Err(e) => return ApiJson(e.into()),
     };
 
     if !data.check_totp(&other_user, &req.totp) {
-        return Json(Error::NotAllowed.into());
+        return ApiJson(Error::NotAllowed.into());
     }
 
     // ...
     let recovery_codes = DataManager::generate_totp_recovery_codes();
     match data.update_user_totp(id, &user.totp, &recovery_codes).await {
-        Ok(()) => Json(ApiReturn {
-            ok: true,
-            message: "Recovery codes refreshed".to_string(),
-            payload: Some(recovery_codes),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(()) => ApiJson(ApiReturn::ok(
+            "Recovery codes refreshed".to_string(),
+            Some(recovery_codes),
+        )),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -387,12 +562,11 @@ pub async fn has_totp_enabled_request(
     let data = &(data.read().await).0;
     let user = match data.get_user_by_username(&username).await {
         Ok(u) => u,
-        Err(e) => return Json(e.into()),
+        Err(e) => return ApiJson(e.into()),
     };
 
-    Json(ApiReturn {
-        ok: true,
-        message: "User exists".to_string(),
-        payload: Some(!user.totp.is_empty()),
-    })
+    ApiJson(ApiReturn::ok(
+        "User exists".to_string(),
+        Some(!user.totp.is_empty()),
+    ))
 }
@@ -1,8 +1,9 @@
 use crate::{
     State, get_user_from_token,
     model::{ApiReturn, Error},
+    routes::api::v1::ApiJson,
 };
-use axum::{Extension, Json, extract::Path, response::IntoResponse};
+use axum::{Extension, extract::Path, response::IntoResponse};
 use axum_extra::extract::CookieJar;
 use tetratto_core::model::auth::{FollowResult, IpBlock, Notification, UserBlock, UserFollow};
 
@@ -15,18 +16,14 @@ pub async fn follow_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     if let Ok(userfollow) = data.get_userfollow_by_initiator_receiver(user.id, id).await {
         // delete
         match data.delete_userfollow(userfollow.id, &user, false).await {
-            Ok(_) => Json(ApiReturn {
-                ok: true,
-                message: "User unfollowed".to_string(),
-                payload: (),
-            }),
-            Err(e) => Json(e.into()),
+            Ok(_) => ApiJson(ApiReturn::ok("User unfollowed".to_string(), ())),
+            Err(e) => ApiJson(e.into()),
         }
     } else {
         // create
@@ -47,23 +44,15 @@ pub async fn follow_request(
                         ))
                         .await
                     {
-                        return Json(e.into());
+                        return ApiJson(e.into());
                     };
 
-                    Json(ApiReturn {
-                        ok: true,
-                        message: "User followed".to_string(),
-                        payload: (),
-                    })
+                    ApiJson(ApiReturn::ok("User followed".to_string(), ()))
                 } else {
-                    Json(ApiReturn {
-                        ok: true,
-                        message: "Asked to follow user".to_string(),
-                        payload: (),
-                    })
+                    ApiJson(ApiReturn::ok("Asked to follow user".to_string(), ()))
                 }
             }
-            Err(e) => Json(e.into()),
+            Err(e) => ApiJson(e.into()),
         }
     }
 }
@@ -76,16 +65,12 @@ pub async fn cancel_follow_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     match data.delete_request(user.id, id, &user, true).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Follow request deleted".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("Follow request deleted".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -97,12 +82,12 @@ pub async fn accept_follow_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     // delete the request
     if let Err(e) = data.delete_request(id, user.id, &user, true).await {
-        return Json(e.into());
+        return ApiJson(e.into());
     }
 
     // create follow
@@ -122,16 +107,15 @@ pub async fn accept_follow_request(
                 ))
                 .await
             {
-                return Json(e.into());
+                return ApiJson(e.into());
             };
 
-            Json(ApiReturn {
-                ok: true,
-                message: "User follow request accepted".to_string(),
-                payload: (),
-            })
+            ApiJson(ApiReturn::ok(
+                "User follow request accepted".to_string(),
+                (),
+            ))
         }
-        Err(e) => Json(e.into()),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -144,18 +128,14 @@ pub async fn block_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     if let Ok(userblock) = data.get_userblock_by_initiator_receiver(user.id, id).await {
         // delete
         match data.delete_userblock(userblock.id, user).await {
-            Ok(_) => Json(ApiReturn {
-                ok: true,
-                message: "User unblocked".to_string(),
-                payload: (),
-            }),
-            Err(e) => Json(e.into()),
+            Ok(_) => ApiJson(ApiReturn::ok("User unblocked".to_string(), ())),
+            Err(e) => ApiJson(e.into()),
         }
     } else {
         // create
@@ -165,35 +145,23 @@ pub async fn block_request(
                 {
                     // automatically unfollow
                     match data.delete_userfollow(userfollow.id, &user, false).await {
-                        Ok(_) => Json(ApiReturn {
-                            ok: true,
-                            message: "User blocked".to_string(),
-                            payload: (),
-                        }),
-                        Err(e) => Json(e.into()),
+                        Ok(_) => ApiJson(ApiReturn::ok("User blocked".to_string(), ())),
+                        Err(e) => ApiJson(e.into()),
                     }
                 } else if let Ok(userfollow) =
                     data.get_userfollow_by_receiver_initiator(user.id, id).await
                 {
                     // automatically unfollow
                     match data.delete_userfollow(userfollow.id, &user, false).await {
-                        Ok(_) => Json(ApiReturn {
-                            ok: true,
-                            message: "User blocked".to_string(),
-                            payload: (),
-                        }),
-                        Err(e) => Json(e.into()),
+                        Ok(_) => ApiJson(ApiReturn::ok("User blocked".to_string(), ())),
+                        Err(e) => ApiJson(e.into()),
                     }
                 } else {
                     // not following user, don't do anything else
-                    Json(ApiReturn {
-                        ok: true,
-                        message: "User blocked".to_string(),
-                        payload: (),
-                    })
+                    ApiJson(ApiReturn::ok("User blocked".to_string(), ()))
                 }
             }
-            Err(e) => Json(e.into()),
+            Err(e) => ApiJson(e.into()),
         }
     }
 }
@@ -207,28 +175,20 @@ pub async fn ip_block_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     if let Ok(ipblock) = data.get_ipblock_by_initiator_receiver(user.id, &ip).await {
         // delete
         match data.delete_ipblock(ipblock.id, user).await {
-            Ok(_) => Json(ApiReturn {
-                ok: true,
-                message: "IP unblocked".to_string(),
-                payload: (),
-            }),
-            Err(e) => Json(e.into()),
+            Ok(_) => ApiJson(ApiReturn::ok("IP unblocked".to_string(), ())),
+            Err(e) => ApiJson(e.into()),
         }
     } else {
         // create
         match data.create_ipblock(IpBlock::new(user.id, ip)).await {
-            Ok(_) => Json(ApiReturn {
-                ok: true,
-                message: "IP blocked".to_string(),
-                payload: (),
-            }),
-            Err(e) => Json(e.into()),
+            Ok(_) => ApiJson(ApiReturn::ok("IP blocked".to_string(), ())),
+            Err(e) => ApiJson(e.into()),
         }
     }
 }
@@ -1,7 +1,7 @@
 use crate::{
     get_user_from_token,
     model::{ApiReturn, Error},
-    routes::api::v1::CreateUserWarning,
+    routes::api::v1::{ApiJson, CreateUserWarning},
     State,
 };
 use axum::{Extension, Json, extract::Path, response::IntoResponse};
@@ -18,23 +18,19 @@ pub async fn create_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     if !user.permissions.check(FinePermission::MANAGE_BANS) {
-        return Json(Error::NotAllowed.into());
+        return ApiJson(Error::NotAllowed.into());
     }
 
     match data
         .create_user_warning(UserWarning::new(uid, user.id, req.content))
         .await
     {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "User warning created".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("User warning created".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -47,19 +43,15 @@ pub async fn delete_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     if !user.permissions.check(FinePermission::MANAGE_WARNINGS) {
-        return Json(Error::NotAllowed.into());
+        return ApiJson(Error::NotAllowed.into());
     }
 
     match data.delete_user_warning(id, user).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "User warning deleted".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("User warning deleted".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
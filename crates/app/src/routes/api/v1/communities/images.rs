@@ -1,4 +1,4 @@
-use axum::{Extension, Json, body::Body, extract::Path, response::IntoResponse};
+use axum::{Extension, body::Body, extract::Path, response::IntoResponse};
 use axum_extra::extract::CookieJar;
 use pathbufd::{PathBufD, pathd};
 use std::fs::exists;
@@ -8,7 +8,10 @@ use crate::{
     State,
     avif::{Image, save_avif_buffer},
     get_user_from_token,
-    routes::api::v1::auth::images::{MAXIUMUM_FILE_SIZE, read_image},
+    routes::api::v1::{
+        ApiJson,
+        auth::images::{MAXIUMUM_FILE_SIZE, read_image},
+    },
 };
 
 /// Get a community's avatar image
@@ -112,12 +115,12 @@ pub async fn upload_avatar_request(
     let data = &(data.read().await).0;
     let auth_user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     let community = match data.get_community_by_id(id).await {
         Ok(c) => c,
-        Err(e) => return Json(e.into()),
+        Err(e) => return ApiJson(e.into()),
     };
 
     if auth_user.id != community.owner
@@ -125,7 +128,7 @@ pub async fn upload_avatar_request(
             .permissions
             .check(FinePermission::MANAGE_COMMUNITIES)
     {
-        return Json(Error::NotAllowed.into());
+        return ApiJson(Error::NotAllowed.into());
     }
 
     let path = pathd!(
@@ -136,7 +139,7 @@ pub async fn upload_avatar_request(
 
     // check file size
     if img.0.len() > MAXIUMUM_FILE_SIZE {
-        return Json(Error::DataTooLong("image".to_string()).into());
+        return ApiJson(Error::DataTooLong("image".to_string()).into());
     }
 
     // upload image
@@ -147,12 +150,11 @@ pub async fn upload_avatar_request(
     }
 
     match save_avif_buffer(&path, bytes) {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Avatar uploaded. It might take a bit to update".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(Error::MiscError(e.to_string()).into()),
+        Ok(_) => ApiJson(ApiReturn::ok(
+            "Avatar uploaded. It might take a bit to update".to_string(),
+            (),
+        )),
+        Err(e) => ApiJson(Error::MiscError(e.to_string()).into()),
     }
 }
 
@@ -167,12 +169,12 @@ pub async fn upload_banner_request(
     let data = &(data.read().await).0;
     let auth_user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     let community = match data.get_community_by_id(id).await {
         Ok(c) => c,
-        Err(e) => return Json(e.into()),
+        Err(e) => return ApiJson(e.into()),
     };
 
     if auth_user.id != community.owner
@@ -180,7 +182,7 @@ pub async fn upload_banner_request(
             .permissions
             .check(FinePermission::MANAGE_COMMUNITIES)
     {
-        return Json(Error::NotAllowed.into());
+        return ApiJson(Error::NotAllowed.into());
     }
 
     let path = pathd!(
@@ -191,7 +193,7 @@ pub async fn upload_banner_request(
 
     // check file size
     if img.0.len() > MAXIUMUM_FILE_SIZE {
-        return Json(Error::DataTooLong("image".to_string()).into());
+        return ApiJson(Error::DataTooLong("image".to_string()).into());
     }
 
     // upload image
@@ -202,11 +204,10 @@ pub async fn upload_banner_request(
     }
 
     match save_avif_buffer(&path, bytes) {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Banner uploaded. It might take a bit to update".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(Error::MiscError(e.to_string()).into()),
+        Ok(_) => ApiJson(ApiReturn::ok(
+            "Banner uploaded. It might take a bit to update".to_string(),
+            (),
+        )),
+        Err(e) => ApiJson(Error::MiscError(e.to_string()).into()),
     }
 }
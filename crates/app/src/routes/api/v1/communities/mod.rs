@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod communities;
 pub mod images;
 pub mod posts;
@@ -1,34 +1,34 @@
 use axum::{Extension, Json, extract::Path, response::IntoResponse};
 use axum_extra::extract::CookieJar;
-use tetratto_core::model::{ApiReturn, Error, communities::Post};
+use tetratto_core::model::{ApiReturn, Error, auth::User, communities::Post};
 
 use crate::{
     get_user_from_token,
-    routes::api::v1::{CreatePost, CreateRepost, UpdatePostContent, UpdatePostContext},
+    routes::api::v1::{ApiJson, CreatePost, CreateRepost, UpdatePostContent, UpdatePostContext},
     State,
 };
 
 pub async fn create_request(
-    jar: CookieJar,
     Extension(data): Extension<State>,
+    Extension(user): Extension<Option<User>>,
     Json(req): Json<CreatePost>,
 ) -> impl IntoResponse {
     let data = &(data.read().await).0;
-    let user = match get_user_from_token!(jar, data) {
+    let user = match user {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     let mut props = Post::new(
         req.content,
         match req.community.parse::<usize>() {
             Ok(x) => x,
-            Err(e) => return Json(Error::MiscError(e.to_string()).into()),
+            Err(_) => return ApiJson(Error::InvalidId("community".to_string()).into()),
         },
         if let Some(rt) = req.replying_to {
             match rt.parse::<usize>() {
                 Ok(x) => Some(x),
-                Err(e) => return Json(Error::MiscError(e.to_string()).into()),
+                Err(_) => return ApiJson(Error::InvalidId("post".to_string()).into()),
             }
         } else {
             None
@@ -40,30 +40,29 @@ pub async fn create_request(
         // we're answering a question!
         props.context.answering = match req.answering.parse::<usize>() {
             Ok(x) => x,
-            Err(e) => return Json(Error::MiscError(e.to_string()).into()),
+            Err(_) => return ApiJson(Error::InvalidId("question".to_string()).into()),
         };
     }
 
     match data.create_post(props).await {
-        Ok(id) => Json(ApiReturn {
-            ok: true,
-            message: "Post created".to_string(),
-            payload: Some(id.to_string()),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(id) => ApiJson(ApiReturn::ok(
+            "Post created".to_string(),
+            Some(id.to_string()),
+        )),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
 pub async fn create_repost_request(
-    jar: CookieJar,
     Extension(data): Extension<State>,
+    Extension(user): Extension<Option<User>>,
     Path(id): Path<usize>,
     Json(req): Json<CreateRepost>,
 ) -> impl IntoResponse {
     let data = &(data.read().await).0;
-    let user = match get_user_from_token!(jar, data) {
+    let user = match user {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     match data
@@ -71,19 +70,18 @@ pub async fn create_repost_request(
             req.content,
             match req.community.parse::<usize>() {
                 Ok(x) => x,
-                Err(e) => return Json(Error::MiscError(e.to_string()).into()),
+                Err(_) => return ApiJson(Error::InvalidId("community".to_string()).into()),
             },
             user.id,
             id,
         ))
         .await
     {
-        Ok(id) => Json(ApiReturn {
-            ok: true,
-            message: "Post reposted".to_string(),
-            payload: Some(id.to_string()),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(id) => ApiJson(ApiReturn::ok(
+            "Post reposted".to_string(),
+            Some(id.to_string()),
+        )),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -95,16 +93,46 @@ pub async fn delete_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     match data.delete_post(id, user).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Post deleted".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("Post deleted".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
+    }
+}
+
+pub async fn restore_request(
+    jar: CookieJar,
+    Extension(data): Extension<State>,
+    Path(id): Path<usize>,
+) -> impl IntoResponse {
+    let data = &(data.read().await).0;
+    let user = match get_user_from_token!(jar, data) {
+        Some(ua) => ua,
+        None => return ApiJson(Error::NotAllowed.into()),
+    };
+
+    match data.restore_post(id, user).await {
+        Ok(_) => ApiJson(ApiReturn::ok("Post restored".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
+    }
+}
+
+pub async fn revisions_request(
+    jar: CookieJar,
+    Extension(data): Extension<State>,
+    Path(id): Path<usize>,
+) -> impl IntoResponse {
+    let data = &(data.read().await).0;
+    let user = match get_user_from_token!(jar, data) {
+        Some(ua) => ua,
+        None => return ApiJson(Error::NotAllowed.into()),
+    };
+
+    match data.get_post_revisions(id, &user).await {
+        Ok(x) => ApiJson(ApiReturn::ok("Success".to_string(), Some(x))),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -117,16 +145,12 @@ pub async fn update_content_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     match data.update_post_content(id, user, req.content).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Post updated".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("Post updated".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -139,15 +163,11 @@ pub async fn update_context_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     match data.update_post_context(id, user, req.context).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Post updated".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("Post updated".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
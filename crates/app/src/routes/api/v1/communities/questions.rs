@@ -5,20 +5,27 @@ use axum::{
     Extension, Json,
 };
 use axum_extra::extract::CookieJar;
-use tetratto_core::model::{auth::IpBlock, communities::Question, ApiReturn, Error};
-use crate::{get_user_from_token, routes::api::v1::CreateQuestion, State};
+use tetratto_core::model::{
+    auth::{IpBlock, User},
+    communities::Question,
+    ApiReturn, Error,
+};
+use crate::{
+    get_user_from_token,
+    routes::api::v1::{ApiJson, CreateQuestion},
+    State,
+};
 
 pub async fn create_request(
-    jar: CookieJar,
     headers: HeaderMap,
     Extension(data): Extension<State>,
+    Extension(user): Extension<Option<User>>,
     Json(req): Json<CreateQuestion>,
 ) -> impl IntoResponse {
     let data = &(data.read().await).0;
-    let user = get_user_from_token!(jar, data);
 
     if req.is_global && user.is_none() {
-        return Json(Error::NotAllowed.into());
+        return ApiJson(Error::NotAllowed.into());
     }
 
     // get real ip
@@ -31,7 +38,7 @@ pub async fn create_request(
 
     // check for ip ban
     if data.get_ipban_by_ip(&real_ip).await.is_ok() {
-        return Json(Error::NotAllowed.into());
+        return ApiJson(Error::NotAllowed.into());
     }
 
     // ...
@@ -39,7 +46,7 @@ pub async fn create_request(
         if let Some(ref ua) = user { ua.id } else { 0 },
         match req.receiver.parse::<usize>() {
             Ok(x) => x,
-            Err(e) => return Json(Error::MiscError(e.to_string()).into()),
+            Err(_) => return ApiJson(Error::InvalidId("user".to_string()).into()),
         },
         req.content,
         req.is_global,
@@ -51,17 +58,16 @@ pub async fn create_request(
         props.receiver = 0;
         props.community = match req.community.parse::<usize>() {
             Ok(x) => x,
-            Err(e) => return Json(Error::MiscError(e.to_string()).into()),
+            Err(_) => return ApiJson(Error::InvalidId("community".to_string()).into()),
         }
     }
 
     match data.create_question(props).await {
-        Ok(id) => Json(ApiReturn {
-            ok: true,
-            message: "Question created".to_string(),
-            payload: Some(id.to_string()),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(id) => ApiJson(ApiReturn::ok(
+            "Question created".to_string(),
+            Some(id.to_string()),
+        )),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -73,16 +79,29 @@ pub async fn delete_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     match data.delete_question(id, &user).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Question deleted".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("Question deleted".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
+    }
+}
+
+pub async fn restore_request(
+    jar: CookieJar,
+    Extension(data): Extension<State>,
+    Path(id): Path<usize>,
+) -> impl IntoResponse {
+    let data = &(data.read().await).0;
+    let user = match get_user_from_token!(jar, data) {
+        Some(ua) => ua,
+        None => return ApiJson(Error::NotAllowed.into()),
+    };
+
+    match data.restore_question(id, &user).await {
+        Ok(_) => ApiJson(ApiReturn::ok("Question restored".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -94,13 +113,13 @@ pub async fn ip_block_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     // get question
     let question = match data.get_question_by_id(id).await {
         Ok(q) => q,
-        Err(e) => return Json(e.into()),
+        Err(e) => return ApiJson(e.into()),
     };
 
     // check for an existing ip block
@@ -109,7 +128,7 @@ pub async fn ip_block_request(
         .await
         .is_ok()
     {
-        return Json(Error::NotAllowed.into());
+        return ApiJson(Error::NotAllowed.into());
     }
 
     // create ip block
@@ -117,11 +136,7 @@ pub async fn ip_block_request(
         .create_ipblock(IpBlock::new(user.id, question.ip))
         .await
     {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "IP blocked".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("IP blocked".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
@@ -1,17 +1,27 @@
+pub mod admin;
 pub mod auth;
 pub mod communities;
 pub mod notifications;
 pub mod reactions;
 pub mod reports;
 pub mod requests;
+pub mod search;
 pub mod util;
 
 use axum::{
-    Router,
+    Extension, Json, Router,
+    extract::{self, ConnectInfo, Request},
+    http::{HeaderValue, StatusCode, header::RETRY_AFTER},
+    middleware::{Next, from_fn_with_state},
+    response::{IntoResponse, Response},
     routing::{delete, get, post},
 };
-use serde::Deserialize;
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use tetratto_core::config::RateLimitAction;
 use tetratto_core::model::{
+    ApiReturn, Error,
     communities::{
         CommunityContext, CommunityJoinAccess, CommunityReadAccess, CommunityWriteAccess,
         PostContext,
@@ -21,6 +31,8 @@ use tetratto_core::model::{
     reactions::AssetType,
 };
 
+use crate::{State, get_user_from_token};
+
 pub fn routes() -> Router {
     Router::new()
         // misc
@@ -44,6 +56,10 @@ pub fn routes() -> Router {
             "/communities/{id}",
             delete(communities::communities::delete_request),
         )
+        .route(
+            "/communities/{id}/restore",
+            post(communities::communities::restore_request),
+        )
         .route(
             "/communities/{id}/title",
             post(communities::communities::update_title_request),
@@ -81,11 +97,24 @@ pub fn routes() -> Router {
             get(communities::images::banner_request),
         )
         // posts
-        .route("/posts", post(communities::posts::create_request))
+        .route(
+            "/posts",
+            post(communities::posts::create_request)
+                .route_layer(from_fn_with_state(RateLimitAction::CreatePost, rate_limit)),
+        )
         .route("/posts/{id}", delete(communities::posts::delete_request))
+        .route(
+            "/posts/{id}/revisions",
+            get(communities::posts::revisions_request),
+        )
+        .route(
+            "/posts/{id}/restore",
+            post(communities::posts::restore_request),
+        )
         .route(
             "/posts/{id}/repost",
-            post(communities::posts::create_repost_request),
+            post(communities::posts::create_repost_request)
+                .route_layer(from_fn_with_state(RateLimitAction::CreatePost, rate_limit)),
         )
         .route(
             "/posts/{id}/content",
@@ -96,19 +125,37 @@ pub fn routes() -> Router {
             post(communities::posts::update_context_request),
         )
         // questions
-        .route("/questions", post(communities::questions::create_request))
+        .route(
+            "/questions",
+            post(communities::questions::create_request).route_layer(from_fn_with_state(
+                RateLimitAction::CreateQuestion,
+                rate_limit,
+            )),
+        )
         .route(
             "/questions/{id}",
             delete(communities::questions::delete_request),
         )
+        .route(
+            "/questions/{id}/restore",
+            post(communities::questions::restore_request),
+        )
         .route(
             "/questions/{id}/block_ip",
             post(communities::questions::ip_block_request),
         )
         // auth
         // global
-        .route("/auth/register", post(auth::register_request))
-        .route("/auth/login", post(auth::login_request))
+        .route(
+            "/auth/register",
+            post(auth::register_request)
+                .route_layer(from_fn_with_state(RateLimitAction::Register, rate_limit)),
+        )
+        .route(
+            "/auth/login",
+            post(auth::login_request)
+                .route_layer(from_fn_with_state(RateLimitAction::Login, rate_limit)),
+        )
         .route("/auth/logout", post(auth::logout_request))
         .route("/auth/token", get(auth::set_token_request))
         .route(
@@ -156,6 +203,22 @@ pub fn routes() -> Router {
             "/auth/user/{id}/tokens",
             post(auth::profile::update_user_tokens_request),
         )
+        .route(
+            "/auth/user/{id}/sessions",
+            get(auth::profile::get_user_sessions_request),
+        )
+        .route(
+            "/auth/user/{id}/sessions",
+            delete(auth::profile::delete_other_sessions_request),
+        )
+        .route(
+            "/auth/user/{id}/sessions/{session}",
+            delete(auth::profile::delete_session_request),
+        )
+        .route(
+            "/auth/user/{id}/sessions/{session}/label",
+            post(auth::profile::update_session_label_request),
+        )
         .route(
             "/auth/user/{id}/verified",
             post(auth::profile::update_user_is_verified_request),
@@ -177,6 +240,12 @@ pub fn routes() -> Router {
             get(auth::profile::has_totp_enabled_request),
         )
         .route("/auth/user/me/seen", post(auth::profile::seen_request))
+        .route(
+            "/auth/user/me/export",
+            post(auth::profile::create_export_request),
+        )
+        .route("/auth/user/me/export", get(auth::profile::export_request))
+        .route("/auth/user/import", post(auth::profile::import_request))
         .route("/auth/user/find/{id}", get(auth::profile::redirect_from_id))
         .route(
             "/auth/user/find_by_ip/{ip}",
@@ -220,7 +289,13 @@ pub fn routes() -> Router {
         .route("/bans/{ip}", post(auth::ipbans::create_request))
         .route("/bans/id/{id}", delete(auth::ipbans::delete_request))
         // reports
-        .route("/reports", post(reports::create_request))
+        .route(
+            "/reports",
+            post(reports::create_request).route_layer(from_fn_with_state(
+                RateLimitAction::CreateReport,
+                rate_limit,
+            )),
+        )
         .route("/reports/{id}", delete(reports::delete_request))
         // requests
         .route(
@@ -228,6 +303,84 @@ pub fn routes() -> Router {
             delete(requests::delete_request),
         )
         .route("/requests/my", delete(requests::delete_all_request))
+        // admin
+        .route("/admin/counters", post(admin::reconcile_counters_request))
+        .route(
+            "/admin/counters/{asset_type}/{id}",
+            post(admin::reconcile_asset_counters_request),
+        )
+        // search
+        .route("/search", get(search::search_request))
+}
+
+/// An [`ApiReturn`] sent as JSON, with the HTTP status matching its error code.
+pub struct ApiJson<T: Serialize>(pub ApiReturn<T>);
+
+impl<T: Serialize> IntoResponse for ApiJson<T> {
+    fn into_response(self) -> Response {
+        let status = match self.0.code {
+            Some(ref code) => StatusCode::from_u16(Error::status_of(code))
+                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            None => StatusCode::OK,
+        };
+
+        (status, Json(self.0)).into_response()
+    }
+}
+
+/// Reject requests once the user (or IP) making them has reached the rate limit
+/// of the given action, telling them when to try again with `Retry-After`.
+///
+/// The user making the request is passed on to the handler as an
+/// `Extension<Option<User>>`, so it doesn't have to be looked up again.
+async fn rate_limit(
+    extract::State(action): extract::State<RateLimitAction>,
+    jar: CookieJar,
+    Extension(data): Extension<State>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let (res, user) = {
+        let data = &(data.read().await).0;
+        let user = get_user_from_token!(jar, data);
+
+        let user_key = match user {
+            Some(ref ua) => format!("user:{}", ua.id),
+            None => String::new(),
+        };
+
+        // fall back to the address of the connection if we aren't behind a proxy
+        // which sets the real IP header
+        let ip = match req
+            .headers()
+            .get(data.0.security.real_ip_header.to_owned())
+            .and_then(|x| x.to_str().ok())
+        {
+            Some(ip) if !ip.is_empty() => format!("ip:{ip}"),
+            _ => match req.extensions().get::<ConnectInfo<SocketAddr>>() {
+                Some(ConnectInfo(addr)) => format!("ip:{}", addr.ip()),
+                None => String::new(),
+            },
+        };
+
+        (data.check_rate_limit(action, &[&user_key, &ip]).await, user)
+    };
+
+    match res {
+        Err(Error::RateLimited(seconds)) => {
+            let mut res =
+                ApiJson(ApiReturn::<()>::from(Error::RateLimited(seconds))).into_response();
+
+            res.headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(seconds));
+
+            res
+        }
+        _ => {
+            req.extensions_mut().insert(user);
+            next.run(req).await
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -316,6 +469,11 @@ pub struct CreateReport {
     pub asset_type: AssetType,
 }
 
+#[derive(Deserialize)]
+pub struct UpdateSessionLabel {
+    pub label: String,
+}
+
 #[derive(Deserialize)]
 pub struct UpdateUserPassword {
     pub from: String,
@@ -1,4 +1,4 @@
-use super::UpdateNotificationRead;
+use super::{ApiJson, UpdateNotificationRead};
 use crate::{State, get_user_from_token};
 use axum::{Extension, Json, extract::Path, response::IntoResponse};
 use axum_extra::extract::CookieJar;
@@ -12,16 +12,12 @@ pub async fn delete_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     match data.delete_notification(id, &user).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Notification deleted".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("Notification deleted".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -32,16 +28,12 @@ pub async fn delete_all_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     match data.delete_all_notifications(&user).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Notifications cleared".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("Notifications cleared".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -54,15 +46,11 @@ pub async fn update_read_status_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     match data.update_notification_read(id, req.read, &user).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Notification updated".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("Notification updated".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
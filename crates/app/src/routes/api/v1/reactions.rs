@@ -1,4 +1,7 @@
-use crate::{State, get_user_from_token, routes::api::v1::CreateReaction};
+use crate::{
+    State, get_user_from_token,
+    routes::api::v1::{ApiJson, CreateReaction},
+};
 use axum::{Extension, Json, extract::Path, response::IntoResponse};
 use axum_extra::extract::CookieJar;
 use tetratto_core::model::{ApiReturn, Error, reactions::Reaction};
@@ -11,16 +14,12 @@ pub async fn get_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     match data.get_reaction_by_owner_asset(user.id, id).await {
-        Ok(r) => Json(ApiReturn {
-            ok: true,
-            message: "Reaction exists".to_string(),
-            payload: Some(r),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(r) => ApiJson(ApiReturn::ok("Reaction exists".to_string(), Some(r))),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -32,12 +31,12 @@ pub async fn create_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     let asset_id = match req.asset.parse::<usize>() {
         Ok(n) => n,
-        Err(e) => return Json(Error::MiscError(e.to_string()).into()),
+        Err(e) => return ApiJson(Error::MiscError(e.to_string()).into()),
     };
 
     // check for existing reaction
@@ -47,14 +46,10 @@ pub async fn create_request(
                 // if we're trying to create a reaction of a DIFFERENT TYPE, then
                 // we don't need to return here
                 if r.is_like == req.is_like {
-                    return Json(ApiReturn {
-                        ok: true,
-                        message: "Reaction removed".to_string(),
-                        payload: (),
-                    });
+                    return ApiJson(ApiReturn::ok("Reaction removed".to_string(), ()));
                 }
             }
-            Err(e) => return Json(e.into()),
+            Err(e) => return ApiJson(e.into()),
         };
     }
 
@@ -66,12 +61,8 @@ pub async fn create_request(
         )
         .await
     {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Reaction created".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("Reaction created".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -83,20 +74,16 @@ pub async fn delete_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     let reaction = match data.get_reaction_by_owner_asset(user.id, id).await {
         Ok(r) => r,
-        Err(e) => return Json(e.into()),
+        Err(e) => return ApiJson(e.into()),
     };
 
     match data.delete_reaction(reaction.id, &user).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Reaction deleted".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("Reaction deleted".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
@@ -1,35 +1,31 @@
-use super::CreateReport;
+use super::{ApiJson, CreateReport};
 use crate::{State, get_user_from_token};
 use axum::{Extension, Json, extract::Path, response::IntoResponse};
 use axum_extra::extract::CookieJar;
-use tetratto_core::model::{ApiReturn, Error, moderation::Report};
+use tetratto_core::model::{ApiReturn, Error, auth::User, moderation::Report};
 
 pub async fn create_request(
-    jar: CookieJar,
     Extension(data): Extension<State>,
+    Extension(user): Extension<Option<User>>,
     Json(req): Json<CreateReport>,
 ) -> impl IntoResponse {
     let data = &(data.read().await).0;
-    let user = match get_user_from_token!(jar, data) {
+    let user = match user {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     let asset_id = match req.asset.parse::<usize>() {
         Ok(n) => n,
-        Err(e) => return Json(Error::MiscError(e.to_string()).into()),
+        Err(e) => return ApiJson(Error::MiscError(e.to_string()).into()),
     };
 
     match data
         .create_report(Report::new(user.id, req.content, asset_id, req.asset_type))
         .await
     {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Report created".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("Report created".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -41,15 +37,11 @@ pub async fn delete_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     match data.delete_report(id, user).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Report deleted".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("Report deleted".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
@@ -1,5 +1,6 @@
+use super::ApiJson;
 use crate::{State, get_user_from_token};
-use axum::{Extension, Json, extract::Path, response::IntoResponse};
+use axum::{Extension, extract::Path, response::IntoResponse};
 use axum_extra::extract::CookieJar;
 use tetratto_core::model::{ApiReturn, Error};
 
@@ -11,16 +12,12 @@ pub async fn delete_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     match data.delete_request(id, linked_asset, &user, false).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Request deleted".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("Request deleted".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
 
@@ -31,15 +28,11 @@ pub async fn delete_all_request(
     let data = &(data.read().await).0;
     let user = match get_user_from_token!(jar, data) {
         Some(ua) => ua,
-        None => return Json(Error::NotAllowed.into()),
+        None => return ApiJson(Error::NotAllowed.into()),
     };
 
     match data.delete_all_requests(&user).await {
-        Ok(_) => Json(ApiReturn {
-            ok: true,
-            message: "Requests cleared".to_string(),
-            payload: (),
-        }),
-        Err(e) => Json(e.into()),
+        Ok(_) => ApiJson(ApiReturn::ok("Requests cleared".to_string(), ())),
+        Err(e) => ApiJson(e.into()),
     }
 }
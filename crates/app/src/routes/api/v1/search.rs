@@ -0,0 +1,66 @@
+use super::ApiJson;
+use crate::{State, get_user_from_token};
+use axum::{Extension, extract::Query, response::IntoResponse};
+use axum_extra::extract::CookieJar;
+use serde::Deserialize;
+use tetratto_core::model::{
+    ApiReturn, Error,
+    pagination::Cursor,
+    search::{SearchQuery, SearchResults, SearchType},
+};
+
+#[derive(Deserialize)]
+pub struct SearchProps {
+    #[serde(default)]
+    pub q: String,
+    #[serde(default, rename = "type")]
+    pub search_type: SearchType,
+    #[serde(default)]
+    pub cursor: Option<Cursor>,
+}
+
+/// Search the content of posts or questions.
+pub async fn search_request(
+    jar: CookieJar,
+    Extension(data): Extension<State>,
+    Query(props): Query<SearchProps>,
+) -> impl IntoResponse {
+    let data = &(data.read().await).0;
+    let user = get_user_from_token!(jar, data);
+
+    let query = SearchQuery::parse(&props.q);
+    if query.is_empty() {
+        return ApiJson(Error::MiscError("Search query is empty".to_string()).into());
+    }
+
+    let mut results = SearchResults::default();
+    match props.search_type {
+        SearchType::Posts => {
+            let (posts, next) = match data.search_posts(&query, &user, 12, props.cursor).await {
+                Ok(p) => p,
+                Err(e) => return ApiJson(e.into()),
+            };
+
+            // drop posts from private profiles the user can't see
+            match data
+                .fill_posts_with_community(posts, user.as_ref().map(|x| x.id).unwrap_or(0))
+                .await
+            {
+                Ok(p) => results.posts = p.into_iter().map(|x| x.0).collect(),
+                Err(e) => return ApiJson(e.into()),
+            }
+
+            results.next = next;
+        }
+        SearchType::Questions => match data.search_questions(&query, &user, 12, props.cursor).await
+        {
+            Ok((q, next)) => {
+                results.questions = q;
+                results.next = next;
+            }
+            Err(e) => return ApiJson(e.into()),
+        },
+    }
+
+    ApiJson(ApiReturn::ok("Search results".to_string(), results))
+}
@@ -1,4 +1,4 @@
-use super::{render_error, PaginatedQuery, SearchedQuery};
+use super::{render_error, CursorQuery, PaginatedQuery, SearchedQuery};
 use crate::{assets::initial_context, get_lang, get_user_from_token, sanitize::clean_context, State};
 use axum::{
     Extension,
@@ -270,6 +270,7 @@ pub async fn create_post_request(
     ))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn community_context(
     context: &mut Context,
     community: &Community,
@@ -299,7 +300,7 @@ pub fn community_context(
 pub async fn feed_request(
     jar: CookieJar,
     Path(title): Path<String>,
-    Query(props): Query<PaginatedQuery>,
+    Query(props): Query<CursorQuery>,
     Extension(data): Extension<State>,
 ) -> impl IntoResponse {
     let data = data.read().await;
@@ -327,13 +328,13 @@ pub async fn feed_request(
     let (can_read, _) = check_permissions!(community, jar, data, user);
 
     // ...
-    let feed = match data
+    let (feed, next) = match data
         .0
-        .get_posts_by_community(community.id, 12, props.page)
+        .get_posts_by_community(community.id, 12, props.cursor)
         .await
     {
-        Ok(p) => match data.0.fill_posts(p).await {
-            Ok(p) => p,
+        Ok((p, next)) => match data.0.fill_posts(p).await {
+            Ok(p) => (p, next),
             Err(e) => return Err(Html(render_error(e, &jar, &data, &user).await)),
         },
         Err(e) => return Err(Html(render_error(e, &jar, &data, &user).await)),
@@ -364,7 +365,8 @@ pub async fn feed_request(
 
     context.insert("feed", &feed);
     context.insert("pinned", &pinned);
-    context.insert("page", &props.page);
+    context.insert("cursor", &props.cursor);
+    context.insert("next", &next);
     community_context(
         &mut context,
         &community,
@@ -389,7 +391,7 @@ pub async fn feed_request(
 pub async fn questions_request(
     jar: CookieJar,
     Path(title): Path<String>,
-    Query(props): Query<PaginatedQuery>,
+    Query(props): Query<CursorQuery>,
     Extension(data): Extension<State>,
 ) -> impl IntoResponse {
     let data = data.read().await;
@@ -423,13 +425,13 @@ pub async fn questions_request(
     let (can_read, _) = check_permissions!(community, jar, data, user);
 
     // ...
-    let feed = match data
+    let (feed, next) = match data
         .0
-        .get_questions_by_community(community.id, 12, props.page)
+        .get_questions_by_community(community.id, 12, props.cursor)
         .await
     {
-        Ok(p) => match data.0.fill_questions(p).await {
-            Ok(p) => p,
+        Ok((p, next)) => match data.0.fill_questions(p).await {
+            Ok(p) => (p, next),
             Err(e) => return Err(Html(render_error(e, &jar, &data, &user).await)),
         },
         Err(e) => return Err(Html(render_error(e, &jar, &data, &user).await)),
@@ -451,7 +453,8 @@ pub async fn questions_request(
     ) = community_context_bools!(data, user, community);
 
     context.insert("feed", &feed);
-    context.insert("page", &props.page);
+    context.insert("cursor", &props.cursor);
+    context.insert("next", &next);
     community_context(
         &mut context,
         &community,
@@ -535,7 +538,7 @@ pub async fn settings_request(
 pub async fn post_request(
     jar: CookieJar,
     Path(id): Path<usize>,
-    Query(props): Query<PaginatedQuery>,
+    Query(props): Query<CursorQuery>,
     Extension(data): Extension<State>,
 ) -> impl IntoResponse {
     let data = data.read().await;
@@ -570,9 +573,9 @@ pub async fn post_request(
     }
 
     // ...
-    let feed = match data.0.get_post_comments(post.id, 12, props.page).await {
-        Ok(p) => match data.0.fill_posts(p).await {
-            Ok(p) => p,
+    let (feed, next) = match data.0.get_post_comments(post.id, 12, props.cursor).await {
+        Ok((p, next)) => match data.0.fill_posts(p).await {
+            Ok(p) => (p, next),
             Err(e) => return Err(Html(render_error(e, &jar, &data, &user).await)),
         },
         Err(e) => return Err(Html(render_error(e, &jar, &data, &user).await)),
@@ -597,7 +600,8 @@ pub async fn post_request(
     context.insert("reposting", &reposting);
     context.insert("question", &question);
     context.insert("replies", &feed);
-    context.insert("page", &props.page);
+    context.insert("cursor", &props.cursor);
+    context.insert("next", &next);
     context.insert(
         "owner",
         &data
@@ -726,7 +730,7 @@ pub async fn members_request(
 pub async fn question_request(
     jar: CookieJar,
     Path(id): Path<usize>,
-    Query(props): Query<PaginatedQuery>,
+    Query(props): Query<CursorQuery>,
     Extension(data): Extension<State>,
 ) -> impl IntoResponse {
     let data = data.read().await;
@@ -761,13 +765,13 @@ pub async fn question_request(
     }
 
     // ...
-    let feed = match data
+    let (feed, next) = match data
         .0
-        .get_posts_by_question(question.id, 12, props.page)
+        .get_posts_by_question(question.id, 12, props.cursor)
         .await
     {
-        Ok(p) => match data.0.fill_posts(p).await {
-            Ok(p) => p,
+        Ok((p, next)) => match data.0.fill_posts(p).await {
+            Ok(p) => (p, next),
             Err(e) => return Err(Html(render_error(e, &jar, &data, &user).await)),
         },
         Err(e) => return Err(Html(render_error(e, &jar, &data, &user).await)),
@@ -790,7 +794,8 @@ pub async fn question_request(
 
     context.insert("question", &question);
     context.insert("replies", &feed);
-    context.insert("page", &props.page);
+    context.insert("cursor", &props.cursor);
+    context.insert("next", &next);
     context.insert(
         "owner",
         &data
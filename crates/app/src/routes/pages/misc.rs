@@ -1,4 +1,4 @@
-use super::{PaginatedQuery, render_error};
+use super::{ContentSearchQuery, CursorQuery, render_error};
 use crate::{State, assets::initial_context, get_lang, get_user_from_token};
 use axum::{
     extract::{Path, Query},
@@ -6,7 +6,11 @@ use axum::{
     Extension,
 };
 use axum_extra::extract::CookieJar;
-use tetratto_core::model::{requests::ActionType, Error};
+use tetratto_core::model::{
+    requests::ActionType,
+    search::{SearchQuery, SearchType},
+    Error,
+};
 use std::fs::read_to_string;
 use pathbufd::PathBufD;
 
@@ -28,7 +32,7 @@ pub async fn not_found(jar: CookieJar, Extension(data): Extension<State>) -> imp
 pub async fn index_request(
     jar: CookieJar,
     Extension(data): Extension<State>,
-    Query(req): Query<PaginatedQuery>,
+    Query(req): Query<CursorQuery>,
 ) -> impl IntoResponse {
     let data = data.read().await;
     let user = match get_user_from_token!(jar, data.0) {
@@ -42,13 +46,13 @@ pub async fn index_request(
         }
     };
 
-    let list = match data
+    let (list, next) = match data
         .0
-        .get_posts_from_user_communities(user.id, 12, req.page)
+        .get_posts_from_user_communities(user.id, 12, req.cursor)
         .await
     {
-        Ok(l) => match data.0.fill_posts_with_community(l, user.id).await {
-            Ok(l) => l,
+        Ok((l, next)) => match data.0.fill_posts_with_community(l, user.id).await {
+            Ok(l) => (l, next),
             Err(e) => return Html(render_error(e, &jar, &data, &Some(user)).await),
         },
         Err(e) => return Html(render_error(e, &jar, &data, &Some(user)).await),
@@ -58,7 +62,8 @@ pub async fn index_request(
     let mut context = initial_context(&data.0.0, lang, &Some(user)).await;
 
     context.insert("list", &list);
-    context.insert("page", &req.page);
+    context.insert("cursor", &req.cursor);
+    context.insert("next", &next);
     Html(data.1.render("timelines/home.html", &context).unwrap())
 }
 
@@ -66,18 +71,18 @@ pub async fn index_request(
 pub async fn popular_request(
     jar: CookieJar,
     Extension(data): Extension<State>,
-    Query(req): Query<PaginatedQuery>,
+    Query(req): Query<CursorQuery>,
 ) -> impl IntoResponse {
     let data = data.read().await;
     let user = get_user_from_token!(jar, data.0);
 
-    let list = match data.0.get_popular_posts(12, req.page, 604_800_000).await {
-        Ok(l) => match data
+    let (list, next) = match data.0.get_popular_posts(12, req.cursor, 604_800_000).await {
+        Ok((l, next)) => match data
             .0
             .fill_posts_with_community(l, if let Some(ref ua) = user { ua.id } else { 0 })
             .await
         {
-            Ok(l) => l,
+            Ok(l) => (l, next),
             Err(e) => return Html(render_error(e, &jar, &data, &user).await),
         },
         Err(e) => return Html(render_error(e, &jar, &data, &user).await),
@@ -87,7 +92,8 @@ pub async fn popular_request(
     let mut context = initial_context(&data.0.0, lang, &user).await;
 
     context.insert("list", &list);
-    context.insert("page", &req.page);
+    context.insert("cursor", &req.cursor);
+    context.insert("next", &next);
     Html(data.1.render("timelines/popular.html", &context).unwrap())
 }
 
@@ -95,7 +101,7 @@ pub async fn popular_request(
 pub async fn following_request(
     jar: CookieJar,
     Extension(data): Extension<State>,
-    Query(req): Query<PaginatedQuery>,
+    Query(req): Query<CursorQuery>,
 ) -> impl IntoResponse {
     let data = data.read().await;
     let user = match get_user_from_token!(jar, data.0) {
@@ -107,13 +113,13 @@ pub async fn following_request(
         }
     };
 
-    let list = match data
+    let (list, next) = match data
         .0
-        .get_posts_from_user_following(user.id, 12, req.page)
+        .get_posts_from_user_following(user.id, 12, req.cursor)
         .await
     {
-        Ok(l) => match data.0.fill_posts_with_community(l, user.id).await {
-            Ok(l) => l,
+        Ok((l, next)) => match data.0.fill_posts_with_community(l, user.id).await {
+            Ok(l) => (l, next),
             Err(e) => return Err(Html(render_error(e, &jar, &data, &Some(user)).await)),
         },
         Err(e) => return Err(Html(render_error(e, &jar, &data, &Some(user)).await)),
@@ -123,7 +129,8 @@ pub async fn following_request(
     let mut context = initial_context(&data.0.0, lang, &Some(user)).await;
 
     context.insert("list", &list);
-    context.insert("page", &req.page);
+    context.insert("cursor", &req.cursor);
+    context.insert("next", &next);
     Ok(Html(
         data.1.render("timelines/following.html", &context).unwrap(),
     ))
@@ -133,18 +140,18 @@ pub async fn following_request(
 pub async fn all_request(
     jar: CookieJar,
     Extension(data): Extension<State>,
-    Query(req): Query<PaginatedQuery>,
+    Query(req): Query<CursorQuery>,
 ) -> impl IntoResponse {
     let data = data.read().await;
     let user = get_user_from_token!(jar, data.0);
 
-    let list = match data.0.get_latest_posts(12, req.page).await {
-        Ok(l) => match data
+    let (list, next) = match data.0.get_latest_posts(12, req.cursor).await {
+        Ok((l, next)) => match data
             .0
             .fill_posts_with_community(l, if let Some(ref ua) = user { ua.id } else { 0 })
             .await
         {
-            Ok(l) => l,
+            Ok(l) => (l, next),
             Err(e) => return Html(render_error(e, &jar, &data, &user).await),
         },
         Err(e) => return Html(render_error(e, &jar, &data, &user).await),
@@ -154,7 +161,8 @@ pub async fn all_request(
     let mut context = initial_context(&data.0.0, lang, &user).await;
 
     context.insert("list", &list);
-    context.insert("page", &req.page);
+    context.insert("cursor", &req.cursor);
+    context.insert("next", &next);
     Html(data.1.render("timelines/all.html", &context).unwrap())
 }
 
@@ -162,7 +170,7 @@ pub async fn all_request(
 pub async fn index_questions_request(
     jar: CookieJar,
     Extension(data): Extension<State>,
-    Query(req): Query<PaginatedQuery>,
+    Query(req): Query<CursorQuery>,
 ) -> impl IntoResponse {
     let data = data.read().await;
     let user = match get_user_from_token!(jar, data.0) {
@@ -172,13 +180,13 @@ pub async fn index_questions_request(
         }
     };
 
-    let list = match data
+    let (list, next) = match data
         .0
-        .get_questions_from_user_communities(user.id, 12, req.page)
+        .get_questions_from_user_communities(user.id, 12, req.cursor)
         .await
     {
-        Ok(l) => match data.0.fill_questions(l).await {
-            Ok(l) => l,
+        Ok((l, next)) => match data.0.fill_questions(l).await {
+            Ok(l) => (l, next),
             Err(e) => return Html(render_error(e, &jar, &data, &Some(user)).await),
         },
         Err(e) => return Html(render_error(e, &jar, &data, &Some(user)).await),
@@ -188,7 +196,8 @@ pub async fn index_questions_request(
     let mut context = initial_context(&data.0.0, lang, &Some(user)).await;
 
     context.insert("list", &list);
-    context.insert("page", &req.page);
+    context.insert("cursor", &req.cursor);
+    context.insert("next", &next);
     Html(
         data.1
             .render("timelines/home_questions.html", &context)
@@ -200,7 +209,7 @@ pub async fn index_questions_request(
 pub async fn popular_questions_request(
     jar: CookieJar,
     Extension(data): Extension<State>,
-    Query(req): Query<PaginatedQuery>,
+    Query(req): Query<CursorQuery>,
 ) -> impl IntoResponse {
     let data = data.read().await;
     let user = match get_user_from_token!(jar, data.0) {
@@ -210,13 +219,13 @@ pub async fn popular_questions_request(
         }
     };
 
-    let list = match data
+    let (list, next) = match data
         .0
-        .get_popular_global_questions(12, req.page, 604_800_000)
+        .get_popular_global_questions(12, req.cursor, 604_800_000)
         .await
     {
-        Ok(l) => match data.0.fill_questions(l).await {
-            Ok(l) => l,
+        Ok((l, next)) => match data.0.fill_questions(l).await {
+            Ok(l) => (l, next),
             Err(e) => return Html(render_error(e, &jar, &data, &Some(user)).await),
         },
         Err(e) => return Html(render_error(e, &jar, &data, &Some(user)).await),
@@ -226,7 +235,8 @@ pub async fn popular_questions_request(
     let mut context = initial_context(&data.0.0, lang, &Some(user)).await;
 
     context.insert("list", &list);
-    context.insert("page", &req.page);
+    context.insert("cursor", &req.cursor);
+    context.insert("next", &next);
     Html(
         data.1
             .render("timelines/popular_questions.html", &context)
@@ -238,7 +248,7 @@ pub async fn popular_questions_request(
 pub async fn following_questions_request(
     jar: CookieJar,
     Extension(data): Extension<State>,
-    Query(req): Query<PaginatedQuery>,
+    Query(req): Query<CursorQuery>,
 ) -> impl IntoResponse {
     let data = data.read().await;
     let user = match get_user_from_token!(jar, data.0) {
@@ -250,13 +260,13 @@ pub async fn following_questions_request(
         }
     };
 
-    let list = match data
+    let (list, next) = match data
         .0
-        .get_questions_from_user_following(user.id, 12, req.page)
+        .get_questions_from_user_following(user.id, 12, req.cursor)
         .await
     {
-        Ok(l) => match data.0.fill_questions(l).await {
-            Ok(l) => l,
+        Ok((l, next)) => match data.0.fill_questions(l).await {
+            Ok(l) => (l, next),
             Err(e) => return Err(Html(render_error(e, &jar, &data, &Some(user)).await)),
         },
         Err(e) => return Err(Html(render_error(e, &jar, &data, &Some(user)).await)),
@@ -266,7 +276,8 @@ pub async fn following_questions_request(
     let mut context = initial_context(&data.0.0, lang, &Some(user)).await;
 
     context.insert("list", &list);
-    context.insert("page", &req.page);
+    context.insert("cursor", &req.cursor);
+    context.insert("next", &next);
     Ok(Html(
         data.1
             .render("timelines/following_questions.html", &context)
@@ -278,14 +289,14 @@ pub async fn following_questions_request(
 pub async fn all_questions_request(
     jar: CookieJar,
     Extension(data): Extension<State>,
-    Query(req): Query<PaginatedQuery>,
+    Query(req): Query<CursorQuery>,
 ) -> impl IntoResponse {
     let data = data.read().await;
     let user = get_user_from_token!(jar, data.0);
 
-    let list = match data.0.get_latest_global_questions(12, req.page).await {
-        Ok(l) => match data.0.fill_questions(l).await {
-            Ok(l) => l,
+    let (list, next) = match data.0.get_latest_global_questions(12, req.cursor).await {
+        Ok((l, next)) => match data.0.fill_questions(l).await {
+            Ok(l) => (l, next),
             Err(e) => return Html(render_error(e, &jar, &data, &user).await),
         },
         Err(e) => return Html(render_error(e, &jar, &data, &user).await),
@@ -295,7 +306,8 @@ pub async fn all_questions_request(
     let mut context = initial_context(&data.0.0, lang, &user).await;
 
     context.insert("list", &list);
-    context.insert("page", &req.page);
+    context.insert("cursor", &req.cursor);
+    context.insert("next", &next);
     Html(
         data.1
             .render("timelines/all_questions.html", &context)
@@ -303,10 +315,64 @@ pub async fn all_questions_request(
     )
 }
 
+/// `/search`
+pub async fn search_request(
+    jar: CookieJar,
+    Extension(data): Extension<State>,
+    Query(req): Query<ContentSearchQuery>,
+) -> impl IntoResponse {
+    let data = data.read().await;
+    let user = get_user_from_token!(jar, data.0);
+
+    let query = SearchQuery::parse(&req.q);
+    let lang = get_lang!(jar, data.0);
+    let mut context = initial_context(&data.0.0, lang, &user).await;
+
+    if !query.is_empty() {
+        match req.search_type {
+            SearchType::Posts => {
+                let (list, next) = match data.0.search_posts(&query, &user, 12, req.cursor).await {
+                    Ok((l, next)) => match data
+                        .0
+                        .fill_posts_with_community(l, user.as_ref().map(|x| x.id).unwrap_or(0))
+                        .await
+                    {
+                        Ok(l) => (l, next),
+                        Err(e) => return Html(render_error(e, &jar, &data, &user).await),
+                    },
+                    Err(e) => return Html(render_error(e, &jar, &data, &user).await),
+                };
+
+                context.insert("list", &list);
+                context.insert("next", &next);
+            }
+            SearchType::Questions => {
+                let (list, next) =
+                    match data.0.search_questions(&query, &user, 12, req.cursor).await {
+                        Ok((l, next)) => match data.0.fill_questions(l).await {
+                            Ok(l) => (l, next),
+                            Err(e) => return Html(render_error(e, &jar, &data, &user).await),
+                        },
+                        Err(e) => return Html(render_error(e, &jar, &data, &user).await),
+                    };
+
+                context.insert("list", &list);
+                context.insert("next", &next);
+            }
+        }
+    }
+
+    context.insert("q", &req.q);
+    context.insert("search_type", &req.search_type);
+    context.insert("cursor", &req.cursor);
+    Html(data.1.render("misc/search.html", &context).unwrap())
+}
+
 /// `/notifs`
 pub async fn notifications_request(
     jar: CookieJar,
     Extension(data): Extension<State>,
+    Query(req): Query<CursorQuery>,
 ) -> impl IntoResponse {
     let data = data.read().await;
     let user = match get_user_from_token!(jar, data.0) {
@@ -318,7 +384,11 @@ pub async fn notifications_request(
         }
     };
 
-    let notifications = match data.0.get_notifications_by_owner(user.id).await {
+    let (notifications, next) = match data
+        .0
+        .get_notifications_by_owner(user.id, 12, req.cursor)
+        .await
+    {
         Ok(p) => p,
         Err(e) => return Err(Html(render_error(e, &jar, &data, &Some(user)).await)),
     };
@@ -326,6 +396,8 @@ pub async fn notifications_request(
     let lang = get_lang!(jar, data.0);
     let mut context = initial_context(&data.0.0, lang, &Some(user)).await;
     context.insert("notifications", &notifications);
+    context.insert("cursor", &req.cursor);
+    context.insert("next", &next);
 
     // return
     Ok(Html(
@@ -9,7 +9,7 @@ use axum_extra::extract::CookieJar;
 use serde::Deserialize;
 use tetratto_core::{
     DataManager,
-    model::{Error, auth::User},
+    model::{Error, auth::User, pagination::Cursor, search::SearchType},
 };
 
 use crate::{assets::initial_context, get_lang};
@@ -32,11 +32,16 @@ pub fn routes() -> Router {
         // misc
         .route("/notifs", get(misc::notifications_request))
         .route("/requests", get(misc::requests_request))
+        .route("/search", get(misc::search_request))
         .route("/doc/{*file_name}", get(misc::markdown_document_request))
         .fallback_service(get(misc::not_found))
         // mod
         .route("/mod_panel/audit_log", get(mod_panel::audit_log_request))
         .route("/mod_panel/reports", get(mod_panel::reports_request))
+        .route(
+            "/mod_panel/post_revisions/{id}",
+            get(mod_panel::post_revisions_request),
+        )
         .route(
             "/mod_panel/file_report",
             get(mod_panel::file_report_request),
@@ -97,10 +102,16 @@ pub struct PaginatedQuery {
     pub page: usize,
 }
 
+#[derive(Deserialize)]
+pub struct CursorQuery {
+    #[serde(default)]
+    pub cursor: Option<Cursor>,
+}
+
 #[derive(Deserialize)]
 pub struct ProfileQuery {
     #[serde(default)]
-    pub page: usize,
+    pub cursor: Option<Cursor>,
     #[serde(default)]
     pub warning: bool,
 }
@@ -112,3 +123,13 @@ pub struct SearchedQuery {
     #[serde(default)]
     pub page: usize,
 }
+
+#[derive(Deserialize)]
+pub struct ContentSearchQuery {
+    #[serde(default)]
+    pub q: String,
+    #[serde(default, rename = "type")]
+    pub search_type: SearchType,
+    #[serde(default)]
+    pub cursor: Option<Cursor>,
+}
@@ -81,6 +81,41 @@ pub async fn reports_request(
     Ok(Html(data.1.render("mod/reports.html", &context).unwrap()))
 }
 
+/// `/mod_panel/post_revisions/{id}`
+pub async fn post_revisions_request(
+    jar: CookieJar,
+    Extension(data): Extension<State>,
+    Path(id): Path<usize>,
+) -> impl IntoResponse {
+    let data = data.read().await;
+    let user = match get_user_from_token!(jar, data.0) {
+        Some(ua) => ua,
+        None => {
+            return Err(Html(
+                render_error(Error::NotAllowed, &jar, &data, &None).await,
+            ));
+        }
+    };
+
+    let items = match data.0.get_post_revisions(id, &user).await {
+        Ok(p) => match data.0.fill_post_revisions(p).await {
+            Ok(p) => p,
+            Err(e) => return Err(Html(render_error(e, &jar, &data, &Some(user)).await)),
+        },
+        Err(e) => return Err(Html(render_error(e, &jar, &data, &Some(user)).await)),
+    };
+
+    let lang = get_lang!(jar, data.0);
+    let mut context = initial_context(&data.0.0, lang, &Some(user)).await;
+    context.insert("items", &items);
+    context.insert("post", &id);
+
+    // return
+    Ok(Html(
+        data.1.render("mod/post_revisions.html", &context).unwrap(),
+    ))
+}
+
 #[derive(Deserialize)]
 pub struct FileReportQuery {
     pub asset: String,
@@ -1,4 +1,4 @@
-use super::{render_error, PaginatedQuery, ProfileQuery};
+use super::{render_error, CursorQuery, ProfileQuery};
 use crate::{assets::initial_context, get_lang, get_user_from_token, sanitize::clean_settings, State};
 use axum::{
     Extension,
@@ -45,19 +45,17 @@ pub async fn settings_request(
         }
     };
 
-    let tokens = profile.tokens.clone();
+    let current_token = match jar.get("__Secure-atto-token") {
+        Some(token) => hash(token.to_string().replace("__Secure-atto-token=", "")),
+        None => String::new(),
+    };
 
     let lang = get_lang!(jar, data.0);
     let mut context = initial_context(&data.0.0, lang, &Some(user)).await;
 
     context.insert("profile", &profile);
     context.insert("user_settings_serde", &clean_settings(&profile.settings));
-    context.insert(
-        "user_tokens_serde",
-        &serde_json::to_string(&tokens)
-            .unwrap()
-            .replace("\"", "\\\""),
-    );
+    context.insert("current_token", &current_token);
 
     // return
     Ok(Html(
@@ -65,6 +63,7 @@ pub async fn settings_request(
     ))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn profile_context(
     context: &mut Context,
     user: &Option<User>,
@@ -113,17 +112,16 @@ pub async fn posts_request(
     };
 
     // check if we're blocked
-    if let Some(ref ua) = user {
-        if data
+    if let Some(ref ua) = user
+        && data
             .0
             .get_userblock_by_initiator_receiver(other_user.id, ua.id)
             .await
             .is_ok()
-        {
-            return Err(Html(
-                render_error(Error::NotAllowed, &jar, &data, &user).await,
-            ));
-        }
+    {
+        return Err(Html(
+            render_error(Error::NotAllowed, &jar, &data, &user).await,
+        ));
     }
 
     // check for private profile
@@ -190,17 +188,17 @@ pub async fn posts_request(
     }
 
     // fetch data
-    let posts = match data
+    let (posts, next) = match data
         .0
-        .get_posts_by_user(other_user.id, 12, props.page)
+        .get_posts_by_user(other_user.id, 12, props.cursor)
         .await
     {
-        Ok(p) => match data
+        Ok((p, next)) => match data
             .0
             .fill_posts_with_community(p, if let Some(ref ua) = user { ua.id } else { 0 })
             .await
         {
-            Ok(p) => p,
+            Ok(p) => (p, next),
             Err(e) => return Err(Html(render_error(e, &jar, &data, &user).await)),
         },
         Err(e) => return Err(Html(render_error(e, &jar, &data, &user).await)),
@@ -265,7 +263,8 @@ pub async fn posts_request(
 
     context.insert("posts", &posts);
     context.insert("pinned", &pinned);
-    context.insert("page", &props.page);
+    context.insert("cursor", &props.cursor);
+    context.insert("next", &next);
     profile_context(
         &mut context,
         &user,
@@ -285,7 +284,7 @@ pub async fn posts_request(
 pub async fn following_request(
     jar: CookieJar,
     Path(username): Path<String>,
-    Query(props): Query<PaginatedQuery>,
+    Query(props): Query<CursorQuery>,
     Extension(data): Extension<State>,
 ) -> impl IntoResponse {
     let data = data.read().await;
@@ -297,17 +296,16 @@ pub async fn following_request(
     };
 
     // check if we're blocked
-    if let Some(ref ua) = user {
-        if data
+    if let Some(ref ua) = user
+        && data
             .0
             .get_userblock_by_initiator_receiver(other_user.id, ua.id)
             .await
             .is_ok()
-        {
-            return Err(Html(
-                render_error(Error::NotAllowed, &jar, &data, &user).await,
-            ));
-        }
+    {
+        return Err(Html(
+            render_error(Error::NotAllowed, &jar, &data, &user).await,
+        ));
     }
 
     // check for private profile
@@ -332,13 +330,13 @@ pub async fn following_request(
     }
 
     // fetch data
-    let list = match data
+    let (list, next) = match data
         .0
-        .get_userfollows_by_initiator(other_user.id, 12, props.page)
+        .get_userfollows_by_initiator(other_user.id, 12, props.cursor)
         .await
     {
-        Ok(l) => match data.0.fill_userfollows_with_receiver(l).await {
-            Ok(l) => l,
+        Ok((l, next)) => match data.0.fill_userfollows_with_receiver(l).await {
+            Ok(l) => (l, next),
             Err(e) => return Err(Html(render_error(e, &jar, &data, &user).await)),
         },
         Err(e) => return Err(Html(render_error(e, &jar, &data, &user).await)),
@@ -390,7 +388,8 @@ pub async fn following_request(
     };
 
     context.insert("list", &list);
-    context.insert("page", &props.page);
+    context.insert("cursor", &props.cursor);
+    context.insert("next", &next);
     profile_context(
         &mut context,
         &user,
@@ -412,7 +411,7 @@ pub async fn following_request(
 pub async fn followers_request(
     jar: CookieJar,
     Path(username): Path<String>,
-    Query(props): Query<PaginatedQuery>,
+    Query(props): Query<CursorQuery>,
     Extension(data): Extension<State>,
 ) -> impl IntoResponse {
     let data = data.read().await;
@@ -424,17 +423,16 @@ pub async fn followers_request(
     };
 
     // check if we're blocked
-    if let Some(ref ua) = user {
-        if data
+    if let Some(ref ua) = user
+        && data
             .0
             .get_userblock_by_initiator_receiver(other_user.id, ua.id)
             .await
             .is_ok()
-        {
-            return Err(Html(
-                render_error(Error::NotAllowed, &jar, &data, &user).await,
-            ));
-        }
+    {
+        return Err(Html(
+            render_error(Error::NotAllowed, &jar, &data, &user).await,
+        ));
     }
 
     // check for private profile
@@ -459,13 +457,13 @@ pub async fn followers_request(
     }
 
     // fetch data
-    let list = match data
+    let (list, next) = match data
         .0
-        .get_userfollows_by_receiver(other_user.id, 12, props.page)
+        .get_userfollows_by_receiver(other_user.id, 12, props.cursor)
         .await
     {
-        Ok(l) => match data.0.fill_userfollows_with_initiator(l).await {
-            Ok(l) => l,
+        Ok((l, next)) => match data.0.fill_userfollows_with_initiator(l).await {
+            Ok(l) => (l, next),
             Err(e) => return Err(Html(render_error(e, &jar, &data, &user).await)),
         },
         Err(e) => return Err(Html(render_error(e, &jar, &data, &user).await)),
@@ -517,7 +515,8 @@ pub async fn followers_request(
     };
 
     context.insert("list", &list);
-    context.insert("page", &props.page);
+    context.insert("cursor", &props.cursor);
+    context.insert("next", &next);
     profile_context(
         &mut context,
         &user,
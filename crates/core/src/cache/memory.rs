@@ -0,0 +1,157 @@
+use hashlink::LruCache;
+use serde::{Serialize, de::DeserializeOwned};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use super::{Cache, EXPIRE_AT, TimedObject};
+use crate::config::CacheConfig;
+
+pub use super::EPOCH_YEAR;
+
+/// A cached object, and the time it expires at.
+type Entry = (String, Instant);
+
+/// A bounded, in-process cache. The least recently used objects are evicted once
+/// it holds `capacity` objects.
+///
+/// Objects aren't shared between processes, so this should only be used by
+/// instances running a single process.
+#[derive(Clone)]
+pub struct MemoryCache {
+    pub client: Arc<Mutex<LruCache<String, Entry>>>,
+    pub ttl: Duration,
+}
+
+impl MemoryCache {
+    /// Run `f` with the cache locked.
+    fn with<T>(&self, f: impl FnOnce(&mut LruCache<String, Entry>) -> T) -> T {
+        let mut cache = self.client.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut cache)
+    }
+
+    /// Add `by` to the number stored at `id`, which then expires after `ttl`.
+    /// Missing objects count as `0`. Returns the new value.
+    fn add(&self, id: String, by: i64, ttl: Duration) -> Option<i64> {
+        let expires = Instant::now() + ttl;
+
+        self.with(|cache| {
+            let value = match cache.get(&id) {
+                Some((value, at)) if *at > Instant::now() => value.parse::<i64>().ok()?,
+                _ => 0,
+            };
+
+            cache.insert(id, ((value + by).to_string(), expires));
+            Some(value + by)
+        })
+    }
+}
+
+impl Cache for MemoryCache {
+    type Item = String;
+    type Client = Arc<Mutex<LruCache<String, Entry>>>;
+
+    async fn new(config: &CacheConfig) -> Self {
+        Self {
+            client: Arc::new(Mutex::new(LruCache::new(config.capacity.max(1)))),
+            ttl: Duration::from_secs(config.ttl),
+        }
+    }
+
+    async fn get_con(&self) -> Self::Client {
+        self.client.clone()
+    }
+
+    async fn get(&self, id: Self::Item) -> Option<String> {
+        self.with(|cache| match cache.get(&id) {
+            Some((value, at)) if *at > Instant::now() => Some(value.clone()),
+            Some(_) => {
+                cache.remove(&id);
+                None
+            }
+            None => None,
+        })
+    }
+
+    async fn set(&self, id: Self::Item, content: Self::Item) -> bool {
+        let expires = Instant::now() + self.ttl;
+        self.with(|cache| cache.insert(id, (content, expires)));
+        true
+    }
+
+    async fn update(&self, id: Self::Item, content: Self::Item) -> bool {
+        self.set(id, content).await
+    }
+
+    async fn remove(&self, id: Self::Item) -> bool {
+        self.with(|cache| cache.remove(&id));
+        true
+    }
+
+    /// Remove every object whose identifier starts with `id` (a trailing `*` is
+    /// ignored, to match the Redis glob syntax).
+    async fn remove_starting_with(&self, id: Self::Item) -> bool {
+        let prefix = id.trim_end_matches('*');
+
+        self.with(|cache| {
+            let keys: Vec<String> = cache
+                .iter()
+                .filter(|(k, _)| k.starts_with(prefix))
+                .map(|(k, _)| k.to_owned())
+                .collect();
+
+            for key in keys {
+                cache.remove(&key);
+            }
+        });
+
+        true
+    }
+
+    async fn bump_namespace(&self, namespace: Self::Item) -> bool {
+        self.remove_starting_with(format!("{namespace}:")).await
+    }
+
+    async fn incr(&self, id: Self::Item) -> bool {
+        self.add(id, 1, self.ttl).is_some()
+    }
+
+    async fn decr(&self, id: Self::Item) -> bool {
+        self.add(id, -1, self.ttl).is_some()
+    }
+
+    async fn get_timed<T: Serialize + DeserializeOwned>(
+        &self,
+        id: Self::Item,
+    ) -> Option<TimedObject<T>> {
+        let d = self.get(id.clone()).await?;
+
+        match serde_json::from_str::<TimedObject<T>>(&d) {
+            Ok(d) => {
+                // check time
+                let now = tetratto_shared::epoch_timestamp(EPOCH_YEAR);
+
+                if now - d.0 >= EXPIRE_AT {
+                    // expired key, remove and return None
+                    self.remove(id).await;
+                    return None;
+                }
+
+                // return
+                Some(d)
+            }
+            Err(_) => None,
+        }
+    }
+
+    async fn set_timed<T: Serialize + DeserializeOwned>(&self, id: Self::Item, content: T) -> bool {
+        match serde_json::to_string::<TimedObject<T>>(&(
+            tetratto_shared::epoch_timestamp(EPOCH_YEAR),
+            content,
+        )) {
+            Ok(s) => self.set(id, s).await,
+            Err(_) => false,
+        }
+    }
+}
@@ -1,15 +1,22 @@
 #![allow(async_fn_in_trait)]
+use crate::config::CacheConfig;
 use serde::{Serialize, de::DeserializeOwned};
 
 pub const EXPIRE_AT: i64 = 3_600_000;
 
+/// The year the timestamps of timed objects start from.
+pub const EPOCH_YEAR: u32 = 2025;
+
 #[allow(type_alias_bounds)]
 pub type TimedObject<T: Serialize + DeserializeOwned> = (i64, T);
 
 #[cfg(feature = "redis")]
 pub mod redis;
 
-#[cfg(not(feature = "redis"))]
+#[cfg(all(feature = "memory", not(feature = "redis")))]
+pub mod memory;
+
+#[cfg(not(any(feature = "redis", feature = "memory")))]
 pub mod no_cache;
 
 /// A simple cache "database".
@@ -18,7 +25,7 @@ pub trait Cache {
     type Client;
 
     /// Create a new [`Cache`].
-    async fn new() -> Self;
+    async fn new(config: &CacheConfig) -> Self;
     /// Get a connection to the cache.
     async fn get_con(&self) -> Self::Client;
 
@@ -49,6 +56,12 @@ pub trait Cache {
     /// # Arguments
     /// * `id` - `String` of the object's id('s start)
     async fn remove_starting_with(&self, id: Self::Item) -> bool;
+    /// Remove every cache object in a namespace (the part of an identifier before
+    /// its first `:`, like `atto.user`)
+    ///
+    /// # Arguments
+    /// * `namespace` - `String` of the namespace
+    async fn bump_namespace(&self, namespace: Self::Item) -> bool;
     /// Increment a cache object by its identifier
     ///
     /// # Arguments
@@ -1,8 +1,9 @@
 use serde::{Serialize, de::DeserializeOwned};
 
 use super::{Cache, EXPIRE_AT, TimedObject};
+use crate::config::CacheConfig;
 
-pub const EPOCH_YEAR: u32 = 2025;
+pub use super::EPOCH_YEAR;
 
 #[derive(Clone)]
 pub struct NoCache {
@@ -13,7 +14,7 @@ impl Cache for NoCache {
     type Item = String;
     type Client = Option<u32>;
 
-    async fn new() -> Self {
+    async fn new(config: &CacheConfig) -> Self {
         Self { client: None }
     }
 
@@ -41,6 +42,10 @@ impl Cache for NoCache {
         true
     }
 
+    async fn bump_namespace(&self, namespace: Self::Item) -> bool {
+        true
+    }
+
     async fn incr(&self, id: Self::Item) -> bool {
         true
     }
@@ -57,6 +62,6 @@ impl Cache for NoCache {
     }
 
     async fn set_timed<T: Serialize + DeserializeOwned>(&self, id: Self::Item, content: T) -> bool {
-        None
+        false
     }
 }
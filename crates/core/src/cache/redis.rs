@@ -1,36 +1,149 @@
-use redis::Commands;
+use redis::{
+    AsyncCommands,
+    aio::{ConnectionManager, ConnectionManagerConfig},
+};
 use serde::{Serialize, de::DeserializeOwned};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use super::{Cache, EXPIRE_AT, TimedObject};
+use crate::config::CacheConfig;
 
-pub const EPOCH_YEAR: u32 = 2025;
+pub use super::EPOCH_YEAR;
 
+/// How long connecting to (or waiting on) Redis can take before we give up.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long to wait before trying to connect again after failing to connect.
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The number of keys checked by each `SCAN` call in [`RedisCache::remove_starting_with`].
+const SCAN_COUNT: usize = 500;
+
+/// The shared connection, and the earliest time we can try to connect again.
+type ConnectionState = (Option<ConnectionManager>, Option<Instant>);
+
+/// A cache backed by Redis.
+///
+/// Every operation is treated as a cache miss (or a failed write) while Redis is
+/// unreachable.
 #[derive(Clone)]
 pub struct RedisCache {
-    pub client: redis::Client,
+    pub client: Option<redis::Client>,
+    pub config: CacheConfig,
+    connection: Arc<Mutex<ConnectionState>>,
+}
+
+impl RedisCache {
+    /// Get the key which stores the current version of the given namespace.
+    fn namespace_key(&self, namespace: &str) -> String {
+        format!("{}__namespace:{namespace}", self.config.prefix)
+    }
+
+    /// Get the key of the object with the given identifier in Redis.
+    ///
+    /// The namespace of the identifier (everything before its first `:`) is
+    /// suffixed with its current version once it has been bumped, so objects
+    /// cached before [`Cache::bump_namespace`] are never read again.
+    async fn key(&self, c: &mut ConnectionManager, id: &str) -> Option<String> {
+        let prefix = &self.config.prefix;
+
+        let Some((namespace, rest)) = id.split_once(':') else {
+            return Some(format!("{prefix}{id}"));
+        };
+
+        // we can't know which version is current if this fails, so we can't
+        // safely use any key
+        let version: Option<u64> = c.get(self.namespace_key(namespace)).await.ok()?;
+
+        Some(match version {
+            Some(v) if v > 0 => format!("{prefix}{namespace}@{v}:{rest}"),
+            _ => format!("{prefix}{id}"),
+        })
+    }
+
+    /// Get a connection, and the key of the object with the given identifier.
+    async fn con_with_key(&self, id: &str) -> Option<(ConnectionManager, String)> {
+        let mut c = self.get_con().await?;
+        let key = self.key(&mut c, id).await?;
+        Some((c, key))
+    }
 }
 
 impl Cache for RedisCache {
     type Item = String;
-    type Client = redis::Connection;
+    type Client = Option<ConnectionManager>;
 
-    async fn new() -> Self {
-        Self {
-            client: redis::Client::open("redis://127.0.0.1:6379").unwrap(),
-        }
+    async fn new(config: &CacheConfig) -> Self {
+        let client = match redis::Client::open(config.url.as_str()) {
+            Ok(c) => Some(c),
+            Err(e) => {
+                tracing::warn!("invalid cache url, caching is disabled: {e}");
+                None
+            }
+        };
+
+        let this = Self {
+            client,
+            config: config.clone(),
+            connection: Arc::new(Mutex::new((None, None))),
+        };
+
+        this.get_con().await;
+        this
     }
 
+    /// Get the shared connection, connecting if we aren't connected yet. Returns
+    /// [`None`] if Redis is unreachable.
     async fn get_con(&self) -> Self::Client {
-        self.client.get_connection().unwrap()
+        let client = self.client.as_ref()?;
+
+        {
+            let mut state = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+
+            if let Some(ref con) = state.0 {
+                return Some(con.clone());
+            }
+
+            if state.1.is_some_and(|x| Instant::now() < x) {
+                return None;
+            }
+
+            // other calls shouldn't also try to connect while we do
+            state.1 = Some(Instant::now() + RETRY_INTERVAL);
+        }
+
+        let config = ConnectionManagerConfig::new()
+            .set_connection_timeout(TIMEOUT)
+            .set_response_timeout(TIMEOUT)
+            .set_number_of_retries(1);
+
+        match ConnectionManager::new_with_config(client.clone(), config).await {
+            Ok(con) => {
+                let mut state = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+                state.0 = Some(con.clone());
+                Some(con)
+            }
+            Err(e) => {
+                tracing::warn!("failed to connect to cache: {e}");
+                None
+            }
+        }
     }
 
     async fn get(&self, id: Self::Item) -> Option<String> {
-        self.get_con().await.get(id).ok()
+        let (mut c, key) = self.con_with_key(&id).await?;
+        c.get(key).await.ok()
     }
 
     async fn set(&self, id: Self::Item, content: Self::Item) -> bool {
-        let mut c = self.get_con().await;
-        let res: Result<String, redis::RedisError> = c.set_ex(id, content, 604800);
+        let Some((mut c, key)) = self.con_with_key(&id).await else {
+            return false;
+        };
+
+        let res: Result<(), redis::RedisError> = c.set_ex(key, content, self.config.ttl).await;
 
         res.is_ok()
     }
@@ -40,40 +153,83 @@ impl Cache for RedisCache {
     }
 
     async fn remove(&self, id: Self::Item) -> bool {
-        let mut c = self.get_con().await;
-        let res: Result<String, redis::RedisError> = c.del(id);
+        let Some((mut c, key)) = self.con_with_key(&id).await else {
+            return false;
+        };
 
+        let res: Result<(), redis::RedisError> = c.del(key).await;
         res.is_ok()
     }
 
+    /// Remove every object matching the given pattern (Redis glob syntax).
+    ///
+    /// Keys are found with `SCAN` (in batches) instead of `KEYS`, so Redis isn't
+    /// blocked while we search. Prefer [`Cache::bump_namespace`] when removing an
+    /// entire namespace.
     async fn remove_starting_with(&self, id: Self::Item) -> bool {
-        let mut c = self.get_con().await;
+        let Some((mut c, pattern)) = self.con_with_key(&id).await else {
+            return false;
+        };
+
+        let mut cursor: u64 = 0;
+
+        loop {
+            let res: Result<(u64, Vec<String>), redis::RedisError> = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(&pattern)
+                .arg("COUNT")
+                .arg(SCAN_COUNT)
+                .query_async(&mut c)
+                .await;
+
+            let (next, keys) = match res {
+                Ok(x) => x,
+                Err(_) => return false,
+            };
 
-        // get keys
-        let mut cmd = redis::cmd("DEL");
-        let keys: Result<Vec<String>, redis::RedisError> = c.keys(id);
+            if !keys.is_empty() {
+                let res: Result<(), redis::RedisError> = c.unlink(keys).await;
 
-        for key in keys.unwrap() {
-            cmd.arg(key);
+                if res.is_err() {
+                    return false;
+                }
+            }
+
+            if next == 0 {
+                return true;
+            }
+
+            cursor = next;
         }
+    }
 
-        // remove
-        let res: Result<String, redis::RedisError> = cmd.query(&mut c);
+    /// Move the namespace to its next version. This takes constant time, objects
+    /// from older versions are left to expire.
+    async fn bump_namespace(&self, namespace: Self::Item) -> bool {
+        let Some(mut c) = self.get_con().await else {
+            return false;
+        };
 
+        let res: Result<u64, redis::RedisError> = c.incr(self.namespace_key(&namespace), 1).await;
         res.is_ok()
     }
 
     async fn incr(&self, id: Self::Item) -> bool {
-        let mut c = self.get_con().await;
-        let res: Result<String, redis::RedisError> = c.incr(id, 1);
+        let Some((mut c, key)) = self.con_with_key(&id).await else {
+            return false;
+        };
 
+        let res: Result<i64, redis::RedisError> = c.incr(key, 1).await;
         res.is_ok()
     }
 
     async fn decr(&self, id: Self::Item) -> bool {
-        let mut c = self.get_con().await;
-        let res: Result<String, redis::RedisError> = c.decr(id, 1);
+        let Some((mut c, key)) = self.con_with_key(&id).await else {
+            return false;
+        };
 
+        let res: Result<i64, redis::RedisError> = c.decr(key, 1).await;
         res.is_ok()
     }
 
@@ -81,42 +237,44 @@ impl Cache for RedisCache {
         &self,
         id: Self::Item,
     ) -> Option<TimedObject<T>> {
-        let mut c = self.get_con().await;
-        let res: Result<String, redis::RedisError> = c.get(&id);
-
-        match res {
-            Ok(d) => match serde_json::from_str::<TimedObject<T>>(&d) {
-                Ok(d) => {
-                    // check time
-                    let now = tetratto_shared::epoch_timestamp(EPOCH_YEAR);
-
-                    if now - d.0 >= EXPIRE_AT {
-                        // expired key, remove and return None
-                        self.remove(id).await;
-                        return None;
-                    }
-
-                    // return
-                    Some(d)
+        let d: String = self.get(id.clone()).await?;
+
+        match serde_json::from_str::<TimedObject<T>>(&d) {
+            Ok(d) => {
+                // check time
+                let now = tetratto_shared::epoch_timestamp(EPOCH_YEAR);
+
+                if now - d.0 >= EXPIRE_AT {
+                    // expired key, remove and return None
+                    self.remove(id).await;
+                    return None;
                 }
-                Err(_) => None,
-            },
+
+                // return
+                Some(d)
+            }
             Err(_) => None,
         }
     }
 
     async fn set_timed<T: Serialize + DeserializeOwned>(&self, id: Self::Item, content: T) -> bool {
-        let mut c = self.get_con().await;
-        let res: Result<String, redis::RedisError> = c.set(
-            id,
-            match serde_json::to_string::<TimedObject<T>>(&(
-                tetratto_shared::epoch_timestamp(EPOCH_YEAR),
-                content,
-            )) {
-                Ok(s) => s,
-                Err(_) => return false,
-            },
-        );
+        let Some((mut c, key)) = self.con_with_key(&id).await else {
+            return false;
+        };
+
+        let res: Result<(), redis::RedisError> = c
+            .set_ex(
+                key,
+                match serde_json::to_string::<TimedObject<T>>(&(
+                    tetratto_shared::epoch_timestamp(EPOCH_YEAR),
+                    content,
+                )) {
+                    Ok(s) => s,
+                    Err(_) => return false,
+                },
+                self.config.ttl,
+            )
+            .await;
 
         res.is_ok()
     }
@@ -1,7 +1,9 @@
 use pathbufd::PathBufD;
 use serde::{Deserialize, Serialize};
-use std::fs;
+use std::{env, fs};
 use std::io::Result;
+use tetratto_shared::hash::{hash_password, password_needs_rehash};
+use tetratto_shared::snow::MAX_NODE_ID;
 
 /// Security configuration.
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -12,6 +14,9 @@ pub struct SecurityConfig {
     /// The name of the header which will contain the real IP of the connecting user.
     #[serde(default = "default_real_ip_header")]
     pub real_ip_header: String,
+    /// Password hashing configuration.
+    #[serde(default = "default_security_password_hashing")]
+    pub password_hashing: PasswordHashingConfig,
 }
 
 fn default_security_registration_enabled() -> bool {
@@ -22,15 +27,84 @@ fn default_real_ip_header() -> String {
     "CF-Connecting-IP".to_string()
 }
 
+fn default_security_password_hashing() -> PasswordHashingConfig {
+    PasswordHashingConfig::default()
+}
+
 impl Default for SecurityConfig {
     fn default() -> Self {
         Self {
             registration_enabled: default_security_registration_enabled(),
             real_ip_header: default_real_ip_header(),
+            password_hashing: default_security_password_hashing(),
         }
     }
 }
 
+/// Password hashing (Argon2id) configuration.
+///
+/// Passwords are rehashed with the current parameters the next time their user
+/// logs in.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PasswordHashingConfig {
+    /// The amount of memory (in KiB) used to hash a password.
+    #[serde(default = "default_password_memory_cost")]
+    pub memory_cost: u32,
+    /// The number of passes over the memory.
+    #[serde(default = "default_password_time_cost")]
+    pub time_cost: u32,
+    /// The number of lanes used.
+    #[serde(default = "default_password_parallelism")]
+    pub parallelism: u32,
+}
+
+fn default_password_memory_cost() -> u32 {
+    19_456 // 19 MiB
+}
+
+fn default_password_time_cost() -> u32 {
+    2
+}
+
+fn default_password_parallelism() -> u32 {
+    1
+}
+
+impl Default for PasswordHashingConfig {
+    fn default() -> Self {
+        Self {
+            memory_cost: default_password_memory_cost(),
+            time_cost: default_password_time_cost(),
+            parallelism: default_password_parallelism(),
+        }
+    }
+}
+
+impl PasswordHashingConfig {
+    /// Hash the given password with these parameters.
+    ///
+    /// Hashing is slow on purpose, so it's done on a blocking thread.
+    pub async fn hash(&self, password: &str) -> String {
+        let (password, hashing) = (password.to_string(), self.clone());
+
+        tokio::task::spawn_blocking(move || {
+            hash_password(
+                &password,
+                hashing.memory_cost,
+                hashing.time_cost,
+                hashing.parallelism,
+            )
+        })
+        .await
+        .expect("failed to hash password")
+    }
+
+    /// Check if the given hash wasn't made with these parameters.
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        password_needs_rehash(hash, self.memory_cost, self.time_cost, self.parallelism)
+    }
+}
+
 /// Directories configuration.
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct DirsConfig {
@@ -87,6 +161,9 @@ impl Default for DirsConfig {
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct DatabaseConfig {
     pub name: String,
+    /// The number of connections kept open in the pool.
+    #[serde(default = "default_database_pool_size")]
+    pub pool_size: u32,
     #[cfg(feature = "postgres")]
     pub url: String,
     #[cfg(feature = "postgres")]
@@ -95,10 +172,15 @@ pub struct DatabaseConfig {
     pub password: String,
 }
 
+fn default_database_pool_size() -> u32 {
+    15
+}
+
 impl Default for DatabaseConfig {
     fn default() -> Self {
         Self {
             name: "atto.db".to_string(),
+            pool_size: default_database_pool_size(),
             #[cfg(feature = "postgres")]
             url: "localhost:5432".to_string(),
             #[cfg(feature = "postgres")]
@@ -109,6 +191,155 @@ impl Default for DatabaseConfig {
     }
 }
 
+/// Cache (Redis) configuration.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct CacheConfig {
+    /// The URL of the Redis server.
+    #[serde(default = "default_cache_url")]
+    pub url: String,
+    /// A string added to the start of every key, so multiple instances can share
+    /// one Redis server.
+    #[serde(default)]
+    pub prefix: String,
+    /// The amount of time (in seconds) cached objects are kept for.
+    #[serde(default = "default_cache_ttl")]
+    pub ttl: u64,
+    /// The maximum number of objects kept by the in-process cache (used when
+    /// built with the `memory` feature instead of `redis`).
+    #[serde(default = "default_cache_capacity")]
+    pub capacity: usize,
+}
+
+fn default_cache_url() -> String {
+    "redis://127.0.0.1:6379".to_string()
+}
+
+fn default_cache_ttl() -> u64 {
+    604_800 // 7 days
+}
+
+fn default_cache_capacity() -> usize {
+    10_000
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            url: default_cache_url(),
+            prefix: String::new(),
+            ttl: default_cache_ttl(),
+            capacity: default_cache_capacity(),
+        }
+    }
+}
+
+/// A limit on how often something can be done, enforced with a token bucket.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+pub struct RateLimit {
+    /// The number of requests which can be made at once (the size of the bucket),
+    /// and are allowed every `seconds` seconds. `0` disables the limit.
+    pub requests: u64,
+    /// The number of seconds it takes for all `requests` to be allowed again.
+    pub seconds: u64,
+}
+
+impl RateLimit {
+    pub const fn new(requests: u64, seconds: u64) -> Self {
+        Self { requests, seconds }
+    }
+}
+
+/// An action which is rate limited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RateLimitAction {
+    Login,
+    Register,
+    CreatePost,
+    CreateQuestion,
+    CreateReport,
+}
+
+impl RateLimitAction {
+    /// The name of the action, used in cache keys.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Login => "login",
+            Self::Register => "register",
+            Self::CreatePost => "create_post",
+            Self::CreateQuestion => "create_question",
+            Self::CreateReport => "create_report",
+        }
+    }
+}
+
+/// Rate limits configuration.
+///
+/// Limits are applied separately to every user and every IP (read from
+/// `security.real_ip_header`, or the address of the connection if it isn't set).
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RateLimitsConfig {
+    /// Logging in.
+    #[serde(default = "default_rate_limit_login")]
+    pub login: RateLimit,
+    /// Registering a new account.
+    #[serde(default = "default_rate_limit_register")]
+    pub register: RateLimit,
+    /// Creating posts (including replies and reposts).
+    #[serde(default = "default_rate_limit_create_post")]
+    pub create_post: RateLimit,
+    /// Asking questions (including anonymous ones).
+    #[serde(default = "default_rate_limit_create_question")]
+    pub create_question: RateLimit,
+    /// Creating reports.
+    #[serde(default = "default_rate_limit_create_report")]
+    pub create_report: RateLimit,
+}
+
+fn default_rate_limit_login() -> RateLimit {
+    RateLimit::new(10, 60)
+}
+
+fn default_rate_limit_register() -> RateLimit {
+    RateLimit::new(5, 3600)
+}
+
+fn default_rate_limit_create_post() -> RateLimit {
+    RateLimit::new(30, 60)
+}
+
+fn default_rate_limit_create_question() -> RateLimit {
+    RateLimit::new(10, 60)
+}
+
+fn default_rate_limit_create_report() -> RateLimit {
+    RateLimit::new(10, 300)
+}
+
+impl RateLimitsConfig {
+    /// Get the limit of the given action.
+    pub fn get(&self, action: RateLimitAction) -> RateLimit {
+        match action {
+            RateLimitAction::Login => self.login,
+            RateLimitAction::Register => self.register,
+            RateLimitAction::CreatePost => self.create_post,
+            RateLimitAction::CreateQuestion => self.create_question,
+            RateLimitAction::CreateReport => self.create_report,
+        }
+    }
+}
+
+impl Default for RateLimitsConfig {
+    fn default() -> Self {
+        Self {
+            login: default_rate_limit_login(),
+            register: default_rate_limit_register(),
+            create_post: default_rate_limit_create_post(),
+            create_question: default_rate_limit_create_question(),
+            create_report: default_rate_limit_create_report(),
+        }
+    }
+}
+
 /// Policies config (TOS/privacy)
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct PoliciesConfig {
@@ -186,6 +417,12 @@ pub struct Config {
     /// Database configuration.
     #[serde(default = "default_database")]
     pub database: DatabaseConfig,
+    /// Cache configuration.
+    #[serde(default = "default_cache")]
+    pub cache: CacheConfig,
+    /// Rate limits configuration.
+    #[serde(default = "default_rate_limits")]
+    pub rate_limits: RateLimitsConfig,
     /// A list of files (just their name, no full path) which are NOT updated to match the
     /// version built with the server binary.
     #[serde(default = "default_no_track")]
@@ -205,6 +442,21 @@ pub struct Config {
     /// This community **must** have open write access.
     #[serde(default)]
     pub town_square: usize,
+    /// The amount of time (in ms) deleted posts, questions and communities can be
+    /// restored for. They're permanently purged once it passes.
+    #[serde(default = "default_restore_window")]
+    pub restore_window: usize,
+    /// The amount of time (in ms) finished data exports can be downloaded for.
+    /// They're deleted once it passes.
+    #[serde(default = "default_export_window")]
+    pub export_window: usize,
+    /// The server ID included in every ID this process generates (`0` to `1023`).
+    ///
+    /// Every process using the same database needs its own node ID. Can be
+    /// overridden with the `NODE_ID` environment variable. The server refuses to
+    /// start if it's out of range.
+    #[serde(default = "default_node_id")]
+    pub node_id: usize,
 }
 
 fn default_name() -> String {
@@ -242,6 +494,14 @@ fn default_database() -> DatabaseConfig {
     DatabaseConfig::default()
 }
 
+fn default_cache() -> CacheConfig {
+    CacheConfig::default()
+}
+
+fn default_rate_limits() -> RateLimitsConfig {
+    RateLimitsConfig::default()
+}
+
 fn default_no_track() -> Vec<String> {
     Vec::new()
 }
@@ -269,6 +529,18 @@ fn default_turnstile() -> TurnstileConfig {
     TurnstileConfig::default()
 }
 
+fn default_restore_window() -> usize {
+    604_800_000 // 7 days
+}
+
+fn default_export_window() -> usize {
+    604_800_000 // 7 days
+}
+
+fn default_node_id() -> usize {
+    1234567890 % 1024
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -279,6 +551,8 @@ impl Default for Config {
             banned_hosts: default_banned_hosts(),
             host: default_host(),
             database: default_database(),
+            cache: default_cache(),
+            rate_limits: default_rate_limits(),
             security: default_security(),
             dirs: default_dirs(),
             no_track: default_no_track(),
@@ -286,6 +560,9 @@ impl Default for Config {
             policies: default_policies(),
             turnstile: default_turnstile(),
             town_square: 0,
+            restore_window: default_restore_window(),
+            export_window: default_export_window(),
+            node_id: default_node_id(),
         }
     }
 }
@@ -297,16 +574,30 @@ impl Config {
     }
 
     /// Pull configuration file
+    ///
+    /// # Panics
+    /// Panics if the node ID (from the file or the `NODE_ID` environment variable)
+    /// is larger than [`MAX_NODE_ID`].
     pub fn get_config() -> Self {
         let path = PathBufD::current().join("tetratto.toml");
 
-        match fs::read_to_string(&path) {
+        let mut config = match fs::read_to_string(&path) {
             Ok(c) => Config::read(c),
             Err(_) => {
                 Self::update_config(Self::default()).expect("failed to write default config");
                 Self::default()
             }
+        };
+
+        if let Ok(node_id) = env::var("NODE_ID") {
+            config.node_id = node_id.parse::<usize>().expect("NODE_ID must be a number");
         }
+
+        if config.node_id > MAX_NODE_ID {
+            panic!("node_id must be at most {MAX_NODE_ID}");
+        }
+
+        config
     }
 
     /// Update configuration file
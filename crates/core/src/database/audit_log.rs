@@ -14,13 +14,13 @@ impl DataManager {
     pub(crate) fn get_audit_log_entry_from_row(
         #[cfg(feature = "sqlite")] x: &Row<'_>,
         #[cfg(feature = "postgres")] x: &Row,
-    ) -> AuditLogEntry {
-        AuditLogEntry {
+    ) -> Result<AuditLogEntry> {
+        Ok(AuditLogEntry {
             id: get!(x->0(i64)) as usize,
             created: get!(x->1(i64)) as usize,
             moderator: get!(x->2(i64)) as usize,
             content: get!(x->3(String)),
-        }
+        })
     }
 
     auto_method!(get_audit_log_entry_by_id(usize as i64)@get_audit_log_entry_from_row -> "SELECT * FROM audit_log WHERE id = $1" --name="audit log entry" --returns=AuditLogEntry --cache-key-tmpl="atto.audit_log:{}");
@@ -45,13 +45,9 @@ impl DataManager {
             "SELECT * FROM audit_log ORDER BY created DESC LIMIT $1 OFFSET $2",
             &[&(batch as i64), &((page * batch) as i64)],
             |x| { Self::get_audit_log_entry_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("audit log entry".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Create a new audit log entry in the database.
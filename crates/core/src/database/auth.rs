@@ -9,9 +9,12 @@ use crate::model::{
 use crate::{auto_method, execute, get, query_row, params};
 use pathbufd::PathBufD;
 use std::fs::{exists, remove_file};
-use tetratto_shared::hash::{hash_salted, salt};
+use tetratto_shared::hash::salt;
 use tetratto_shared::unix_epoch_timestamp;
 
+/// How often (in ms) the last used time of a session is recorded.
+const SESSION_TOUCH_INTERVAL: usize = 300_000;
+
 #[cfg(feature = "sqlite")]
 use rusqlite::Row;
 
@@ -23,26 +26,29 @@ impl DataManager {
     pub(crate) fn get_user_from_row(
         #[cfg(feature = "sqlite")] x: &Row<'_>,
         #[cfg(feature = "postgres")] x: &Row,
-    ) -> User {
-        User {
+    ) -> Result<User> {
+        Ok(User {
             id: get!(x->0(i64)) as usize,
             created: get!(x->1(i64)) as usize,
             username: get!(x->2(String)),
             password: get!(x->3(String)),
             salt: get!(x->4(String)),
-            settings: serde_json::from_str(&get!(x->5(String)).to_string()).unwrap(),
-            tokens: serde_json::from_str(&get!(x->6(String)).to_string()).unwrap(),
-            permissions: FinePermission::from_bits(get!(x->7(i32)) as u32).unwrap(),
+            settings: get!(x->5(json)),
+            tokens: get!(x->6(json)),
+            permissions: match FinePermission::from_bits(get!(x->7(i32)) as u32) {
+                Some(x) => x,
+                None => return Err(Error::DatabaseError("invalid permissions".to_string())),
+            },
             is_verified: get!(x->8(i32)) as i8 == 1,
             notification_count: get!(x->9(i32)) as usize,
             follower_count: get!(x->10(i32)) as usize,
             following_count: get!(x->11(i32)) as usize,
             last_seen: get!(x->12(i64)) as usize,
             totp: get!(x->13(String)),
-            recovery_codes: serde_json::from_str(&get!(x->14(String)).to_string()).unwrap(),
+            recovery_codes: get!(x->14(json)),
             post_count: get!(x->15(i32)) as usize,
             request_count: get!(x->16(i32)) as usize,
-        }
+        })
     }
 
     auto_method!(get_user_by_id(usize as i64)@get_user_from_row -> "SELECT * FROM users WHERE id = $1" --name="user" --returns=User --cache-key-tmpl="atto.user:{}");
@@ -62,39 +68,63 @@ impl DataManager {
             &conn,
             "SELECT * FROM users WHERE id = $1",
             &[&(id as i64)],
-            |x| Ok(Self::get_user_from_row(x))
-        );
+            |x| { Self::get_user_from_row(x) }
+        )?;
 
-        if res.is_err() {
+        let Some(res) = res else {
             return Ok(User::deleted());
             // return Err(Error::UserNotFound);
-        }
+        };
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Get a user given just their auth token.
     ///
     /// # Arguments
-    /// * `token` - the token of the user
+    /// * `token` - the hashed token of the user
     pub async fn get_user_by_token(&self, token: &str) -> Result<User> {
         let conn = match self.connect().await {
             Ok(c) => c,
             Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
         };
 
+        // match the token field itself (or the second item of sessions stored as
+        // arrays), so it can't be matched by the label or user agent of a session
         let res = query_row!(
             &conn,
-            "SELECT * FROM users WHERE tokens LIKE $1",
-            &[&format!("%\"{token}\"%")],
-            |x| Ok(Self::get_user_from_row(x))
-        );
+            "SELECT * FROM users WHERE tokens LIKE $1 OR tokens LIKE $2",
+            params![
+                &format!("%\"token\":\"{token}\"%"),
+                &format!("%[\"%\",\"{token}\",%")
+            ],
+            |x| { Self::get_user_from_row(x) }
+        )?;
 
-        if res.is_err() {
-            return Err(Error::UserNotFound);
+        match res {
+            Some(res) if res.tokens.iter().any(|t| t.token == token) => Ok(res),
+            _ => Err(Error::UserNotFound),
+        }
+    }
+
+    /// Check if a new user can be created with the given (lowercase) username.
+    pub(crate) async fn check_new_username(&self, username: &str) -> Result<()> {
+        if username.len() < 2 {
+            return Err(Error::DataTooShort("username".to_string()));
+        } else if username.len() > 32 {
+            return Err(Error::DataTooLong("username".to_string()));
         }
 
-        Ok(res.unwrap())
+        if self.0.banned_usernames.iter().any(|x| x == username) {
+            return Err(Error::MiscError("This username cannot be used".to_string()));
+        }
+
+        // make sure username isn't taken
+        if self.get_user_by_username(username).await.is_ok() {
+            return Err(Error::UsernameInUse);
+        }
+
+        Ok(())
     }
 
     /// Create a new user in the database.
@@ -109,25 +139,12 @@ impl DataManager {
         data.username = data.username.to_lowercase();
 
         // check values
-        if data.username.len() < 2 {
-            return Err(Error::DataTooShort("username".to_string()));
-        } else if data.username.len() > 32 {
-            return Err(Error::DataTooLong("username".to_string()));
-        }
+        self.check_new_username(&data.username).await?;
 
         if data.password.len() < 6 {
             return Err(Error::DataTooShort("password".to_string()));
         }
 
-        if self.0.banned_usernames.contains(&data.username) {
-            return Err(Error::MiscError("This username cannot be used".to_string()));
-        }
-
-        // make sure username isn't taken
-        if self.get_user_by_username(&data.username).await.is_ok() {
-            return Err(Error::UsernameInUse);
-        }
-
         // ...
         let conn = match self.connect().await {
             Ok(c) => c,
@@ -174,126 +191,184 @@ impl DataManager {
     pub async fn delete_user(&self, id: usize, password: &str, force: bool) -> Result<()> {
         let user = self.get_user_by_id(id).await?;
 
-        if (hash_salted(password.to_string(), user.salt.clone()) != user.password) && !force {
+        if !user.check_password(password.to_string()).await && !force {
             return Err(Error::IncorrectPassword);
         }
 
-        let conn = match self.connect().await {
-            Ok(c) => c,
-            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
-        };
+        // everybody who follows (or is followed by) this user needs their counts updated
+        let followers = self.get_userfollows_by_receiver_all(id).await?;
+        let following = self.get_userfollows_by_initiator_all(id).await?;
 
-        let res = execute!(&conn, "DELETE FROM users WHERE id = $1", &[&(id as i64)]);
+        self.transaction(async |conn| {
+            let res = execute!(
+                conn,
+                "DELETE FROM users WHERE id = $1",
+                &[&(id as i64)]
+            );
 
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
-        self.cache_clear_user(&user).await;
+            // delete communities
+            let res = execute!(
+                conn,
+                "DELETE FROM communities WHERE owner = $1",
+                &[&(id as i64)]
+            );
 
-        // delete communities
-        let res = execute!(
-            &conn,
-            "DELETE FROM communities WHERE owner = $1",
-            &[&(id as i64)]
-        );
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+            // delete memberships
+            // member counts will remain the same... but that should probably be changed
+            let res = execute!(
+                conn,
+                "DELETE FROM memberships WHERE owner = $1",
+                &[&(id as i64)]
+            );
 
-        // delete memberships
-        // member counts will remain the same... but that should probably be changed
-        let res = execute!(
-            &conn,
-            "DELETE FROM memberships WHERE owner = $1",
-            &[&(id as i64)]
-        );
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+            // delete notifications
+            let res = execute!(
+                conn,
+                "DELETE FROM notifications WHERE owner = $1",
+                &[&(id as i64)]
+            );
 
-        // delete notifications
-        let res = execute!(
-            &conn,
-            "DELETE FROM notifications WHERE owner = $1",
-            &[&(id as i64)]
-        );
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+            // delete requests
+            let res = execute!(
+                conn,
+                "DELETE FROM requests WHERE owner = $1",
+                &[&(id as i64)]
+            );
 
-        // delete requests
-        let res = execute!(
-            &conn,
-            "DELETE FROM requests WHERE owner = $1",
-            &[&(id as i64)]
-        );
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+            // delete warnings
+            let res = execute!(
+                conn,
+                "DELETE FROM user_warnings WHERE receiver = $1",
+                &[&(id as i64)]
+            );
 
-        // delete warnings
-        let res = execute!(
-            &conn,
-            "DELETE FROM user_warnings WHERE receiver = $1",
-            &[&(id as i64)]
-        );
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+            // delete blocks
+            let res = execute!(
+                conn,
+                "DELETE FROM userblocks WHERE initiator = $1 OR receiver = $1",
+                &[&(id as i64)]
+            );
 
-        // delete blocks
-        let res = execute!(
-            &conn,
-            "DELETE FROM userblocks WHERE initiator = $1 OR receiver = $1",
-            &[&(id as i64)]
-        );
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+            let res = execute!(
+                conn,
+                "DELETE FROM ipblocks WHERE initiator = $1",
+                &[&(id as i64)]
+            );
 
-        let res = execute!(
-            &conn,
-            "DELETE FROM ipblocks WHERE initiator = $1",
-            &[&(id as i64)]
-        );
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+            // delete reactions
+            // reactions counts will remain the same :)
+            let res = execute!(
+                conn,
+                "DELETE FROM reactions WHERE owner = $1",
+                &[&(id as i64)]
+            );
 
-        // delete reactions
-        // reactions counts will remain the same :)
-        let res = execute!(
-            &conn,
-            "DELETE FROM reactions WHERE owner = $1",
-            &[&(id as i64)]
-        );
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+            // delete posts (and their revisions)
+            let res = execute!(
+                conn,
+                "DELETE FROM post_revisions WHERE post IN (SELECT id FROM posts WHERE owner = $1)",
+                &[&(id as i64)]
+            );
 
-        // delete posts
-        let res = execute!(&conn, "DELETE FROM posts WHERE owner = $1", &[&(id as i64)]);
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+            let res = execute!(
+                conn,
+                "DELETE FROM posts WHERE owner = $1",
+                &[&(id as i64)]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
+
+            // delete user follows (and update the counts of everybody on the other end)
+            let res = execute!(
+                conn,
+                "UPDATE users SET following_count = following_count - 1 WHERE id IN (SELECT initiator FROM userfollows WHERE receiver = $1)",
+                &[&(id as i64)]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
+
+            let res = execute!(
+                conn,
+                "UPDATE users SET follower_count = follower_count - 1 WHERE id IN (SELECT receiver FROM userfollows WHERE initiator = $1)",
+                &[&(id as i64)]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
+
+            let res = execute!(
+                conn,
+                "DELETE FROM userfollows WHERE initiator = $1 OR receiver = $1",
+                &[&(id as i64)]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
+
+            Ok(())
+        })
+        .await?;
+
+        self.cache_clear_user(&user).await;
 
-        // delete user follows... individually since it requires updating user counts
-        for follow in self.get_userfollows_by_receiver_all(id).await? {
-            self.delete_userfollow(follow.id, &user, true).await?;
+        for follow in followers {
+            self.2
+                .remove(format!("atto.userfollow:{}", follow.id))
+                .await;
+            self.cache_clear_user_by_id(follow.initiator).await;
         }
 
-        for follow in self.get_userfollows_by_initiator_all(id).await? {
-            self.delete_userfollow(follow.id, &user, true).await?;
+        for follow in following {
+            self.2
+                .remove(format!("atto.userfollow:{}", follow.id))
+                .await;
+            self.cache_clear_user_by_id(follow.receiver).await;
         }
 
         // remove images
@@ -309,12 +384,24 @@ impl DataManager {
             &format!("{}.avif", &(user.id as i64)),
         ]);
 
-        if exists(&avatar).unwrap() {
-            remove_file(avatar).unwrap();
+        if exists(&avatar).unwrap_or(false)
+            && let Err(e) = remove_file(avatar)
+        {
+            return Err(Error::MiscError(e.to_string()));
+        }
+
+        if exists(&banner).unwrap_or(false)
+            && let Err(e) = remove_file(banner)
+        {
+            return Err(Error::MiscError(e.to_string()));
         }
 
-        if exists(&banner).unwrap() {
-            remove_file(banner).unwrap();
+        let export = self.user_export_path(user.id);
+
+        if exists(&export).unwrap_or(false)
+            && let Err(e) = remove_file(export)
+        {
+            return Err(Error::MiscError(e.to_string()));
         }
 
         // ...
@@ -368,30 +455,52 @@ impl DataManager {
         force: bool,
     ) -> Result<()> {
         // verify password
-        if (hash_salted(from.clone(), user.salt.clone()) != user.password) && !force {
-            return Err(Error::MiscError("Password does not match".to_string()));
+        if !user.check_password(from.clone()).await && !force {
+            return Err(Error::IncorrectPassword);
         }
 
         // ...
+        self.set_user_password(id, &to).await?;
+        self.cache_clear_user(&user).await;
+
+        Ok(())
+    }
+
+    /// Rehash the password of the given user with the current parameters, if it
+    /// was hashed with different ones. The password should already be checked.
+    ///
+    /// # Arguments
+    /// * `user` - the user
+    /// * `password` - the (unhashed) password of the user
+    pub async fn upgrade_user_password(&self, user: &User, password: String) -> Result<()> {
+        if !user.password_needs_rehash(&self.0.security.password_hashing) {
+            return Ok(());
+        }
+
+        self.set_user_password(user.id, &password).await?;
+        self.cache_clear_user(user).await;
+
+        Ok(())
+    }
+
+    /// Hash and store a new password for the user with the given ID.
+    async fn set_user_password(&self, id: usize, password: &str) -> Result<()> {
         let conn = match self.connect().await {
             Ok(c) => c,
             Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
         };
 
-        let new_salt = salt();
-        let new_password = hash_salted(to, new_salt.clone());
+        let new_password = self.0.security.password_hashing.hash(password).await;
         let res = execute!(
             &conn,
             "UPDATE users SET password = $1, salt = $2 WHERE id = $3",
-            params![&new_password.as_str(), &new_salt.as_str(), &(id as i64)]
+            params![&new_password.as_str(), &"", &(id as i64)]
         );
 
         if let Err(e) = res {
             return Err(Error::DatabaseError(e.to_string()));
         }
 
-        self.cache_clear_user(&user).await;
-
         Ok(())
     }
 
@@ -507,7 +616,7 @@ impl DataManager {
 
         if let Some(totp) = totp {
             return !code.is_empty()
-                && (totp.check_current(code).unwrap()
+                && (totp.check_current(code).unwrap_or(false)
                     | ua.recovery_codes.contains(&code.to_string()));
         }
 
@@ -607,11 +716,9 @@ impl DataManager {
                 .replace(":", "_"),
         ));
 
-        if totp.is_none() {
+        let Some(totp) = totp else {
             return Err(Error::MiscError("Failed to get TOTP code".to_string()));
-        }
-
-        let totp = totp.unwrap();
+        };
 
         // generate qr
         let qr = match totp.get_qr_base64() {
@@ -628,6 +735,129 @@ impl DataManager {
         self.2.remove(format!("atto.user:{}", user.username)).await;
     }
 
+    /// Clear the cached user with the given ID, if it exists.
+    pub async fn cache_clear_user_by_id(&self, id: usize) {
+        if let Ok(user) = self.get_user_by_id(id).await {
+            self.cache_clear_user(&user).await;
+        }
+    }
+
+    /// Record that the session with the given (hashed) token was just used.
+    ///
+    /// This is skipped if it was already recorded in the last few minutes
+    /// (according to the given `user`, so most requests don't touch the database),
+    /// or if the user's tokens changed since they were read (so sessions created
+    /// or removed in the meantime aren't overwritten).
+    ///
+    /// # Arguments
+    /// * `user` - the user the session belongs to
+    /// * `token` - the hashed token of the session
+    pub async fn touch_user_token(&self, user: &User, token: &str) -> Result<()> {
+        let now = unix_epoch_timestamp() as usize;
+
+        let Some(t) = user.tokens.iter().find(|t| t.token == token) else {
+            return Ok(());
+        };
+
+        if now.saturating_sub(t.last_used.max(t.created)) < SESSION_TOUCH_INTERVAL {
+            return Ok(());
+        }
+
+        let conn = match self.connect().await {
+            Ok(c) => c,
+            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+        };
+
+        let current = query_row!(
+            &conn,
+            "SELECT tokens FROM users WHERE id = $1",
+            &[&(user.id as i64)],
+            |x| Ok(get!(x->0(String)))
+        )?;
+
+        let Some(current) = current else {
+            return Err(Error::UserNotFound);
+        };
+
+        let mut tokens: Vec<Token> = match serde_json::from_str(&current) {
+            Ok(x) => x,
+            Err(e) => return Err(Error::DatabaseError(e.to_string())),
+        };
+
+        let Some(t) = tokens.iter_mut().find(|t| t.token == token) else {
+            return Ok(());
+        };
+
+        t.last_used = now;
+
+        let res = execute!(
+            &conn,
+            "UPDATE users SET tokens = $1 WHERE id = $2 AND tokens = $3",
+            params![
+                &serde_json::to_string(&tokens).unwrap(),
+                &(user.id as i64),
+                &current
+            ]
+        );
+
+        if let Err(e) = res {
+            return Err(Error::DatabaseError(e.to_string()));
+        }
+
+        self.cache_clear_user(user).await;
+        Ok(())
+    }
+
+    /// Remove the session with the given (hashed) token from a user.
+    ///
+    /// # Arguments
+    /// * `id` - the ID of the user
+    /// * `token` - the hashed token of the session
+    pub async fn delete_user_token(&self, id: usize, token: &str) -> Result<()> {
+        let mut tokens = self.get_user_by_id(id).await?.tokens;
+
+        let Some(index) = tokens.iter().position(|t| t.token == token) else {
+            return Err(Error::GeneralNotFound("session".to_string()));
+        };
+
+        tokens.remove(index);
+        self.update_user_tokens(id, tokens).await
+    }
+
+    /// Remove every session of a user, except the one with the given (hashed) token.
+    ///
+    /// # Arguments
+    /// * `id` - the ID of the user
+    /// * `keep` - the hashed token of the session to keep
+    pub async fn delete_user_tokens_except(&self, id: usize, keep: &str) -> Result<()> {
+        let mut tokens = self.get_user_by_id(id).await?.tokens;
+        tokens.retain(|t| t.token == keep);
+        self.update_user_tokens(id, tokens).await
+    }
+
+    /// Update the label of the session with the given (hashed) token.
+    ///
+    /// # Arguments
+    /// * `id` - the ID of the user
+    /// * `token` - the hashed token of the session
+    /// * `label` - the new label of the session
+    pub async fn update_user_token_label(&self, id: usize, token: &str, label: &str) -> Result<()> {
+        let label = label.trim();
+
+        if label.chars().count() > Token::MAX_LABEL_LEN {
+            return Err(Error::DataTooLong("label".to_string()));
+        }
+
+        let mut tokens = self.get_user_by_id(id).await?.tokens;
+
+        let Some(t) = tokens.iter_mut().find(|t| t.token == token) else {
+            return Err(Error::GeneralNotFound("session".to_string()));
+        };
+
+        t.label = label.to_string();
+        self.update_user_tokens(id, tokens).await
+    }
+
     auto_method!(update_user_tokens(Vec<Token>)@get_user_by_id -> "UPDATE users SET tokens = $1 WHERE id = $2" --serde --cache-key-tmpl=cache_clear_user);
     auto_method!(update_user_settings(UserSettings)@get_user_by_id -> "UPDATE users SET settings = $1 WHERE id = $2" --serde --cache-key-tmpl=cache_clear_user);
 
@@ -7,27 +7,38 @@ use crate::{
 use super::DataManager;
 
 impl DataManager {
+    /// Create all tables (if they don't already exist) and apply pending migrations.
     pub async fn init(&self) -> Result<()> {
         let conn = match self.connect().await {
             Ok(c) => c,
             Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
         };
 
-        execute!(&conn, common::CREATE_TABLE_USERS).unwrap();
-        execute!(&conn, common::CREATE_TABLE_COMMUNITIES).unwrap();
-        execute!(&conn, common::CREATE_TABLE_POSTS).unwrap();
-        execute!(&conn, common::CREATE_TABLE_MEMBERSHIPS).unwrap();
-        execute!(&conn, common::CREATE_TABLE_REACTIONS).unwrap();
-        execute!(&conn, common::CREATE_TABLE_NOTIFICATIONS).unwrap();
-        execute!(&conn, common::CREATE_TABLE_USERFOLLOWS).unwrap();
-        execute!(&conn, common::CREATE_TABLE_USERBLOCKS).unwrap();
-        execute!(&conn, common::CREATE_TABLE_IPBANS).unwrap();
-        execute!(&conn, common::CREATE_TABLE_AUDIT_LOG).unwrap();
-        execute!(&conn, common::CREATE_TABLE_REPORTS).unwrap();
-        execute!(&conn, common::CREATE_TABLE_USER_WARNINGS).unwrap();
-        execute!(&conn, common::CREATE_TABLE_REQUESTS).unwrap();
-        execute!(&conn, common::CREATE_TABLE_QUESTIONS).unwrap();
-        execute!(&conn, common::CREATE_TABLE_IPBLOCKS).unwrap();
+        for table in [
+            common::CREATE_TABLE_USERS,
+            common::CREATE_TABLE_COMMUNITIES,
+            common::CREATE_TABLE_POSTS,
+            common::CREATE_TABLE_MEMBERSHIPS,
+            common::CREATE_TABLE_REACTIONS,
+            common::CREATE_TABLE_NOTIFICATIONS,
+            common::CREATE_TABLE_USERFOLLOWS,
+            common::CREATE_TABLE_USERBLOCKS,
+            common::CREATE_TABLE_IPBANS,
+            common::CREATE_TABLE_AUDIT_LOG,
+            common::CREATE_TABLE_REPORTS,
+            common::CREATE_TABLE_USER_WARNINGS,
+            common::CREATE_TABLE_REQUESTS,
+            common::CREATE_TABLE_QUESTIONS,
+            common::CREATE_TABLE_IPBLOCKS,
+            common::CREATE_TABLE_POST_REVISIONS,
+            common::CREATE_TABLE_SCHEMA_VERSION,
+        ] {
+            if let Err(e) = execute!(&conn, table) {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
+        }
+
+        self.migrate().await?;
 
         Ok(())
     }
@@ -42,22 +53,23 @@ macro_rules! auto_method {
                 Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
             };
 
-            let res = query_row!(&conn, $query, &[&(id as i64)], |x| {
-                Ok(Self::$select_fn(x))
-            });
+            let res = query_row!(&conn, $query, &[&(id as i64)], |x| { Self::$select_fn(x) })?;
 
-            if res.is_err() {
+            let Some(res) = res else {
                 return Err(Error::GeneralNotFound($name_.to_string()));
-            }
+            };
 
-            Ok(res.unwrap())
+            Ok(res)
         }
     };
 
     ($name:ident()@$select_fn:ident -> $query:literal --name=$name_:literal --returns=$returns_:tt --cache-key-tmpl=$cache_key_tmpl:literal) => {
         pub async fn $name(&self, id: usize) -> Result<$returns_> {
-            if let Some(cached) = self.2.get(format!($cache_key_tmpl, id)).await {
-                return Ok(serde_json::from_str(&cached).unwrap());
+            // corrupt cache entries are replaced by the database row
+            if let Some(cached) = self.2.get(format!($cache_key_tmpl, id)).await
+                && let Ok(x) = serde_json::from_str(&cached)
+            {
+                return Ok(x);
             }
 
             let conn = match self.connect().await {
@@ -65,21 +77,15 @@ macro_rules! auto_method {
                 Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
             };
 
-            let res = query_row!(&conn, $query, &[&(id as i64)], |x| {
-                Ok(Self::$select_fn(x))
-            });
+            let res = query_row!(&conn, $query, &[&(id as i64)], |x| { Self::$select_fn(x) })?;
 
-            if res.is_err() {
+            let Some(x) = res else {
                 return Err(Error::GeneralNotFound($name_.to_string()));
-            }
+            };
 
-            let x = res.unwrap();
-            self.2
-                .set(
-                    format!($cache_key_tmpl, id),
-                    serde_json::to_string(&x).unwrap(),
-                )
-                .await;
+            if let Ok(value) = serde_json::to_string(&x) {
+                self.2.set(format!($cache_key_tmpl, id), value).await;
+            }
 
             Ok(x)
         }
@@ -92,13 +98,13 @@ macro_rules! auto_method {
                 Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
             };
 
-            let res = query_row!(&conn, $query, &[&selector], |x| { Ok(Self::$select_fn(x)) });
+            let res = query_row!(&conn, $query, &[&selector], |x| { Self::$select_fn(x) })?;
 
-            if res.is_err() {
+            let Some(res) = res else {
                 return Err(Error::GeneralNotFound($name_.to_string()));
-            }
+            };
 
-            Ok(res.unwrap())
+            Ok(res)
         }
     };
 
@@ -106,8 +112,10 @@ macro_rules! auto_method {
         pub async fn $name(&self, selector: $selector_t) -> Result<$returns_> {
             let selector = selector.to_string().to_lowercase();
 
-            if let Some(cached) = self.2.get(format!($cache_key_tmpl, selector)).await {
-                return Ok(serde_json::from_str(&cached).unwrap());
+            if let Some(cached) = self.2.get(format!($cache_key_tmpl, selector)).await
+                && let Ok(x) = serde_json::from_str(&cached)
+            {
+                return Ok(x);
             }
 
             let conn = match self.connect().await {
@@ -116,20 +124,16 @@ macro_rules! auto_method {
             };
 
             let res = query_row!(&conn, $query, &[&selector.to_string()], |x| {
-                Ok(Self::$select_fn(x))
-            });
+                Self::$select_fn(x)
+            })?;
 
-            if res.is_err() {
+            let Some(x) = res else {
                 return Err(Error::GeneralNotFound($name_.to_string()));
-            }
+            };
 
-            let x = res.unwrap();
-            self.2
-                .set(
-                    format!($cache_key_tmpl, selector),
-                    serde_json::to_string(&x).unwrap(),
-                )
-                .await;
+            if let Ok(value) = serde_json::to_string(&x) {
+                self.2.set(format!($cache_key_tmpl, selector), value).await;
+            }
 
             Ok(x)
         }
@@ -141,8 +145,9 @@ macro_rules! auto_method {
                 .2
                 .get(format!($cache_key_tmpl, selector.to_string()))
                 .await
+                && let Ok(x) = serde_json::from_str(&cached)
             {
-                return Ok(serde_json::from_str(&cached).unwrap());
+                return Ok(x);
             }
 
             let conn = match self.connect().await {
@@ -151,20 +156,16 @@ macro_rules! auto_method {
             };
 
             let res = query_row!(&conn, $query, &[&(selector as i64)], |x| {
-                Ok(Self::$select_fn(x))
-            });
+                Self::$select_fn(x)
+            })?;
 
-            if res.is_err() {
+            let Some(x) = res else {
                 return Err(Error::GeneralNotFound($name_.to_string()));
-            }
+            };
 
-            let x = res.unwrap();
-            self.2
-                .set(
-                    format!($cache_key_tmpl, selector),
-                    serde_json::to_string(&x).unwrap(),
-                )
-                .await;
+            if let Ok(value) = serde_json::to_string(&x) {
+                self.2.set(format!($cache_key_tmpl, selector), value).await;
+            }
 
             Ok(x)
         }
@@ -527,7 +528,7 @@ macro_rules! auto_method {
                 if !user.permissions.check(FinePermission::$permission) {
                     return Err(Error::NotAllowed);
                 } else {
-                    self.create_audit_log_entry(crate::model::moderation::AuditLogEntry::new(
+                    self.create_audit_log_entry($crate::model::moderation::AuditLogEntry::new(
                         user.id,
                         format!("invoked `{}` with x value `{x}`", stringify!($name)),
                     ))
@@ -606,7 +607,7 @@ macro_rules! auto_method {
                 if !user.permissions.check(FinePermission::$permission) {
                     return Err(Error::NotAllowed);
                 } else {
-                    self.create_audit_log_entry(crate::model::moderation::AuditLogEntry::new(
+                    self.create_audit_log_entry($crate::model::moderation::AuditLogEntry::new(
                         user.id,
                         format!("invoked `{}` with x value `{x:?}`", stringify!($name)),
                     ))
@@ -1,4 +1,5 @@
 use super::*;
+use super::feeds::POPULAR_COMMUNITIES;
 use crate::cache::Cache;
 use crate::model::communities::{CommunityContext, CommunityJoinAccess, CommunityMembership};
 use crate::model::communities_permissions::CommunityPermission;
@@ -10,8 +11,7 @@ use crate::model::{
     permissions::FinePermission,
 };
 use crate::{auto_method, execute, get, query_row, query_rows, params};
-use pathbufd::PathBufD;
-use std::fs::{exists, remove_file};
+use tetratto_shared::unix_epoch_timestamp;
 
 #[cfg(feature = "sqlite")]
 use rusqlite::Row;
@@ -24,22 +24,23 @@ impl DataManager {
     pub(crate) fn get_community_from_row(
         #[cfg(feature = "sqlite")] x: &Row<'_>,
         #[cfg(feature = "postgres")] x: &Row,
-    ) -> Community {
-        Community {
+    ) -> Result<Community> {
+        Ok(Community {
             id: get!(x->0(i64)) as usize,
             created: get!(x->1(i64)) as usize,
             title: get!(x->2(String)),
-            context: serde_json::from_str(&get!(x->3(String))).unwrap(),
+            context: get!(x->3(json)),
             owner: get!(x->4(i64)) as usize,
-            read_access: serde_json::from_str(&get!(x->5(String))).unwrap(),
-            write_access: serde_json::from_str(&get!(x->6(String))).unwrap(),
-            join_access: serde_json::from_str(&get!(x->7(String))).unwrap(),
+            read_access: get!(x->5(json)),
+            write_access: get!(x->6(json)),
+            join_access: get!(x->7(json)),
             // likes
             likes: get!(x->8(i32)) as isize,
             dislikes: get!(x->9(i32)) as isize,
             // counts
             member_count: get!(x->10(i32)) as usize,
-        }
+            deleted_at: get!(x->12(i64)) as usize,
+        })
     }
 
     pub async fn get_community_by_id(&self, id: usize) -> Result<Community> {
@@ -47,8 +48,10 @@ impl DataManager {
             return Ok(Community::void());
         }
 
-        if let Some(cached) = self.2.get(format!("atto.community:{}", id)).await {
-            return Ok(serde_json::from_str(&cached).unwrap());
+        if let Some(cached) = self.2.get(format!("atto.community:{}", id)).await
+            && let Ok(x) = serde_json::from_str(&cached)
+        {
+            return Ok(x);
         }
 
         let conn = match self.connect().await {
@@ -58,23 +61,19 @@ impl DataManager {
 
         let res = query_row!(
             &conn,
-            "SELECT * FROM communities WHERE id = $1",
+            "SELECT * FROM communities WHERE deleted_at = 0 AND id = $1",
             &[&(id as i64)],
-            |x| { Ok(Self::get_community_from_row(x)) }
-        );
+            |x| { Self::get_community_from_row(x) }
+        )?;
 
-        if res.is_err() {
+        let Some(x) = res else {
             return Ok(Community::void());
             // return Err(Error::GeneralNotFound("community".to_string()));
-        }
+        };
 
-        let x = res.unwrap();
-        self.2
-            .set(
-                format!("atto.community:{}", id),
-                serde_json::to_string(&x).unwrap(),
-            )
-            .await;
+        if let Ok(value) = serde_json::to_string(&x) {
+            self.2.set(format!("atto.community:{}", id), value).await;
+        }
 
         Ok(x)
     }
@@ -84,8 +83,10 @@ impl DataManager {
             return Ok(Community::void());
         }
 
-        if let Some(cached) = self.2.get(format!("atto.community:{}", id)).await {
-            return Ok(serde_json::from_str(&cached).unwrap());
+        if let Some(cached) = self.2.get(format!("atto.community:{}", id)).await
+            && let Ok(x) = serde_json::from_str(&cached)
+        {
+            return Ok(x);
         }
 
         let conn = match self.connect().await {
@@ -95,54 +96,50 @@ impl DataManager {
 
         let res = query_row!(
             &conn,
-            "SELECT * FROM communities WHERE title = $1",
+            "SELECT * FROM communities WHERE deleted_at = 0 AND title = $1",
             params![&id],
-            |x| { Ok(Self::get_community_from_row(x)) }
-        );
+            |x| { Self::get_community_from_row(x) }
+        )?;
 
-        if res.is_err() {
+        let Some(x) = res else {
             return Ok(Community::void());
             // return Err(Error::GeneralNotFound("community".to_string()));
-        }
+        };
 
-        let x = res.unwrap();
-        self.2
-            .set(
-                format!("atto.community:{}", id),
-                serde_json::to_string(&x).unwrap(),
-            )
-            .await;
+        if let Ok(value) = serde_json::to_string(&x) {
+            self.2.set(format!("atto.community:{}", id), value).await;
+        }
 
         Ok(x)
     }
 
-    auto_method!(get_community_by_id_no_void()@get_community_from_row -> "SELECT * FROM communities WHERE id = $1" --name="community" --returns=Community --cache-key-tmpl="atto.community:{}");
-    auto_method!(get_community_by_title_no_void(&str)@get_community_from_row -> "SELECT * FROM communities WHERE title = $1" --name="community" --returns=Community --cache-key-tmpl="atto.community:{}");
+    auto_method!(get_community_by_id_no_void()@get_community_from_row -> "SELECT * FROM communities WHERE deleted_at = 0 AND id = $1" --name="community" --returns=Community --cache-key-tmpl="atto.community:{}");
+    auto_method!(get_community_by_title_no_void(&str)@get_community_from_row -> "SELECT * FROM communities WHERE deleted_at = 0 AND title = $1" --name="community" --returns=Community --cache-key-tmpl="atto.community:{}");
+    auto_method!(get_deleted_community_by_id()@get_community_from_row -> "SELECT * FROM communities WHERE NOT deleted_at = 0 AND id = $1" --name="community" --returns=Community);
 
-    /// Get the top 12 most popular (most likes) communities.
+    /// Get the top 12 most popular (most likes) communities. Cached for up to 5 minutes.
     pub async fn get_popular_communities(&self) -> Result<Vec<Community>> {
-        let conn = match self.connect().await {
-            Ok(c) => c,
-            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
-        };
+        self.get_feed_page(&POPULAR_COMMUNITIES, "top".to_string(), move || async move {
+            let conn = match self.connect().await {
+                Ok(c) => c,
+                Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+            };
 
-        #[cfg(feature = "sqlite")]
-        let empty = [];
-        #[cfg(feature = "postgres")]
-        let empty = &[];
+            #[cfg(feature = "sqlite")]
+            let empty = [];
+            #[cfg(feature = "postgres")]
+            let empty = &[];
 
-        let res = query_rows!(
-            &conn,
-            "SELECT * FROM communities WHERE NOT context LIKE '%\"is_nsfw\":true%' ORDER BY member_count DESC LIMIT 12",
-            empty,
-            |x| { Self::get_community_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("communities".to_string()));
-        }
+            let res = query_rows!(
+                &conn,
+                "SELECT * FROM communities WHERE deleted_at = 0 AND is_nsfw = 0 ORDER BY member_count DESC LIMIT 12",
+                empty,
+                |x| { Self::get_community_from_row(x) }
+            )?;
 
-        Ok(res.unwrap())
+            Ok(res)
+        })
+        .await
     }
 
     /// Get all communities, filtering their title.
@@ -160,20 +157,16 @@ impl DataManager {
 
         let res = query_rows!(
             &conn,
-            "SELECT * FROM communities WHERE title LIKE $1 ORDER BY member_count DESC, created DESC LIMIT $2 OFFSET $3",
+            "SELECT * FROM communities WHERE deleted_at = 0 AND title LIKE $1 ORDER BY member_count DESC, created DESC LIMIT $2 OFFSET $3",
             params![
                 &format!("%{query}%"),
                 &(batch as i64),
                 &((page * batch) as i64)
             ],
             |x| { Self::get_community_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("communities".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Create a new community in the database.
@@ -234,45 +227,58 @@ impl DataManager {
             .await
             .is_ok()
         {
-            return Err(Error::MiscError("Title already in use".to_string()));
+            return Err(Error::TitleInUse);
         }
 
         // ...
-        let conn = match self.connect().await {
-            Ok(c) => c,
-            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
-        };
-
-        let res = execute!(
-            &conn,
-            "INSERT INTO communities VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
-            params![
-                &(data.id as i64),
-                &(data.created as i64),
-                &data.title.to_lowercase(),
-                &serde_json::to_string(&data.context).unwrap().as_str(),
-                &(data.owner as i64),
-                &serde_json::to_string(&data.read_access).unwrap().as_str(),
-                &serde_json::to_string(&data.write_access).unwrap().as_str(),
-                &serde_json::to_string(&data.join_access).unwrap().as_str(),
-                &0_i32,
-                &0_i32,
-                &1_i32
-            ]
-        );
+        self.transaction(async |conn| {
+            let res = execute!(
+                conn,
+                "INSERT INTO communities VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)",
+                params![
+                    &(data.id as i64),
+                    &(data.created as i64),
+                    &data.title.to_lowercase(),
+                    &serde_json::to_string(&data.context).unwrap().as_str(),
+                    &(data.owner as i64),
+                    &serde_json::to_string(&data.read_access).unwrap().as_str(),
+                    &serde_json::to_string(&data.write_access).unwrap().as_str(),
+                    &serde_json::to_string(&data.join_access).unwrap().as_str(),
+                    &0_i32,
+                    &0_i32,
+                    &1_i32,
+                    &{ if data.context.is_nsfw { 1 } else { 0 } },
+                    &0_i64
+                ]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+            // add community owner as admin (already counted in the member count above)
+            let membership =
+                CommunityMembership::new(data.owner, data.id, CommunityPermission::ADMINISTRATOR);
+
+            let res = execute!(
+                conn,
+                "INSERT INTO memberships VALUES ($1, $2, $3, $4, $5)",
+                params![
+                    &(membership.id as i64),
+                    &(membership.created as i64),
+                    &(membership.owner as i64),
+                    &(membership.community as i64),
+                    &(membership.role.bits() as i32),
+                ]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
-        // add journal page owner as admin
-        self.create_membership(CommunityMembership::new(
-            data.owner,
-            data.id,
-            CommunityPermission::ADMINISTRATOR,
-        ))
-        .await
-        .unwrap();
+            Ok(())
+        })
+        .await?;
 
         // return
         Ok(data.title)
@@ -302,6 +308,7 @@ impl DataManager {
             }
         }
 
+        // memberships and images are kept until the community is purged
         let conn = match self.connect().await {
             Ok(c) => c,
             Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
@@ -309,8 +316,8 @@ impl DataManager {
 
         let res = execute!(
             &conn,
-            "DELETE FROM communities WHERE id = $1",
-            &[&(id as i64)]
+            "UPDATE communities SET deleted_at = $1 WHERE id = $2",
+            &[&(unix_epoch_timestamp() as i64), &(id as i64)]
         );
 
         if let Err(e) = res {
@@ -318,11 +325,43 @@ impl DataManager {
         }
 
         self.cache_clear_community(&y).await;
+        self.invalidate_feed(&POPULAR_COMMUNITIES).await;
+
+        // ...
+        Ok(())
+    }
+
+    /// Restore a deleted community (if its restore window hasn't passed).
+    pub async fn restore_community(&self, id: usize, user: User) -> Result<()> {
+        let y = self.get_deleted_community_by_id(id).await?;
+        self.check_restore_window(y.deleted_at)?;
+
+        if user.id != y.owner {
+            if !user.permissions.check(FinePermission::MANAGE_COMMUNITIES) {
+                return Err(Error::NotAllowed);
+            } else {
+                self.create_audit_log_entry(crate::model::moderation::AuditLogEntry::new(
+                    user.id,
+                    format!("invoked `restore_community` with x value `{id}`"),
+                ))
+                .await?
+            }
+        }
+
+        // the title may have been taken since the community was deleted
+        if self.get_community_by_title_no_void(&y.title).await.is_ok() {
+            return Err(Error::TitleInUse);
+        }
+
+        // ...
+        let conn = match self.connect().await {
+            Ok(c) => c,
+            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+        };
 
-        // remove memberships
         let res = execute!(
             &conn,
-            "DELETE FROM memberships WHERE community = $1",
+            "UPDATE communities SET deleted_at = 0 WHERE id = $1",
             &[&(id as i64)]
         );
 
@@ -330,26 +369,7 @@ impl DataManager {
             return Err(Error::DatabaseError(e.to_string()));
         }
 
-        // remove images
-        let avatar = PathBufD::current().extend(&[
-            self.0.dirs.media.as_str(),
-            "community_avatars",
-            &format!("{}.avif", &y.id),
-        ]);
-
-        let banner = PathBufD::current().extend(&[
-            self.0.dirs.media.as_str(),
-            "community_banners",
-            &format!("{}.avif", &y.id),
-        ]);
-
-        if exists(&avatar).unwrap() {
-            remove_file(avatar).unwrap();
-        }
-
-        if exists(&banner).unwrap() {
-            remove_file(banner).unwrap();
-        }
+        self.cache_clear_community(&y).await;
 
         // ...
         Ok(())
@@ -397,7 +417,50 @@ impl DataManager {
         Ok(())
     }
 
-    auto_method!(update_community_context(CommunityContext)@get_community_by_id_no_void:MANAGE_COMMUNITIES -> "UPDATE communities SET context = $1 WHERE id = $2" --serde --cache-key-tmpl=cache_clear_community);
+    pub async fn update_community_context(
+        &self,
+        id: usize,
+        user: User,
+        x: CommunityContext,
+    ) -> Result<()> {
+        let y = self.get_community_by_id_no_void(id).await?;
+
+        if user.id != y.owner {
+            if !user.permissions.check(FinePermission::MANAGE_COMMUNITIES) {
+                return Err(Error::NotAllowed);
+            } else {
+                self.create_audit_log_entry(crate::model::moderation::AuditLogEntry::new(
+                    user.id,
+                    format!("invoked `update_community_context` with x value `{x:?}`"),
+                ))
+                .await?
+            }
+        }
+
+        let conn = match self.connect().await {
+            Ok(c) => c,
+            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+        };
+
+        let res = execute!(
+            &conn,
+            "UPDATE communities SET context = $1, is_nsfw = $2 WHERE id = $3",
+            params![
+                &serde_json::to_string(&x).unwrap(),
+                &{ if x.is_nsfw { 1 } else { 0 } },
+                &(id as i64)
+            ]
+        );
+
+        if let Err(e) = res {
+            return Err(Error::DatabaseError(e.to_string()));
+        }
+
+        self.cache_clear_community(&y).await;
+
+        Ok(())
+    }
+
     auto_method!(update_community_read_access(CommunityReadAccess)@get_community_by_id_no_void:MANAGE_COMMUNITIES -> "UPDATE communities SET read_access = $1 WHERE id = $2" --serde --cache-key-tmpl=cache_clear_community);
     auto_method!(update_community_write_access(CommunityWriteAccess)@get_community_by_id_no_void:MANAGE_COMMUNITIES -> "UPDATE communities SET write_access = $1 WHERE id = $2" --serde --cache-key-tmpl=cache_clear_community);
     auto_method!(update_community_join_access(CommunityJoinAccess)@get_community_by_id_no_void:MANAGE_COMMUNITIES -> "UPDATE communities SET join_access = $1 WHERE id = $2" --serde --cache-key-tmpl=cache_clear_community);
@@ -0,0 +1,280 @@
+use super::*;
+use crate::cache::Cache;
+use crate::model::{
+    Error, Result, communities_permissions::CommunityPermission, moderation::CounterChange,
+    reactions::AssetType,
+};
+use crate::{execute, get, query_rows, params};
+
+#[cfg(feature = "sqlite")]
+use rusqlite::Row;
+
+#[cfg(feature = "postgres")]
+use tokio_postgres::Row;
+
+/// The number of rows corrected in each transaction by a reconcile.
+const RECONCILE_BATCH_SIZE: usize = 500;
+
+/// A denormalized counter column, along with the SQL used to compute its real value
+/// from the table it is derived from.
+struct Counter {
+    asset_type: AssetType,
+    table: &'static str,
+    column: &'static str,
+    /// An expression which computes the value of the counter for the row being
+    /// selected from `table`.
+    expr: String,
+}
+
+impl Counter {
+    fn new(asset_type: AssetType, table: &'static str, column: &'static str, expr: &str) -> Self {
+        Self {
+            asset_type,
+            table,
+            column,
+            expr: expr.to_string(),
+        }
+    }
+
+    /// Count the likes (or dislikes) given to assets from `table`.
+    fn reactions(asset_type: AssetType, table: &'static str, is_like: bool) -> Self {
+        Self {
+            asset_type,
+            table,
+            column: if is_like { "likes" } else { "dislikes" },
+            expr: format!(
+                "SELECT COUNT(*) FROM reactions WHERE asset = {table}.id AND asset_type = '{}' AND is_like = {}",
+                serde_json::to_string(&asset_type).unwrap(),
+                if is_like { 1 } else { 0 }
+            ),
+        }
+    }
+}
+
+/// Every denormalized counter we keep.
+fn counters() -> Vec<Counter> {
+    vec![
+        // users
+        Counter::new(
+            AssetType::User,
+            "users",
+            "follower_count",
+            "SELECT COUNT(*) FROM userfollows WHERE receiver = users.id",
+        ),
+        Counter::new(
+            AssetType::User,
+            "users",
+            "following_count",
+            "SELECT COUNT(*) FROM userfollows WHERE initiator = users.id",
+        ),
+        Counter::new(
+            AssetType::User,
+            "users",
+            "post_count",
+            "SELECT COUNT(*) FROM posts WHERE owner = users.id AND deleted_at = 0",
+        ),
+        Counter::new(
+            AssetType::User,
+            "users",
+            "notification_count",
+            "SELECT COUNT(*) FROM notifications WHERE owner = users.id AND read = 0",
+        ),
+        Counter::new(
+            AssetType::User,
+            "users",
+            "request_count",
+            "SELECT COUNT(*) FROM requests WHERE owner = users.id",
+        ),
+        // communities
+        // (requested and banned members don't count towards the member count)
+        Counter::new(
+            AssetType::Community,
+            "communities",
+            "member_count",
+            &format!(
+                "SELECT COUNT(*) FROM memberships WHERE community = communities.id AND (role & {}) = 0 AND (role & {}) = 0",
+                CommunityPermission::REQUESTED.bits(),
+                CommunityPermission::BANNED.bits()
+            ),
+        ),
+        Counter::reactions(AssetType::Community, "communities", true),
+        Counter::reactions(AssetType::Community, "communities", false),
+        // posts
+        Counter::new(
+            AssetType::Post,
+            "posts",
+            "comment_count",
+            "SELECT COUNT(*) FROM posts AS replies WHERE replies.replying_to = posts.id AND replies.deleted_at = 0",
+        ),
+        Counter::reactions(AssetType::Post, "posts", true),
+        Counter::reactions(AssetType::Post, "posts", false),
+        // questions
+        // (only global questions keep track of their answers)
+        Counter::new(
+            AssetType::Question,
+            "questions",
+            "answer_count",
+            "CASE WHEN questions.is_global = 1 THEN (SELECT COUNT(*) FROM posts WHERE posts.answering = questions.id AND posts.deleted_at = 0) ELSE 0 END",
+        ),
+        Counter::reactions(AssetType::Question, "questions", true),
+        Counter::reactions(AssetType::Question, "questions", false),
+    ]
+}
+
+impl DataManager {
+    /// Get a [`CounterChange`] from an SQL row.
+    fn get_counter_change_from_row(
+        #[cfg(feature = "sqlite")] x: &Row<'_>,
+        #[cfg(feature = "postgres")] x: &Row,
+        counter: &Counter,
+    ) -> Result<CounterChange> {
+        Ok(CounterChange {
+            asset: get!(x->0(i64)) as usize,
+            asset_type: counter.asset_type,
+            counter: counter.column.to_string(),
+            old: get!(x->1(i32)),
+            new: get!(x->2(i32)),
+        })
+    }
+
+    /// Recompute every denormalized counter on the instance from its source table.
+    ///
+    /// Wrong counters are found without locking anything, then corrected in
+    /// batches of [`RECONCILE_BATCH_SIZE`], each in its own transaction, so other
+    /// writes only wait on a single batch.
+    ///
+    /// # Returns
+    /// Every counter which was changed.
+    pub async fn reconcile_counters(&self) -> Result<Vec<CounterChange>> {
+        self.reconcile_counters_where(None).await
+    }
+
+    /// Recompute the denormalized counters of a single asset from their source tables.
+    ///
+    /// # Returns
+    /// Every counter which was changed.
+    pub async fn reconcile_asset_counters(
+        &self,
+        asset_type: AssetType,
+        id: usize,
+    ) -> Result<Vec<CounterChange>> {
+        self.reconcile_counters_where(Some((asset_type, id))).await
+    }
+
+    async fn reconcile_counters_where(
+        &self,
+        asset: Option<(AssetType, usize)>,
+    ) -> Result<Vec<CounterChange>> {
+        let counters: Vec<Counter> = counters()
+            .into_iter()
+            .filter(|x| match asset {
+                Some((asset_type, _)) => x.asset_type == asset_type,
+                None => true,
+            })
+            .collect();
+
+        let mut changes: Vec<CounterChange> = Vec::new();
+
+        for counter in &counters {
+            let query = format!(
+                "SELECT id, {column}, CAST(({expr}) AS INT) FROM {table} WHERE {column} <> CAST(({expr}) AS INT)",
+                table = counter.table,
+                column = counter.column,
+                expr = counter.expr
+            );
+
+            let conn = match self.connect().await {
+                Ok(c) => c,
+                Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+            };
+
+            let wrong = match asset {
+                Some((_, id)) => query_rows!(
+                    &conn,
+                    &format!("{query} AND id = $1"),
+                    &[&(id as i64)],
+                    |x| { Self::get_counter_change_from_row(x, counter) }
+                )?,
+                None => query_rows!(&conn, &query, params![], |x| {
+                    Self::get_counter_change_from_row(x, counter)
+                })?,
+            };
+
+            drop(conn);
+
+            // the value is computed again when it's written, since it could have
+            // changed since it was selected
+            let update = format!(
+                "UPDATE {table} SET {column} = CAST(({expr}) AS INT) WHERE id = $1",
+                table = counter.table,
+                column = counter.column,
+                expr = counter.expr
+            );
+
+            for batch in wrong.chunks(RECONCILE_BATCH_SIZE) {
+                self.transaction(async |conn| {
+                    for change in batch {
+                        if let Err(e) = execute!(conn, &update, &[&(change.asset as i64)]) {
+                            return Err(Error::DatabaseError(e.to_string()));
+                        }
+                    }
+
+                    Ok(())
+                })
+                .await?;
+            }
+
+            changes.extend(wrong);
+        }
+
+        // clear cache
+        if asset.is_none() {
+            // a full reconcile can change a lot of rows, so it's cheaper to drop
+            // every cached object of the changed types
+            let mut types: Vec<AssetType> = Vec::new();
+
+            for change in &changes {
+                if !types.contains(&change.asset_type) {
+                    types.push(change.asset_type);
+                }
+            }
+
+            for asset_type in types {
+                self.2
+                    .bump_namespace(
+                        match asset_type {
+                            AssetType::User => "atto.user",
+                            AssetType::Community => "atto.community",
+                            AssetType::Post => "atto.post",
+                            AssetType::Question => "atto.question",
+                        }
+                        .to_string(),
+                    )
+                    .await;
+            }
+
+            return Ok(changes);
+        }
+
+        for change in &changes {
+            match change.asset_type {
+                AssetType::User => self.cache_clear_user_by_id(change.asset).await,
+                AssetType::Community => {
+                    if let Ok(community) = self.get_community_by_id_no_void(change.asset).await {
+                        self.cache_clear_community(&community).await;
+                    }
+                }
+                AssetType::Post => {
+                    self.2.remove(format!("atto.post:{}", change.asset)).await;
+                }
+                AssetType::Question => {
+                    self.2
+                        .remove(format!("atto.question:{}", change.asset))
+                        .await;
+                }
+            }
+        }
+
+        Ok(changes)
+    }
+}
@@ -0,0 +1,178 @@
+use super::*;
+use crate::cache::Cache;
+use crate::model::{Error, Result, reactions::AssetType, requests::ActionType};
+use crate::{execute, query_rows};
+use pathbufd::PathBufD;
+use std::fs::{exists, remove_file};
+use tetratto_shared::unix_epoch_timestamp;
+
+/// Select the IDs of the communities being purged (with the cutoff as `$1`).
+const PURGED_COMMUNITIES: &str =
+    "SELECT id FROM communities WHERE NOT deleted_at = 0 AND deleted_at < $1";
+
+/// Select the IDs of the posts being purged (with the cutoff as `$1`).
+const PURGED_POSTS: &str = "SELECT id FROM posts WHERE (NOT deleted_at = 0 AND deleted_at < $1) OR community IN (SELECT id FROM communities WHERE NOT deleted_at = 0 AND deleted_at < $1)";
+
+/// Select the IDs of the questions being purged (with the cutoff as `$1`).
+const PURGED_QUESTIONS: &str = "SELECT id FROM questions WHERE (NOT deleted_at = 0 AND deleted_at < $1) OR community IN (SELECT id FROM communities WHERE NOT deleted_at = 0 AND deleted_at < $1)";
+
+/// Count the visible posts of a user (from the `users` table) in the communities
+/// being purged.
+const COMMUNITY_POST_COUNT: &str = "(SELECT COUNT(*) FROM posts WHERE owner = users.id AND deleted_at = 0 AND community IN (SELECT id FROM communities WHERE NOT deleted_at = 0 AND deleted_at < $1))";
+
+impl DataManager {
+    /// Check if an item deleted at the given timestamp can still be restored.
+    pub(crate) fn check_restore_window(&self, deleted_at: usize) -> Result<()> {
+        if (unix_epoch_timestamp() as usize).saturating_sub(deleted_at) > self.0.restore_window {
+            return Err(Error::MiscError(
+                "Restore window has already passed".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Permanently delete every post, question and community which was deleted
+    /// longer than `restore_window` ago.
+    ///
+    /// Purging a community also deletes every post and question in it. Everything
+    /// which depends on a purged item (reactions, requests, reports, notifications
+    /// linking to it, revisions) is deleted along with it.
+    pub async fn purge_deleted(&self) -> Result<()> {
+        let cutoff = (unix_epoch_timestamp() as usize).saturating_sub(self.0.restore_window) as i64;
+
+        let conn = match self.connect().await {
+            Ok(c) => c,
+            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+        };
+
+        let communities = query_rows!(
+            &conn,
+            "SELECT * FROM communities WHERE NOT deleted_at = 0 AND deleted_at < $1",
+            &[&cutoff],
+            |x| { Self::get_community_from_row(x) }
+        )?;
+
+        // posts which were still visible in purged communities are cached, and
+        // their owners' post counts still include them
+        let community_posts = query_rows!(
+            &conn,
+            &format!(
+                "SELECT * FROM posts WHERE deleted_at = 0 AND community IN ({PURGED_COMMUNITIES})"
+            ),
+            &[&cutoff],
+            |x| { Self::get_post_from_row(x) }
+        )?;
+
+        // answer requests for purged questions and join requests for purged communities
+        let purged_requests = format!(
+            "(action_type = '{}' AND linked_asset IN ({PURGED_QUESTIONS})) OR (action_type = '{}' AND linked_asset IN ({PURGED_COMMUNITIES}))",
+            serde_json::to_string(&ActionType::Answer).unwrap(),
+            serde_json::to_string(&ActionType::CommunityJoin).unwrap()
+        );
+
+        let requests = query_rows!(
+            &conn,
+            &format!("SELECT * FROM requests WHERE {purged_requests}"),
+            &[&cutoff],
+            |x| { Self::get_request_from_row(x) }
+        )?;
+
+        drop(conn);
+
+        self.transaction(async |conn| {
+            let mut queries: Vec<String> = vec![
+                format!("DELETE FROM post_revisions WHERE post IN ({PURGED_POSTS})"),
+                format!(
+                    "DELETE FROM notifications WHERE EXISTS (SELECT 1 FROM posts WHERE posts.id IN ({PURGED_POSTS}) AND notifications.content LIKE '%(/post/' || posts.id || ')%')"
+                ),
+                format!(
+                    "DELETE FROM notifications WHERE EXISTS (SELECT 1 FROM questions WHERE questions.id IN ({PURGED_QUESTIONS}) AND notifications.content LIKE '%(/question/' || questions.id || ')%')"
+                ),
+                format!(
+                    "UPDATE users SET post_count = CASE WHEN post_count > {COMMUNITY_POST_COUNT} THEN post_count - {COMMUNITY_POST_COUNT} ELSE 0 END WHERE id IN (SELECT owner FROM posts WHERE deleted_at = 0 AND community IN ({PURGED_COMMUNITIES}))"
+                ),
+            ];
+
+            for (asset_type, purged) in [
+                (AssetType::Post, PURGED_POSTS),
+                (AssetType::Question, PURGED_QUESTIONS),
+                (AssetType::Community, PURGED_COMMUNITIES),
+            ] {
+                let asset_type = serde_json::to_string(&asset_type).unwrap();
+
+                for table in ["reactions", "reports"] {
+                    queries.push(format!(
+                        "DELETE FROM {table} WHERE asset_type = '{asset_type}' AND asset IN ({purged})"
+                    ));
+                }
+            }
+
+            queries.push(format!("DELETE FROM requests WHERE {purged_requests}"));
+
+            // items themselves (communities last, since the others are selected by them)
+            queries.push(format!("DELETE FROM posts WHERE id IN ({PURGED_POSTS})"));
+            queries.push(format!(
+                "DELETE FROM questions WHERE id IN ({PURGED_QUESTIONS})"
+            ));
+            queries.push(format!(
+                "DELETE FROM memberships WHERE community IN ({PURGED_COMMUNITIES})"
+            ));
+            queries.push(format!("DELETE FROM communities WHERE id IN ({PURGED_COMMUNITIES})"));
+
+            for query in queries {
+                if let Err(e) = execute!(conn, &query, &[&cutoff]) {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
+            }
+
+            Ok(())
+        })
+        .await?;
+
+        // clear cache
+        for post in community_posts {
+            self.2.remove(format!("atto.post:{}", post.id)).await;
+            self.cache_clear_user_by_id(post.owner).await;
+        }
+
+        for request in requests {
+            self.2.remove(format!("atto.request:{}", request.id)).await;
+            self.2
+                .remove(format!(
+                    "atto.request:{}:{}",
+                    request.id, request.linked_asset
+                ))
+                .await;
+        }
+
+        // remove images
+        for community in communities {
+            let avatar = PathBufD::current().extend(&[
+                self.0.dirs.media.as_str(),
+                "community_avatars",
+                &format!("{}.avif", &community.id),
+            ]);
+
+            let banner = PathBufD::current().extend(&[
+                self.0.dirs.media.as_str(),
+                "community_banners",
+                &format!("{}.avif", &community.id),
+            ]);
+
+            if exists(&avatar).unwrap_or(false)
+                && let Err(e) = remove_file(avatar)
+            {
+                return Err(Error::MiscError(e.to_string()));
+            }
+
+            if exists(&banner).unwrap_or(false)
+                && let Err(e) = remove_file(banner)
+            {
+                return Err(Error::MiscError(e.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+}
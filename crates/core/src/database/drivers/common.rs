@@ -13,3 +13,5 @@ pub const CREATE_TABLE_USER_WARNINGS: &str = include_str!("./sql/create_user_war
 pub const CREATE_TABLE_REQUESTS: &str = include_str!("./sql/create_requests.sql");
 pub const CREATE_TABLE_QUESTIONS: &str = include_str!("./sql/create_questions.sql");
 pub const CREATE_TABLE_IPBLOCKS: &str = include_str!("./sql/create_ipblocks.sql");
+pub const CREATE_TABLE_POST_REVISIONS: &str = include_str!("./sql/create_post_revisions.sql");
+pub const CREATE_TABLE_SCHEMA_VERSION: &str = include_str!("./sql/create_schema_version.sql");
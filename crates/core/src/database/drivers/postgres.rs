@@ -1,4 +1,6 @@
-#[cfg(not(feature = "redis"))]
+#[cfg(all(feature = "memory", not(feature = "redis")))]
+use crate::cache::memory::MemoryCache;
+#[cfg(not(any(feature = "redis", feature = "memory")))]
 use crate::cache::no_cache::NoCache;
 #[cfg(feature = "redis")]
 use crate::cache::redis::RedisCache;
@@ -6,9 +8,11 @@ use crate::cache::redis::RedisCache;
 use crate::cache::Cache;
 
 use crate::config::Config;
+use crate::execute;
+use crate::model::Error;
 use bb8_postgres::{
     PostgresConnectionManager,
-    bb8::{Pool, PooledConnection},
+    bb8::{Pool, PooledConnection, RunError},
 };
 use std::collections::HashMap;
 use tetratto_l10n::{LangFile, read_langs};
@@ -23,18 +27,63 @@ pub struct DataManager(
     pub Config,
     pub HashMap<String, LangFile>,
     #[cfg(feature = "redis")] pub RedisCache,
-    #[cfg(not(feature = "redis"))] pub NoCache,
+    #[cfg(all(feature = "memory", not(feature = "redis")))] pub MemoryCache,
+    #[cfg(not(any(feature = "redis", feature = "memory")))] pub NoCache,
     pub Pool<PostgresConnectionManager<NoTls>>,
 );
 
 impl DataManager {
     /// Obtain a connection to the staging database.
-    pub(crate) async fn connect(&self) -> Result<Connection> {
-        Ok(self.3.get().await.unwrap())
+    pub(crate) async fn connect(
+        &self,
+    ) -> std::result::Result<Connection<'_>, RunError<tokio_postgres::Error>> {
+        self.3.get().await
+    }
+
+    /// Run `f` inside of a transaction on a single connection. The transaction is
+    /// committed if `f` returns `Ok`, and rolled back otherwise.
+    ///
+    /// Every write made in `f` **must** go through the given connection.
+    pub(crate) async fn transaction<T>(
+        &self,
+        f: impl AsyncFnOnce(&mut Connection<'_>) -> crate::model::Result<T>,
+    ) -> crate::model::Result<T> {
+        let conn = match self.3.get_owned().await {
+            Ok(c) => c,
+            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+        };
+
+        if let Err(e) = execute!(&conn, "BEGIN") {
+            return Err(Error::DatabaseError(e.to_string()));
+        }
+
+        // rolls the transaction back if we're dropped before it's finished
+        let mut guard = OpenTransaction(Some(conn));
+        let conn = guard.0.as_mut().unwrap();
+
+        let res = match f(conn).await {
+            Ok(x) => match execute!(&conn, "COMMIT") {
+                Ok(_) => Ok(x),
+                Err(e) => {
+                    execute!(&conn, "ROLLBACK").ok();
+                    Err(Error::DatabaseError(e.to_string()))
+                }
+            },
+            Err(e) => {
+                execute!(&conn, "ROLLBACK").ok();
+                Err(e)
+            }
+        };
+
+        // the transaction is finished, the connection can go back to the pool as is
+        guard.0.take();
+        res
     }
 
     /// Create a new [`DataManager`] (and init database).
     pub async fn new(config: Config) -> Result<Self> {
+        tetratto_shared::snow::set_node_id(config.node_id);
+
         let con_url = &format!(
             "postgresql://{}:{}@{}/{}?target_session_attrs=read-write",
             config.database.user,
@@ -44,45 +93,99 @@ impl DataManager {
         );
 
         println!("attempting connection on: {con_url}");
-        let manager = PostgresConnectionManager::new(PgConfig::from_str(con_url).unwrap(), NoTls);
+        let manager = PostgresConnectionManager::new(PgConfig::from_str(con_url)?, NoTls);
 
-        let pool = Pool::builder().max_size(15).build(manager).await.unwrap();
+        let pool = Pool::builder()
+            .max_size(config.database.pool_size)
+            .build(manager)
+            .await?;
         Ok(Self(
             config.clone(),
             read_langs(),
             #[cfg(feature = "redis")]
-            RedisCache::new().await,
-            #[cfg(not(feature = "redis"))]
-            NoCache::new().await,
+            RedisCache::new(&config.cache).await,
+            #[cfg(all(feature = "memory", not(feature = "redis")))]
+            MemoryCache::new(&config.cache).await,
+            #[cfg(not(any(feature = "redis", feature = "memory")))]
+            NoCache::new(&config.cache).await,
             pool,
         ))
     }
 }
 
+/// A connection with an open transaction.
+///
+/// If this is dropped while still holding the connection (like when the future
+/// running the transaction is dropped partway), the transaction is rolled back
+/// before the connection goes back to the pool. Otherwise, later queries on the
+/// connection would run inside of the stale transaction.
+struct OpenTransaction(Option<Connection<'static>>);
+
+impl Drop for OpenTransaction {
+    fn drop(&mut self) {
+        let Some(conn) = self.0.take() else {
+            return;
+        };
+
+        // without a runtime the connection can't be used again anyway
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            // connections which fail this are closed, so the pool discards them
+            handle.spawn(async move { conn.batch_execute("ROLLBACK").await.ok() });
+        }
+    }
+}
+
 #[cfg(feature = "postgres")]
 #[macro_export]
 macro_rules! get {
+    ($row:ident->$idx:literal(json)) => {
+        match serde_json::from_str(&$crate::get!($row->$idx(String))) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err($crate::model::Error::DatabaseError(format!(
+                    "invalid JSON in column {}: {e}",
+                    $idx
+                )));
+            }
+        }
+    };
+
     ($row:ident->$idx:literal($t:ty)) => {
-        $row.get::<usize, Option<$t>>($idx).unwrap()
+        match $row.try_get::<usize, Option<$t>>($idx) {
+            Ok(Some(x)) => x,
+            Ok(None) => {
+                return Err($crate::model::Error::DatabaseError(format!(
+                    "unexpected NULL in column {}",
+                    $idx
+                )));
+            }
+            Err(e) => return Err($crate::model::Error::DatabaseError(e.to_string())),
+        }
     };
 }
 
+/// Select the first row of the given query. Returns [`None`] if the query didn't
+/// return any rows.
 pub async fn query_row_helper<T, F>(
     conn: &Connection<'_>,
     sql: &str,
     params: &[&(dyn ToSql + Sync)],
     f: F,
-) -> Result<T>
+) -> crate::model::Result<Option<T>>
 where
-    F: FnOnce(&Row) -> Result<T>,
+    F: FnOnce(&Row) -> crate::model::Result<T>,
 {
-    let query = conn.prepare(sql).await.unwrap();
-    let res = conn.query_one(&query, params).await;
+    let query = match conn.prepare(sql).await {
+        Ok(x) => x,
+        Err(e) => return Err(Error::DatabaseError(e.to_string())),
+    };
 
-    if let Ok(row) = res {
-        Ok(f(&row).unwrap())
-    } else {
-        Err(res.unwrap_err())
+    match conn.query(&query, params).await {
+        Ok(rows) => match rows.first() {
+            Some(row) => f(row).map(Some),
+            None => Ok(None),
+        },
+        Err(e) => Err(Error::DatabaseError(e.to_string())),
     }
 }
 
@@ -98,23 +201,26 @@ pub async fn query_rows_helper<T, F>(
     sql: &str,
     params: &[&(dyn ToSql + Sync)],
     mut f: F,
-) -> Result<Vec<T>>
+) -> crate::model::Result<Vec<T>>
 where
-    F: FnMut(&Row) -> T,
+    F: FnMut(&Row) -> crate::model::Result<T>,
 {
-    let query = conn.prepare(sql).await.unwrap();
-    let res = conn.query(&query, params).await;
+    let query = match conn.prepare(sql).await {
+        Ok(x) => x,
+        Err(e) => return Err(Error::DatabaseError(e.to_string())),
+    };
 
-    if let Ok(rows) = res {
-        let mut out = Vec::new();
+    match conn.query(&query, params).await {
+        Ok(rows) => {
+            let mut out = Vec::new();
 
-        for row in rows {
-            out.push(f(&row));
-        }
+            for row in rows {
+                out.push(f(&row)?);
+            }
 
-        return Ok(out);
-    } else {
-        Err(res.unwrap_err())
+            Ok(out)
+        }
+        Err(e) => Err(Error::DatabaseError(e.to_string())),
     }
 }
 
@@ -130,7 +236,7 @@ pub async fn execute_helper(
     sql: &str,
     params: &[&(dyn ToSql + Sync)],
 ) -> Result<()> {
-    let query = conn.prepare(sql).await.unwrap();
+    let query = conn.prepare(sql).await?;
     conn.execute(&query, params).await?;
     Ok(())
 }
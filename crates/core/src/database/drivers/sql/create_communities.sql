@@ -11,5 +11,9 @@ CREATE TABLE IF NOT EXISTS communities (
     likes INT NOT NULL,
     dislikes INT NOT NULL,
     -- counts
-    member_count INT NOT NULL
+    member_count INT NOT NULL,
+    -- context flags (copied out of `context` so they can be indexed)
+    is_nsfw INT NOT NULL DEFAULT 0,
+    -- tombstone (0 if the community hasn't been deleted)
+    deleted_at BIGINT NOT NULL DEFAULT 0
 )
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS post_revisions (
+    id BIGINT NOT NULL PRIMARY KEY,
+    created BIGINT NOT NULL,
+    post BIGINT NOT NULL,
+    editor BIGINT NOT NULL,
+    content TEXT NOT NULL
+)
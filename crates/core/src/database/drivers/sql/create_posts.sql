@@ -10,5 +10,12 @@ CREATE TABLE IF NOT EXISTS posts (
     likes INT NOT NULL,
     dislikes INT NOT NULL,
     -- other counts
-    comment_count INT NOT NULL
+    comment_count INT NOT NULL,
+    -- context flags (copied out of `context` so they can be indexed)
+    is_nsfw INT NOT NULL DEFAULT 0,
+    is_pinned INT NOT NULL DEFAULT 0,
+    is_profile_pinned INT NOT NULL DEFAULT 0,
+    answering BIGINT NOT NULL DEFAULT 0,
+    -- tombstone (0 if the post hasn't been deleted)
+    deleted_at BIGINT NOT NULL DEFAULT 0
 )
@@ -12,5 +12,9 @@ CREATE TABLE IF NOT EXISTS questions (
     dislikes INT NOT NULL,
     -- ...
     context TEXT NOT NULL,
-    ip TEXT NOT NULL
+    ip TEXT NOT NULL,
+    -- context flags (copied out of `context` so they can be indexed)
+    is_nsfw INT NOT NULL DEFAULT 0,
+    -- tombstone (0 if the question hasn't been deleted)
+    deleted_at BIGINT NOT NULL DEFAULT 0
 )
@@ -1,4 +1,6 @@
-#[cfg(not(feature = "redis"))]
+#[cfg(all(feature = "memory", not(feature = "redis")))]
+use crate::cache::memory::MemoryCache;
+#[cfg(not(any(feature = "redis", feature = "memory")))]
 use crate::cache::no_cache::NoCache;
 #[cfg(feature = "redis")]
 use crate::cache::redis::RedisCache;
@@ -6,84 +8,224 @@ use crate::cache::redis::RedisCache;
 use crate::cache::Cache;
 
 use crate::config::Config;
-use rusqlite::{Connection, Result};
+use crate::execute;
+use crate::model::Error;
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
 use std::collections::HashMap;
 use tetratto_l10n::{LangFile, read_langs};
 
+pub type Result<T> = std::result::Result<T, r2d2::Error>;
+pub type Connection = PooledConnection<SqliteConnectionManager>;
+
 #[derive(Clone)]
 pub struct DataManager(
     pub Config,
     pub HashMap<String, LangFile>,
     #[cfg(feature = "redis")] pub RedisCache,
-    #[cfg(not(feature = "redis"))] pub NoCache,
+    #[cfg(all(feature = "memory", not(feature = "redis")))] pub MemoryCache,
+    #[cfg(not(any(feature = "redis", feature = "memory")))] pub NoCache,
+    pub Pool<SqliteConnectionManager>,
 );
 
 impl DataManager {
     /// Obtain a connection to the staging database.
+    ///
+    /// Waiting on the pool happens on the blocking thread pool.
     pub(crate) async fn connect(&self) -> Result<Connection> {
-        Connection::open(&self.0.database.name)
+        let pool = self.3.clone();
+        let conn = spawn_blocking(move || pool.get()).await?;
+
+        // a connection can only come back with an open transaction if something
+        // panicked in the middle of one
+        if !conn.is_autocommit() {
+            conn.execute_batch("ROLLBACK").ok();
+        }
+
+        Ok(conn)
+    }
+
+    /// Run `f` inside of a transaction on a single connection. The transaction is
+    /// committed if `f` returns `Ok`, and rolled back otherwise.
+    ///
+    /// Every write made in `f` **must** go through the given connection, as other
+    /// connections will wait on the write lock held by the transaction.
+    pub(crate) async fn transaction<T>(
+        &self,
+        f: impl AsyncFnOnce(&mut Connection) -> crate::model::Result<T>,
+    ) -> crate::model::Result<T> {
+        let mut conn = match self.connect().await {
+            Ok(c) => c,
+            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+        };
+
+        // take the write lock right away so we can't fail to upgrade later
+        if let Err(e) = execute!(&conn, "BEGIN IMMEDIATE") {
+            return Err(Error::DatabaseError(e.to_string()));
+        }
+
+        match f(&mut conn).await {
+            Ok(x) => {
+                if let Err(e) = execute!(&conn, "COMMIT") {
+                    execute!(&conn, "ROLLBACK").ok();
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
+
+                Ok(x)
+            }
+            Err(e) => {
+                execute!(&conn, "ROLLBACK").ok();
+                Err(e)
+            }
+        }
     }
 
     /// Create a new [`DataManager`] (and init database).
     pub async fn new(config: Config) -> Result<Self> {
-        let this = Self(
+        tetratto_shared::snow::set_node_id(config.node_id);
+
+        let manager = SqliteConnectionManager::file(&config.database.name)
+            .with_init(|c| c.pragma_update(None, "journal_mode", "WAL"));
+
+        let pool_size = config.database.pool_size;
+        let pool = spawn_blocking(move || {
+            Pool::builder()
+                .max_size(pool_size)
+                .min_idle(Some(pool_size))
+                .build(manager)
+        })
+        .await?;
+
+        Ok(Self(
             config.clone(),
             read_langs(),
             #[cfg(feature = "redis")]
-            RedisCache::new().await,
-            #[cfg(not(feature = "redis"))]
-            NoCache::new().await,
-        );
+            RedisCache::new(&config.cache).await,
+            #[cfg(all(feature = "memory", not(feature = "redis")))]
+            MemoryCache::new(&config.cache).await,
+            #[cfg(not(any(feature = "redis", feature = "memory")))]
+            NoCache::new(&config.cache).await,
+            pool,
+        ))
+    }
+}
 
-        let conn = this.connect().await?;
-        conn.pragma_update(None, "journal_mode", "WAL").unwrap();
+/// Run `f` on the blocking thread pool and wait for it.
+async fn spawn_blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    match tokio::task::spawn_blocking(f).await {
+        Ok(x) => x,
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
+}
 
-        Ok(this)
+/// Run a query on a connection (used by the query macros).
+///
+/// Connections are borrowed from the pool, so queries can't be moved onto the
+/// blocking thread pool. On the multi-threaded runtime, the worker hands its other
+/// tasks off to another worker while the query runs. Anywhere else, the query runs
+/// in place.
+#[doc(hidden)]
+pub fn run_query<T>(f: impl FnOnce() -> T) -> T {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(f)
+        }
+        _ => f(),
     }
 }
 
 #[macro_export]
 macro_rules! get {
+    ($row:ident->$idx:literal(json)) => {
+        match serde_json::from_str(&$crate::get!($row->$idx(String))) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err($crate::model::Error::DatabaseError(format!(
+                    "invalid JSON in column {}: {e}",
+                    $idx
+                )));
+            }
+        }
+    };
+
     ($row:ident->$idx:literal($t:ty)) => {
-        $row.get::<usize, $t>($idx).unwrap()
+        match $row.get::<usize, $t>($idx) {
+            Ok(x) => x,
+            Err(e) => return Err($crate::model::Error::DatabaseError(e.to_string())),
+        }
     };
 }
 
+/// Map a row with the given function (so the type of its argument can be inferred).
+pub fn map_row<T>(
+    row: &rusqlite::Row<'_>,
+    f: impl FnOnce(&rusqlite::Row<'_>) -> crate::model::Result<T>,
+) -> crate::model::Result<T> {
+    f(row)
+}
+
+/// Select the first row of the given query. Returns [`None`] if the query didn't
+/// return any rows.
 #[macro_export]
 macro_rules! query_row {
-    ($conn:expr, $sql:expr, $params:expr, $f:expr) => {{
-        let mut query = $conn.prepare($sql).unwrap();
-        query.query_row($params, $f)
-    }};
+    ($conn:expr, $sql:expr, $params:expr, $f:expr) => {
+        $crate::database::run_query(|| -> $crate::model::Result<Option<_>> {
+            let mut query = match $conn.prepare($sql) {
+                Ok(x) => x,
+                Err(e) => return Err($crate::model::Error::DatabaseError(e.to_string())),
+            };
+
+            let mut rows = match query.query($params) {
+                Ok(x) => x,
+                Err(e) => return Err($crate::model::Error::DatabaseError(e.to_string())),
+            };
+
+            match rows.next() {
+                Ok(Some(row)) => $crate::database::map_row(row, $f).map(Some),
+                Ok(None) => Ok(None),
+                Err(e) => Err($crate::model::Error::DatabaseError(e.to_string())),
+            }
+        })
+    };
 }
 
 #[macro_export]
 macro_rules! query_rows {
-    ($conn:expr, $sql:expr, $params:expr, $f:expr) => {{
-        let mut query = $conn.prepare($sql).unwrap();
+    ($conn:expr, $sql:expr, $params:expr, $f:expr) => {
+        $crate::database::run_query(|| -> $crate::model::Result<Vec<_>> {
+            let mut query = match $conn.prepare($sql) {
+                Ok(x) => x,
+                Err(e) => return Err($crate::model::Error::DatabaseError(e.to_string())),
+            };
+
+            let mut rows = match query.query($params) {
+                Ok(x) => x,
+                Err(e) => return Err($crate::model::Error::DatabaseError(e.to_string())),
+            };
 
-        if let Ok(mut rows) = query.query($params) {
             let mut out = Vec::new();
 
-            while let Some(row) = rows.next().unwrap() {
-                out.push($f(&row));
+            loop {
+                match rows.next() {
+                    Ok(Some(row)) => out.push($crate::database::map_row(row, $f)?),
+                    Ok(None) => break,
+                    Err(e) => return Err($crate::model::Error::DatabaseError(e.to_string())),
+                }
             }
 
             Ok(out)
-        } else {
-            Err(Error::Unknown)
-        }
-    }};
+        })
+    };
 }
 
 #[macro_export]
 macro_rules! execute {
     ($conn:expr, $sql:expr, $params:expr) => {
-        $conn.prepare($sql).unwrap().execute($params)
+        $crate::database::run_query(|| $conn.prepare($sql).and_then(|mut x| x.execute($params)))
     };
 
     ($conn:expr, $sql:expr) => {
-        $conn.prepare($sql).unwrap().execute(())
+        $crate::database::run_query(|| $conn.prepare($sql).and_then(|mut x| x.execute(())))
     };
 }
 
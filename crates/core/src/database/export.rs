@@ -0,0 +1,372 @@
+use super::*;
+use crate::model::{
+    Error, Result,
+    auth::{Notification, User},
+    communities::Question,
+    export::{EXPORT_README, ExportManifest, ExportUser},
+};
+use crate::query_rows;
+use pathbufd::PathBufD;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::{Cursor, Write};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::fs::{create_dir_all, metadata, read, read_dir, remove_file, rename, try_exists, write};
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+/// The IDs of the users whose export is currently being generated.
+static RUNNING_EXPORTS: LazyLock<Mutex<HashSet<usize>>> = LazyLock::new(Default::default);
+
+/// Marks an export as running until it's dropped (even if the export fails or panics).
+pub struct RunningExport(usize);
+
+impl RunningExport {
+    /// Mark the export of the given user as running, failing if it already is.
+    pub fn claim(id: usize) -> Result<Self> {
+        let mut running = RUNNING_EXPORTS.lock().unwrap_or_else(|e| e.into_inner());
+
+        if running.insert(id) {
+            Ok(Self(id))
+        } else {
+            Err(Error::MiscError(
+                "An export is already being generated".to_string(),
+            ))
+        }
+    }
+}
+
+impl Drop for RunningExport {
+    fn drop(&mut self) {
+        RUNNING_EXPORTS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.0);
+    }
+}
+
+/// Select all rows from the given query (with the user's ID as `$1`).
+macro_rules! export_rows {
+    ($conn:expr, $query:literal, $id:ident, $mapper:ident, $name:literal) => {{
+        let res = query_rows!($conn, $query, &[&($id as i64)], |x| { Self::$mapper(x) });
+
+        match res {
+            Ok(x) => x,
+            Err(Error::DatabaseError(e)) => {
+                return Err(Error::DatabaseError(format!("{}: {e}", $name)));
+            }
+            Err(e) => return Err(e),
+        }
+    }};
+}
+
+impl DataManager {
+    /// Get the path the finished export archive of the given user is stored at.
+    pub fn user_export_path(&self, id: usize) -> PathBufD {
+        PathBufD::current().extend(&[
+            self.0.dirs.media.as_str(),
+            "exports",
+            &format!("{}.zip", id as i64),
+        ])
+    }
+
+    fn export_json<T: Serialize>(data: &T) -> Result<Vec<u8>> {
+        match serde_json::to_vec_pretty(data) {
+            Ok(x) => Ok(x),
+            Err(e) => Err(Error::MiscError(e.to_string())),
+        }
+    }
+
+    /// Build a ZIP archive of all of the data stored for the given user.
+    ///
+    /// The layout of the archive is documented in [`EXPORT_README`], which is
+    /// also included in the archive.
+    pub async fn export_user_data(&self, id: usize) -> Result<Vec<u8>> {
+        let mut user = self.get_user_by_id(id).await?;
+
+        // strip secrets
+        user.password = String::new();
+        user.salt = String::new();
+        user.tokens = Vec::new();
+        user.totp = String::new();
+        user.recovery_codes = Vec::new();
+
+        let conn = match self.connect().await {
+            Ok(c) => c,
+            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+        };
+
+        // questions asked by other users don't include their IP
+        let mut questions_received: Vec<Question> = export_rows!(
+            &conn,
+            "SELECT * FROM questions WHERE receiver = $1 OR id IN (SELECT answering FROM posts WHERE owner = $1) ORDER BY created ASC",
+            id,
+            get_question_from_row,
+            "questions"
+        );
+
+        for question in questions_received.iter_mut() {
+            if question.owner != id {
+                question.ip = String::new();
+            }
+        }
+
+        // other users are only referenced by their ID everywhere else, so we need
+        // their usernames to find them again on other instances
+        let users: Vec<ExportUser> = export_rows!(
+            &conn,
+            "SELECT * FROM users WHERE NOT id = $1 AND (id IN (SELECT receiver FROM userfollows WHERE initiator = $1) OR id IN (SELECT initiator FROM userfollows WHERE receiver = $1) OR id IN (SELECT receiver FROM userblocks WHERE initiator = $1) OR id IN (SELECT receiver FROM questions WHERE owner = $1) OR id IN (SELECT owner FROM questions WHERE receiver = $1 OR id IN (SELECT answering FROM posts WHERE owner = $1)))",
+            id,
+            get_user_from_row,
+            "users"
+        )
+        .into_iter()
+        .map(|x| ExportUser {
+            id: x.id,
+            username: x.username,
+        })
+        .collect();
+
+        let mut files: Vec<(&str, Vec<u8>)> = vec![
+            ("README.md", EXPORT_README.as_bytes().to_vec()),
+            (
+                "manifest.json",
+                Self::export_json(&ExportManifest::new(
+                    self.0.host.clone(),
+                    user.id,
+                    user.username.clone(),
+                ))?,
+            ),
+            ("settings.json", Self::export_json(&user.settings)?),
+            ("profile.json", Self::export_json(&user)?),
+            (
+                "posts.json",
+                Self::export_json(&export_rows!(
+                    &conn,
+                    "SELECT * FROM posts WHERE owner = $1 ORDER BY created ASC",
+                    id,
+                    get_post_from_row,
+                    "posts"
+                ))?,
+            ),
+            (
+                "questions_asked.json",
+                Self::export_json(&export_rows!(
+                    &conn,
+                    "SELECT * FROM questions WHERE owner = $1 ORDER BY created ASC",
+                    id,
+                    get_question_from_row,
+                    "questions"
+                ))?,
+            ),
+            (
+                "questions_received.json",
+                Self::export_json(&questions_received)?,
+            ),
+            (
+                "reactions.json",
+                Self::export_json(&export_rows!(
+                    &conn,
+                    "SELECT * FROM reactions WHERE owner = $1 ORDER BY created ASC",
+                    id,
+                    get_reaction_from_row,
+                    "reactions"
+                ))?,
+            ),
+            (
+                "following.json",
+                Self::export_json(&export_rows!(
+                    &conn,
+                    "SELECT * FROM userfollows WHERE initiator = $1 ORDER BY created ASC",
+                    id,
+                    get_userfollow_from_row,
+                    "userfollows"
+                ))?,
+            ),
+            (
+                "followers.json",
+                Self::export_json(&export_rows!(
+                    &conn,
+                    "SELECT * FROM userfollows WHERE receiver = $1 ORDER BY created ASC",
+                    id,
+                    get_userfollow_from_row,
+                    "userfollows"
+                ))?,
+            ),
+            (
+                "blocks.json",
+                Self::export_json(&export_rows!(
+                    &conn,
+                    "SELECT * FROM userblocks WHERE initiator = $1 ORDER BY created ASC",
+                    id,
+                    get_userblock_from_row,
+                    "userblocks"
+                ))?,
+            ),
+            (
+                "memberships.json",
+                Self::export_json(&export_rows!(
+                    &conn,
+                    "SELECT * FROM memberships WHERE owner = $1 ORDER BY created ASC",
+                    id,
+                    get_membership_from_row,
+                    "memberships"
+                ))?,
+            ),
+            (
+                "notifications.json",
+                Self::export_json(&export_rows!(
+                    &conn,
+                    "SELECT * FROM notifications WHERE owner = $1 ORDER BY created ASC",
+                    id,
+                    get_notification_from_row,
+                    "notifications"
+                ))?,
+            ),
+            ("users.json", Self::export_json(&users)?),
+            (
+                "communities.json",
+                Self::export_json(&export_rows!(
+                    &conn,
+                    "SELECT * FROM communities WHERE id IN (SELECT community FROM memberships WHERE owner = $1) OR id IN (SELECT community FROM posts WHERE owner = $1) OR id IN (SELECT community FROM questions WHERE owner = $1 OR receiver = $1 OR id IN (SELECT answering FROM posts WHERE owner = $1))",
+                    id,
+                    get_community_from_row,
+                    "communities"
+                ))?,
+            ),
+        ];
+
+        // images
+        for (dir, name) in [
+            ("avatars", "media/avatar.avif"),
+            ("banners", "media/banner.avif"),
+        ] {
+            let path = PathBufD::current().extend(&[
+                self.0.dirs.media.as_str(),
+                dir,
+                &format!("{}.avif", id as i64),
+            ]);
+
+            if try_exists(&path).await.unwrap_or(false) {
+                match read(&path).await {
+                    Ok(x) => files.push((name, x)),
+                    Err(e) => return Err(Error::MiscError(e.to_string())),
+                }
+            }
+        }
+
+        // build archive
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        for (name, data) in files {
+            if let Err(e) = zip.start_file(name, options) {
+                return Err(Error::MiscError(e.to_string()));
+            }
+
+            if let Err(e) = zip.write_all(&data) {
+                return Err(Error::MiscError(e.to_string()));
+            }
+        }
+
+        match zip.finish() {
+            Ok(x) => Ok(x.into_inner()),
+            Err(e) => Err(Error::MiscError(e.to_string())),
+        }
+    }
+
+    /// Generate the export archive of the given user and store it at
+    /// [`DataManager::user_export_path`], replacing any previous export.
+    ///
+    /// The user is sent a notification once the archive is ready. This can take a
+    /// while for larger accounts, so it should be run in the background (after the
+    /// export is claimed with [`RunningExport::claim`], so users can't start more
+    /// than one export at once).
+    pub async fn create_user_export(&self, user: &User, _running: RunningExport) -> Result<()> {
+        let path = self.user_export_path(user.id);
+        let tmp_path = format!("{path}.tmp");
+
+        if let Err(e) =
+            create_dir_all(PathBufD::current().extend(&[self.0.dirs.media.as_str(), "exports"]))
+                .await
+        {
+            return Err(Error::MiscError(e.to_string()));
+        }
+
+        let archive = self.export_user_data(user.id).await?;
+
+        let res = match write(&tmp_path, archive).await {
+            Ok(_) => rename(&tmp_path, &path).await,
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = res {
+            let _ = remove_file(&tmp_path).await;
+            return Err(Error::MiscError(e.to_string()));
+        }
+
+        self.create_notification(Notification::new(
+            "Your data export is ready".to_string(),
+            "Your account data has been exported. [Download it](/api/v1/auth/user/me/export)."
+                .to_string(),
+            user.id,
+        ))
+        .await
+    }
+
+    /// Check if the file at the given path was last written longer than
+    /// `export_window` ago.
+    async fn export_expired(&self, path: &str) -> bool {
+        let modified = match metadata(path).await.and_then(|x| x.modified()) {
+            Ok(x) => x,
+            Err(_) => return false,
+        };
+
+        SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default()
+            > Duration::from_millis(self.0.export_window as u64)
+    }
+
+    /// Get the finished export archive of the given user.
+    pub async fn get_user_export(&self, id: usize) -> Result<Vec<u8>> {
+        let path = self.user_export_path(id);
+
+        if !try_exists(&path).await.unwrap_or(false) || self.export_expired(&path.to_string()).await
+        {
+            return Err(Error::GeneralNotFound("export".to_string()));
+        }
+
+        match read(path).await {
+            Ok(x) => Ok(x),
+            Err(e) => Err(Error::MiscError(e.to_string())),
+        }
+    }
+
+    /// Delete every export archive (and any temporary file left behind by an
+    /// interrupted export) which was written longer than `export_window` ago.
+    pub async fn purge_expired_exports(&self) -> Result<()> {
+        let dir = PathBufD::current().extend(&[self.0.dirs.media.as_str(), "exports"]);
+
+        if !try_exists(&dir).await.unwrap_or(false) {
+            return Ok(());
+        }
+
+        let mut entries = match read_dir(&dir).await {
+            Ok(x) => x,
+            Err(e) => return Err(Error::MiscError(e.to_string())),
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+
+            if self.export_expired(&path.to_string_lossy()).await
+                && let Err(e) = remove_file(&path).await
+            {
+                return Err(Error::MiscError(e.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use super::*;
+use crate::cache::{Cache, EPOCH_YEAR};
+use crate::model::Result;
+use serde::{Serialize, de::DeserializeOwned};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use tetratto_shared::epoch_timestamp;
+use tokio::sync::OnceCell;
+
+/// A listing which is kept in the cache, since it's expensive to compute and
+/// changes slowly.
+pub(crate) struct Feed {
+    /// The cache namespace of the feed. Every page of the feed is cached in it.
+    pub namespace: &'static str,
+    /// How old (in ms) a cached page can get before it's recomputed.
+    pub refresh_interval: i64,
+}
+
+pub(crate) const POPULAR_POSTS: Feed = Feed {
+    namespace: "atto.feed.popular_posts",
+    refresh_interval: 60_000,
+};
+
+pub(crate) const POPULAR_QUESTIONS: Feed = Feed {
+    namespace: "atto.feed.popular_questions",
+    refresh_interval: 60_000,
+};
+
+pub(crate) const POPULAR_COMMUNITIES: Feed = Feed {
+    namespace: "atto.feed.popular_communities",
+    refresh_interval: 300_000,
+};
+
+/// The result of computing a page (as JSON), shared with everyone waiting for it.
+/// `None` if computing the page failed.
+type Flight = Arc<OnceCell<Option<String>>>;
+
+/// Pages which are currently being computed.
+static FLIGHTS: LazyLock<Mutex<HashMap<String, Flight>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+impl DataManager {
+    /// Get a cached page of the given feed, if it isn't older than the feed's
+    /// refresh interval.
+    async fn get_fresh_page<T>(&self, feed: &Feed, key: &str) -> Option<T>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let (created, page) = self.2.get_timed::<T>(key.to_string()).await?;
+
+        if epoch_timestamp(EPOCH_YEAR) - created >= feed.refresh_interval {
+            return None;
+        }
+
+        Some(page)
+    }
+
+    /// Get a page of the given feed from the cache, computing (and caching) it
+    /// with `f` if it's missing or too old.
+    ///
+    /// Only one caller (in this process) computes a page at a time. Everyone else
+    /// asking for the same page waits for its result instead of also computing it.
+    ///
+    /// # Arguments
+    /// * `feed` - the feed the page belongs to
+    /// * `page` - a string which identifies the page in the feed
+    /// * `f` - the function computing the page
+    pub(crate) async fn get_feed_page<T, F, Fut>(
+        &self,
+        feed: &Feed,
+        page: String,
+        f: F,
+    ) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned + Clone,
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let key = format!("{}:{page}", feed.namespace);
+
+        if let Some(x) = self.get_fresh_page(feed, &key).await {
+            return Ok(x);
+        }
+
+        let flight_key = format!("{}:{key}", self.0.database.name);
+        let flight = FLIGHTS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(flight_key.clone())
+            .or_default()
+            .clone();
+
+        let mut res: Option<Result<T>> = None;
+        let (slot, key, f) = (&mut res, &key, &f);
+
+        let shared = flight
+            .get_or_init(move || async move {
+                // the page may have been cached while we were checking the cache
+                let x = match self.get_fresh_page(feed, key).await {
+                    Some(x) => Ok(x),
+                    None => f().await,
+                };
+
+                let json = match x {
+                    Ok(ref x) => {
+                        self.2.set_timed(key.to_string(), x.clone()).await;
+                        serde_json::to_string(x).ok()
+                    }
+                    Err(_) => None,
+                };
+
+                *slot = Some(x);
+                json
+            })
+            .await
+            .clone();
+
+        // we computed the page
+        if let Some(res) = res {
+            FLIGHTS
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(&flight_key);
+
+            return res;
+        }
+
+        // somebody else computed the page
+        match shared.and_then(|x| serde_json::from_str(&x).ok()) {
+            Some(x) => Ok(x),
+            None => f().await,
+        }
+    }
+
+    /// Remove every cached page of the given feed.
+    pub(crate) async fn invalidate_feed(&self, feed: &Feed) {
+        self.2.bump_namespace(feed.namespace.to_string()).await;
+    }
+}
@@ -0,0 +1,838 @@
+use super::*;
+use crate::model::{
+    Error, Result,
+    auth::{User, UserBlock, UserFollow},
+    communities::{
+        Community, CommunityJoinAccess, CommunityMembership, CommunityReadAccess,
+        CommunityWriteAccess, Post, Question,
+    },
+    communities_permissions::CommunityPermission,
+    export::{EXPORT_FORMAT_VERSION, ExportManifest, ExportUser, ImportSummary},
+    reactions::{AssetType, Reaction},
+    requests::{ActionRequest, ActionType},
+};
+use crate::{execute, params};
+use pathbufd::PathBufD;
+use serde::de::DeserializeOwned;
+use std::collections::{HashMap, HashSet, hash_map::Entry};
+use std::fs::{create_dir_all, write};
+use std::io::{Cursor, Read};
+use tetratto_shared::snow::AlmostSnowflake;
+use zip::ZipArchive;
+
+/// The largest (uncompressed) file we'll read out of an import archive.
+const MAXIMUM_IMPORT_FILE_SIZE: u64 = 67108864;
+
+/// The part of a mention link after the username.
+const MENTION_LINK: &str = "](/api/v1/auth/user/find/";
+
+/// Generate a new ID for an imported item created at the given timestamp.
+fn new_id(created: usize) -> Result<usize> {
+    match AlmostSnowflake::generate_at(created as u128)
+        .to_string()
+        .parse::<usize>()
+    {
+        Ok(x) => Ok(x),
+        Err(e) => Err(Error::MiscError(e.to_string())),
+    }
+}
+
+/// Read a file from an import archive. Returns `None` if it doesn't exist.
+fn read_file(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> Result<Option<Vec<u8>>> {
+    let file = match archive.by_name(name) {
+        Ok(f) => f,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(Error::MiscError(e.to_string())),
+    };
+
+    if file.size() > MAXIMUM_IMPORT_FILE_SIZE {
+        return Err(Error::DataTooLong(name.to_string()));
+    }
+
+    let mut buf = Vec::new();
+    if let Err(e) = file.take(MAXIMUM_IMPORT_FILE_SIZE).read_to_end(&mut buf) {
+        return Err(Error::MiscError(e.to_string()));
+    }
+
+    Ok(Some(buf))
+}
+
+/// Read a JSON file from an import archive. Missing files are read as `T::default()`.
+fn read_json<T: DeserializeOwned + Default>(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    name: &str,
+) -> Result<T> {
+    match read_file(archive, name)? {
+        Some(x) => match serde_json::from_slice(&x) {
+            Ok(x) => Ok(x),
+            Err(e) => Err(Error::MiscError(format!("{name}: {e}"))),
+        },
+        None => Ok(T::default()),
+    }
+}
+
+/// Call `f` on every mention link (`[@username](/api/v1/auth/user/find/id)`) in the
+/// given `content`. The link is replaced with whatever `f` returns.
+fn replace_mentions(content: &str, mut f: impl FnMut(&str) -> String) -> String {
+    let mut out = String::new();
+    let mut rest = content;
+
+    while let Some(start) = rest.find("[@") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+
+        let link = after.find(MENTION_LINK).and_then(|name_end| {
+            let name = &after[..name_end];
+
+            if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == ']') {
+                return None;
+            }
+
+            let tail = &after[name_end + MENTION_LINK.len()..];
+            let id_end = tail.find(')')?;
+            tail[..id_end].parse::<usize>().ok()?;
+
+            Some((name, name_end + MENTION_LINK.len() + id_end + 1))
+        });
+
+        match link {
+            Some((name, len)) => {
+                out.push_str(&f(name));
+                rest = &after[len..];
+            }
+            None => {
+                out.push_str("[@");
+                rest = after;
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Replace the ID following every occurrence of `prefix` (like `/post/`) in the
+/// given `content` with its new ID. IDs which aren't in `ids` are left alone.
+fn remap_paths(content: &str, prefix: &str, ids: &HashMap<usize, usize>) -> String {
+    let mut out = String::new();
+    let mut rest = content;
+
+    while let Some(start) = rest.find(prefix) {
+        let (head, tail) = rest.split_at(start + prefix.len());
+        out.push_str(head);
+
+        let id_end = tail
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(tail.len());
+
+        match tail[..id_end]
+            .parse::<usize>()
+            .ok()
+            .and_then(|x| ids.get(&x))
+        {
+            Some(id) => out.push_str(&id.to_string()),
+            None => out.push_str(&tail[..id_end]),
+        }
+
+        rest = &tail[id_end..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+impl DataManager {
+    /// Recreate an account from an export archive (see [`DataManager::export_user_data`])
+    /// on this instance.
+    ///
+    /// Every item gets a new ID generated from its `created` timestamp (so it still
+    /// sorts by ID where it was created). Other users are
+    /// matched by their username and communities by their title. Communities owned
+    /// by the account which don't exist here are recreated. Everything which can't
+    /// be resolved is skipped.
+    ///
+    /// Communities which already exist here are only joined and posted in if their
+    /// access settings allow it, and only follows made by the account are imported.
+    ///
+    /// # Arguments
+    /// * `archive` - the bytes of the ZIP archive
+    /// * `password` - the password of the new account (passwords aren't exported)
+    pub async fn import_user_data(
+        &self,
+        archive: &[u8],
+        password: String,
+    ) -> Result<ImportSummary> {
+        let mut archive = match ZipArchive::new(Cursor::new(archive)) {
+            Ok(a) => a,
+            Err(e) => return Err(Error::MiscError(e.to_string())),
+        };
+
+        let manifest: Option<ExportManifest> = read_json(&mut archive, "manifest.json")?;
+        let manifest = match manifest {
+            Some(m) => m,
+            None => return Err(Error::GeneralNotFound("manifest".to_string())),
+        };
+
+        if manifest.version == 0 || manifest.version > EXPORT_FORMAT_VERSION {
+            return Err(Error::MiscError(format!(
+                "Unsupported export version: {}",
+                manifest.version
+            )));
+        }
+
+        let profile: Option<User> = read_json(&mut archive, "profile.json")?;
+        let profile = match profile {
+            Some(p) => p,
+            None => return Err(Error::GeneralNotFound("profile".to_string())),
+        };
+
+        let mut posts: Vec<Post> = read_json(&mut archive, "posts.json")?;
+        let mut questions: Vec<Question> = read_json(&mut archive, "questions_asked.json")?;
+        questions.extend(read_json::<Vec<Question>>(
+            &mut archive,
+            "questions_received.json",
+        )?);
+        let reactions: Vec<Reaction> = read_json(&mut archive, "reactions.json")?;
+        let following: Vec<UserFollow> = read_json(&mut archive, "following.json")?;
+        let blocks: Vec<UserBlock> = read_json(&mut archive, "blocks.json")?;
+        let memberships: Vec<CommunityMembership> = read_json(&mut archive, "memberships.json")?;
+        let users: Vec<ExportUser> = read_json(&mut archive, "users.json")?;
+        let communities: Vec<Community> = read_json(&mut archive, "communities.json")?;
+        let avatar = read_file(&mut archive, "media/avatar.avif")?;
+        let banner = read_file(&mut archive, "media/banner.avif")?;
+
+        // create user
+        let username = profile.username.to_lowercase();
+
+        self.check_new_username(&username).await?;
+
+        if password.len() < 6 {
+            return Err(Error::DataTooShort("password".to_string()));
+        }
+
+        let mut user = User::new(
+            username.clone(),
+            password,
+            &self.0.security.password_hashing,
+        )
+        .await;
+        user.id = new_id(profile.created)?;
+        user.created = profile.created;
+        user.last_seen = profile.last_seen;
+        user.settings = profile.settings;
+
+        let mut summary = ImportSummary {
+            user: user.id,
+            ..Default::default()
+        };
+
+        // resolve users
+        let mut user_ids: HashMap<usize, usize> = HashMap::new();
+        user_ids.insert(profile.id, user.id);
+
+        for other in &users {
+            if let Ok(ua) = self.get_user_by_username(&other.username).await {
+                user_ids.insert(other.id, ua.id);
+            }
+        }
+
+        // resolve communities
+        let mut community_ids: HashMap<usize, usize> = HashMap::new();
+        let mut new_communities: Vec<Community> = Vec::new();
+        let mut existing_communities: HashMap<usize, Community> = HashMap::new();
+
+        for community in communities {
+            if community.deleted_at != 0 {
+                continue;
+            }
+
+            let existing = self.get_community_by_title(&community.title).await?;
+
+            if existing.id != 0 {
+                community_ids.insert(community.id, existing.id);
+                existing_communities.insert(existing.id, existing);
+            } else if community.owner == profile.id {
+                let id = new_id(community.created)?;
+                community_ids.insert(community.id, id);
+                new_communities.push(Community {
+                    id,
+                    owner: user.id,
+                    likes: 0,
+                    dislikes: 0,
+                    member_count: 0,
+                    ..community
+                });
+            }
+        }
+
+        summary.communities = new_communities.len();
+
+        // memberships
+        // (communities which weren't recreated can only be joined if they're open)
+        let mut new_memberships: Vec<CommunityMembership> = Vec::new();
+        let mut joined: HashSet<usize> = HashSet::new();
+
+        for mut membership in memberships {
+            let Some(community) = community_ids.get(&membership.community).copied() else {
+                summary.skipped += 1;
+                continue;
+            };
+
+            let recreated = new_communities.iter().any(|x| x.id == community);
+
+            if membership.role.check_banned()
+                | membership.role.contains(CommunityPermission::REQUESTED)
+                | (!recreated
+                    && existing_communities[&community].join_access
+                        != CommunityJoinAccess::Everybody)
+                | !joined.insert(community)
+            {
+                summary.skipped += 1;
+                continue;
+            }
+
+            // roles only carry over into communities we've recreated
+            if !recreated {
+                membership.role = CommunityPermission::DEFAULT | CommunityPermission::MEMBER;
+            }
+
+            membership.id = new_id(membership.created)?;
+            membership.owner = user.id;
+            membership.community = community;
+            new_memberships.push(membership);
+        }
+
+        for community in &new_communities {
+            if joined.insert(community.id) {
+                let mut membership = CommunityMembership::new(
+                    user.id,
+                    community.id,
+                    CommunityPermission::ADMINISTRATOR,
+                );
+
+                membership.id = new_id(community.created)?;
+                membership.created = community.created;
+                new_memberships.push(membership);
+            }
+        }
+
+        // the communities we can post (and ask questions) in, with the same rules as
+        // `check_can_post`
+        let writable: HashSet<usize> = community_ids
+            .values()
+            .copied()
+            .filter(|id| match existing_communities.get(id) {
+                Some(community) => match community.write_access {
+                    CommunityWriteAccess::Everybody => {
+                        community.read_access != CommunityReadAccess::Joined || joined.contains(id)
+                    }
+                    CommunityWriteAccess::Joined => joined.contains(id),
+                    CommunityWriteAccess::Owner => false,
+                },
+                None => true,
+            })
+            .collect();
+
+        // questions
+        let mut question_ids: HashMap<usize, usize> = HashMap::new();
+        let mut new_questions: Vec<Question> = Vec::new();
+
+        questions.sort_by_key(|x| x.created);
+        for mut question in questions {
+            if question_ids.contains_key(&question.id) {
+                // asked to ourselves, so we've seen it twice
+                continue;
+            }
+
+            let receiver = if question.receiver == 0 {
+                Some(0)
+            } else {
+                user_ids.get(&question.receiver).copied()
+            };
+
+            let community = if question.community == 0 {
+                Some(0)
+            } else {
+                community_ids
+                    .get(&question.community)
+                    .copied()
+                    .filter(|x| writable.contains(x))
+            };
+
+            let (Some(receiver), Some(community)) = (receiver, community) else {
+                summary.skipped += 1;
+                continue;
+            };
+
+            if question.deleted_at != 0 {
+                summary.skipped += 1;
+                continue;
+            }
+
+            question_ids.insert(question.id, new_id(question.created)?);
+            question.id = question_ids[&question.id];
+            question.owner = user_ids.get(&question.owner).copied().unwrap_or(0);
+            question.receiver = receiver;
+            question.community = community;
+            new_questions.push(question);
+        }
+
+        // posts
+        // (sorted so that posts are always seen before replies to them)
+        let mut post_ids: HashMap<usize, usize> = HashMap::new();
+        let mut new_posts: Vec<Post> = Vec::new();
+
+        posts.sort_by_key(|x| x.created);
+        for mut post in posts {
+            let community = community_ids
+                .get(&post.community)
+                .copied()
+                .filter(|x| writable.contains(x));
+
+            let replying_to = match post.replying_to {
+                Some(id) if id != 0 => post_ids.get(&id).map(|x| Some(*x)),
+                _ => Some(None),
+            };
+
+            let answering = if post.context.answering == 0 {
+                Some(0)
+            } else {
+                question_ids.get(&post.context.answering).copied()
+            };
+
+            let reposting = match post.context.repost {
+                Some(ref repost) => match repost.reposting {
+                    Some(id) => post_ids.get(&id).map(|x| Some(*x)),
+                    None => Some(None),
+                },
+                None => Some(None),
+            };
+
+            let (Some(community), Some(replying_to), Some(answering), Some(reposting)) =
+                (community, replying_to, answering, reposting)
+            else {
+                summary.skipped += 1;
+                continue;
+            };
+
+            if post.deleted_at != 0 {
+                summary.skipped += 1;
+                continue;
+            }
+
+            post_ids.insert(post.id, new_id(post.created)?);
+            post.id = post_ids[&post.id];
+            post.owner = user.id;
+            post.community = community;
+            post.replying_to = replying_to;
+            post.context.answering = answering;
+
+            if let Some(ref mut repost) = post.context.repost {
+                repost.reposting = reposting;
+            }
+
+            new_posts.push(post);
+        }
+
+        // remap links in content
+        let mut mentions: HashMap<String, Option<usize>> = HashMap::new();
+        mentions.insert(username.clone(), Some(user.id));
+
+        for content in new_posts
+            .iter()
+            .map(|x| &x.content)
+            .chain(new_questions.iter().map(|x| &x.content))
+        {
+            let mut names: Vec<String> = Vec::new();
+            replace_mentions(content, |name| {
+                names.push(name.to_string());
+                String::new()
+            });
+
+            for name in names {
+                if let Entry::Vacant(entry) = mentions.entry(name) {
+                    let id = self.get_user_by_username(entry.key()).await.ok();
+                    entry.insert(id.map(|x| x.id));
+                }
+            }
+        }
+
+        let remap_content = |content: &str| -> String {
+            let content = replace_mentions(content, |name| match mentions.get(name) {
+                Some(Some(id)) => format!("[@{name}]{}{id})", &MENTION_LINK[1..]),
+                _ => format!("@{name}"),
+            });
+
+            let content = remap_paths(&content, "/post/", &post_ids);
+            remap_paths(&content, "/question/", &question_ids)
+        };
+
+        for post in new_posts.iter_mut() {
+            post.content = remap_content(&post.content);
+        }
+
+        for question in new_questions.iter_mut() {
+            question.content = remap_content(&question.content);
+        }
+
+        // follows and blocks
+        let mut new_follows: Vec<UserFollow> = Vec::new();
+
+        // (only follows we made, other users can't be made to follow us)
+        for mut follow in following {
+            if follow.initiator != profile.id {
+                summary.skipped += 1;
+                continue;
+            }
+
+            match user_ids.get(&follow.receiver).copied() {
+                Some(receiver) if receiver != user.id => {
+                    follow.id = new_id(follow.created)?;
+                    follow.initiator = user.id;
+                    follow.receiver = receiver;
+                    new_follows.push(follow);
+                }
+                _ => summary.skipped += 1,
+            }
+        }
+
+        let mut new_blocks: Vec<UserBlock> = Vec::new();
+
+        for mut block in blocks {
+            match user_ids.get(&block.receiver).copied() {
+                Some(receiver) if receiver != user.id => {
+                    block.id = new_id(block.created)?;
+                    block.initiator = user.id;
+                    block.receiver = receiver;
+                    new_blocks.push(block);
+                }
+                _ => summary.skipped += 1,
+            }
+        }
+
+        // reactions
+        let mut new_reactions: Vec<Reaction> = Vec::new();
+
+        for mut reaction in reactions {
+            let asset = match reaction.asset_type {
+                AssetType::Community => community_ids.get(&reaction.asset),
+                AssetType::Post => post_ids.get(&reaction.asset),
+                AssetType::Question => question_ids.get(&reaction.asset),
+                AssetType::User => user_ids.get(&reaction.asset),
+            };
+
+            match asset {
+                Some(asset) => {
+                    reaction.id = new_id(reaction.created)?;
+                    reaction.owner = user.id;
+                    reaction.asset = *asset;
+                    new_reactions.push(reaction);
+                }
+                None => summary.skipped += 1,
+            }
+        }
+
+        // unanswered questions go back into our inbox
+        let answered: HashSet<usize> = new_posts.iter().map(|x| x.context.answering).collect();
+        let new_requests: Vec<ActionRequest> = new_questions
+            .iter()
+            .filter(|x| !x.is_global && x.receiver == user.id && !answered.contains(&x.id))
+            .map(|x| ActionRequest::with_id(x.owner, x.receiver, ActionType::Answer, x.id))
+            .collect();
+
+        summary.posts = new_posts.len();
+        summary.questions = new_questions.len();
+        summary.memberships = new_memberships.len();
+        summary.follows = new_follows.len();
+        summary.blocks = new_blocks.len();
+        summary.reactions = new_reactions.len();
+
+        // insert everything
+        // (counters are all inserted as 0 and fixed afterwards)
+        self.transaction(async |conn| {
+            let res = execute!(
+                conn,
+                "INSERT INTO users VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)",
+                params![
+                    &(user.id as i64),
+                    &(user.created as i64),
+                    &user.username,
+                    &user.password,
+                    &user.salt,
+                    &serde_json::to_string(&user.settings).unwrap(),
+                    &"[]",
+                    &(user.permissions.bits() as i32),
+                    &0_i32,
+                    &0_i32,
+                    &0_i32,
+                    &0_i32,
+                    &(user.last_seen as i64),
+                    &String::new(),
+                    &"[]",
+                    &0_i32,
+                    &0_i32
+                ]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
+
+            for data in &new_communities {
+                let res = execute!(
+                    conn,
+                    "INSERT INTO communities VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)",
+                    params![
+                        &(data.id as i64),
+                        &(data.created as i64),
+                        &data.title.to_lowercase(),
+                        &serde_json::to_string(&data.context).unwrap().as_str(),
+                        &(data.owner as i64),
+                        &serde_json::to_string(&data.read_access).unwrap().as_str(),
+                        &serde_json::to_string(&data.write_access).unwrap().as_str(),
+                        &serde_json::to_string(&data.join_access).unwrap().as_str(),
+                        &0_i32,
+                        &0_i32,
+                        &0_i32,
+                        &{ if data.context.is_nsfw { 1 } else { 0 } },
+                        &0_i64
+                    ]
+                );
+
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
+            }
+
+            for data in &new_memberships {
+                let res = execute!(
+                    conn,
+                    "INSERT INTO memberships VALUES ($1, $2, $3, $4, $5)",
+                    params![
+                        &(data.id as i64),
+                        &(data.created as i64),
+                        &(data.owner as i64),
+                        &(data.community as i64),
+                        &(data.role.bits() as i32),
+                    ]
+                );
+
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
+            }
+
+            for data in &new_questions {
+                let res = execute!(
+                    conn,
+                    "INSERT INTO questions VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)",
+                    params![
+                        &(data.id as i64),
+                        &(data.created as i64),
+                        &(data.owner as i64),
+                        &(data.receiver as i64),
+                        &data.content,
+                        &{ if data.is_global { 1 } else { 0 } },
+                        &0_i32,
+                        &(data.community as i64),
+                        &0_i32,
+                        &0_i32,
+                        &serde_json::to_string(&data.context).unwrap(),
+                        &data.ip,
+                        &{ if data.context.is_nsfw { 1 } else { 0 } },
+                        &0_i64
+                    ]
+                );
+
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
+            }
+
+            for data in &new_posts {
+                let res = execute!(
+                    conn,
+                    "INSERT INTO posts VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)",
+                    params![
+                        &(data.id as i64),
+                        &(data.created as i64),
+                        &data.content,
+                        &(data.owner as i64),
+                        &(data.community as i64),
+                        &serde_json::to_string(&data.context).unwrap(),
+                        &(data.replying_to.unwrap_or(0) as i64),
+                        &0_i32,
+                        &0_i32,
+                        &0_i32,
+                        &{ if data.context.is_nsfw { 1 } else { 0 } },
+                        &{ if data.context.is_pinned { 1 } else { 0 } },
+                        &{ if data.context.is_profile_pinned { 1 } else { 0 } },
+                        &(data.context.answering as i64),
+                        &0_i64
+                    ]
+                );
+
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
+            }
+
+            // requests are keyed by the ID of the asker, so only one of their
+            // questions can be in the inbox at once
+            for data in &new_requests {
+                let res = execute!(
+                    conn,
+                    "INSERT INTO requests VALUES ($1, $2, $3, $4, $5) ON CONFLICT DO NOTHING",
+                    params![
+                        &(data.id as i64),
+                        &(data.created as i64),
+                        &(data.owner as i64),
+                        &serde_json::to_string(&data.action_type).unwrap().as_str(),
+                        &(data.linked_asset as i64),
+                    ]
+                );
+
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
+            }
+
+            for (table, data) in new_follows
+                .iter()
+                .map(|x| ("userfollows", (x.id, x.created, x.initiator, x.receiver)))
+                .chain(
+                    new_blocks
+                        .iter()
+                        .map(|x| ("userblocks", (x.id, x.created, x.initiator, x.receiver))),
+                )
+            {
+                let res = execute!(
+                    conn,
+                    &format!("INSERT INTO {table} VALUES ($1, $2, $3, $4)"),
+                    params![
+                        &(data.0 as i64),
+                        &(data.1 as i64),
+                        &(data.2 as i64),
+                        &(data.3 as i64)
+                    ]
+                );
+
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
+            }
+
+            for data in &new_reactions {
+                let res = execute!(
+                    conn,
+                    "INSERT INTO reactions VALUES ($1, $2, $3, $4, $5, $6)",
+                    params![
+                        &(data.id as i64),
+                        &(data.created as i64),
+                        &(data.owner as i64),
+                        &(data.asset as i64),
+                        &serde_json::to_string(&data.asset_type).unwrap().as_str(),
+                        &{ if data.is_like { 1 } else { 0 } }
+                    ]
+                );
+
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
+            }
+
+            Ok(())
+        })
+        .await?;
+
+        // images
+        for (dir, data) in [("avatars", avatar), ("banners", banner)] {
+            let Some(data) = data else {
+                continue;
+            };
+
+            let dir = PathBufD::current().extend(&[self.0.dirs.media.as_str(), dir]);
+
+            if let Err(e) = create_dir_all(&dir)
+                .and_then(|_| write(dir.join(format!("{}.avif", user.id as i64)), data))
+            {
+                return Err(Error::MiscError(e.to_string()));
+            }
+        }
+
+        // fix counters
+        let mut assets: Vec<(AssetType, usize)> = vec![(AssetType::User, user.id)];
+        assets.extend(new_follows.iter().flat_map(|x| {
+            [
+                (AssetType::User, x.initiator),
+                (AssetType::User, x.receiver),
+            ]
+        }));
+        assets.extend(community_ids.values().map(|x| (AssetType::Community, *x)));
+        assets.extend(new_posts.iter().map(|x| (AssetType::Post, x.id)));
+        assets.extend(new_questions.iter().map(|x| (AssetType::Question, x.id)));
+
+        let mut seen: HashSet<(usize, usize)> = HashSet::new();
+        for (asset_type, id) in assets {
+            if seen.insert((asset_type as usize, id)) {
+                self.reconcile_asset_counters(asset_type, id).await?;
+            }
+        }
+
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replace_mentions_replaces_links() {
+        let content = "hi [@a](/api/v1/auth/user/find/1) and [@b](/api/v1/auth/user/find/22)!";
+        assert_eq!(
+            replace_mentions(content, |name| format!("@{name}")),
+            "hi @a and @b!"
+        );
+    }
+
+    #[test]
+    fn replace_mentions_ignores_malformed_links() {
+        for content in [
+            "[@a](/api/v1/auth/user/find/abc)",
+            "[@](/api/v1/auth/user/find/1)",
+            "[@a b](/api/v1/auth/user/find/1)",
+            "[@a](/api/v1/auth/user/find/1",
+            "[@a](/post/1)",
+            "[@",
+        ] {
+            assert_eq!(replace_mentions(content, |_| String::new()), content);
+        }
+    }
+
+    #[test]
+    fn replace_mentions_skips_broken_link_before_valid_one() {
+        assert_eq!(
+            replace_mentions("[@ [@a](/api/v1/auth/user/find/1)", |name| name.to_string()),
+            "[@ a"
+        );
+    }
+
+    #[test]
+    fn remap_paths_replaces_known_ids() {
+        let ids = HashMap::from([(1, 10), (2, 20)]);
+        assert_eq!(
+            remap_paths("/post/1 /post/2/edit /post/3 /post/", "/post/", &ids),
+            "/post/10 /post/20/edit /post/3 /post/"
+        );
+    }
+
+    #[test]
+    fn remap_paths_only_reads_whole_ids() {
+        let ids = HashMap::from([(1, 10)]);
+        assert_eq!(
+            remap_paths("/post/12 /post/1a", "/post/", &ids),
+            "/post/12 /post/10a"
+        );
+    }
+}
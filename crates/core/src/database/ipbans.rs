@@ -15,13 +15,13 @@ impl DataManager {
     pub(crate) fn get_ipban_from_row(
         #[cfg(feature = "sqlite")] x: &Row<'_>,
         #[cfg(feature = "postgres")] x: &Row,
-    ) -> IpBan {
-        IpBan {
+    ) -> Result<IpBan> {
+        Ok(IpBan {
             ip: get!(x->0(String)),
             created: get!(x->1(i64)) as usize,
             reason: get!(x->2(String)),
             moderator: get!(x->3(i64)) as usize,
-        }
+        })
     }
 
     auto_method!(get_ipban_by_ip(&str)@get_ipban_from_row -> "SELECT * FROM ipbans WHERE ip = $1" --name="ip ban" --returns=IpBan --cache-key-tmpl="atto.ipban:{}");
@@ -42,13 +42,9 @@ impl DataManager {
             "SELECT * FROM ipbans ORDER BY created DESC LIMIT $1 OFFSET $2",
             &[&(batch as i64), &((page * batch) as i64)],
             |x| { Self::get_ipban_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("ip ban".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Create a new IP ban in the database.
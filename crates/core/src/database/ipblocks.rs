@@ -14,13 +14,13 @@ impl DataManager {
     pub(crate) fn get_ipblock_from_row(
         #[cfg(feature = "sqlite")] x: &Row<'_>,
         #[cfg(feature = "postgres")] x: &Row,
-    ) -> IpBlock {
-        IpBlock {
+    ) -> Result<IpBlock> {
+        Ok(IpBlock {
             id: get!(x->0(i64)) as usize,
             created: get!(x->1(i64)) as usize,
             initiator: get!(x->2(i64)) as usize,
             receiver: get!(x->3(String)),
-        }
+        })
     }
 
     auto_method!(get_ipblock_by_id()@get_ipblock_from_row -> "SELECT * FROM ipblocks WHERE id = $1" --name="ip block" --returns=IpBlock --cache-key-tmpl="atto.ipblock:{}");
@@ -40,14 +40,14 @@ impl DataManager {
             &conn,
             "SELECT * FROM ipblocks WHERE initiator = $1 AND receiver = $2",
             params![&(initiator as i64), &receiver],
-            |x| { Ok(Self::get_ipblock_from_row(x)) }
-        );
+            |x| { Self::get_ipblock_from_row(x) }
+        )?;
 
-        if res.is_err() {
+        let Some(res) = res else {
             return Err(Error::GeneralNotFound("user block".to_string()));
-        }
+        };
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Get a user block by `receiver` and `initiator` (in that order).
@@ -65,14 +65,14 @@ impl DataManager {
             &conn,
             "SELECT * FROM ipblocks WHERE receiver = $1 AND initiator = $2",
             params![&receiver, &(initiator as i64)],
-            |x| { Ok(Self::get_ipblock_from_row(x)) }
-        );
+            |x| { Self::get_ipblock_from_row(x) }
+        )?;
 
-        if res.is_err() {
+        let Some(res) = res else {
             return Err(Error::GeneralNotFound("user block".to_string()));
-        }
+        };
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Create a new user block in the database.
@@ -1,5 +1,6 @@
 use super::*;
 use crate::cache::Cache;
+use crate::model::auth::Notification;
 use crate::model::communities::Community;
 use crate::model::requests::{ActionRequest, ActionType};
 use crate::model::{
@@ -22,14 +23,17 @@ impl DataManager {
     pub(crate) fn get_membership_from_row(
         #[cfg(feature = "sqlite")] x: &Row<'_>,
         #[cfg(feature = "postgres")] x: &Row,
-    ) -> CommunityMembership {
-        CommunityMembership {
+    ) -> Result<CommunityMembership> {
+        Ok(CommunityMembership {
             id: get!(x->0(i64)) as usize,
             created: get!(x->1(i64)) as usize,
             owner: get!(x->2(i64)) as usize,
             community: get!(x->3(i64)) as usize,
-            role: CommunityPermission::from_bits(get!(x->4(i32)) as u32).unwrap(),
-        }
+            role: match CommunityPermission::from_bits(get!(x->4(i32)) as u32) {
+                Some(x) => x,
+                None => return Err(Error::DatabaseError("invalid role".to_string())),
+            },
+        })
     }
 
     auto_method!(get_membership_by_id()@get_membership_from_row -> "SELECT * FROM memberships WHERE id = $1" --name="community membership" --returns=CommunityMembership --cache-key-tmpl="atto.membership:{}");
@@ -71,19 +75,19 @@ impl DataManager {
             &conn,
             "SELECT * FROM memberships WHERE owner = $1 AND community = $2",
             &[&(owner as i64), &(community as i64)],
-            |x| { Ok(Self::get_membership_from_row(x)) }
-        );
+            |x| { Self::get_membership_from_row(x) }
+        )?;
 
-        if res.is_err() {
+        let Some(res) = res else {
             // return Err(Error::GeneralNotFound("community membership".to_string()));
             return Ok(CommunityMembership::new(
                 owner,
                 community,
                 CommunityPermission::DEFAULT,
             ));
-        }
+        };
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Get a community membership by `owner` and `community`.
@@ -101,14 +105,14 @@ impl DataManager {
             &conn,
             "SELECT * FROM memberships WHERE owner = $1 AND community = $2",
             &[&(owner as i64), &(community as i64)],
-            |x| { Ok(Self::get_membership_from_row(x)) }
-        );
+            |x| { Self::get_membership_from_row(x) }
+        )?;
 
-        if res.is_err() {
+        let Some(res) = res else {
             return Err(Error::GeneralNotFound("community membership".to_string()));
-        }
+        };
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Get all community memberships by `owner`.
@@ -121,16 +125,12 @@ impl DataManager {
         let res = query_rows!(
             &conn,
             // 33 = banned, 65 = pending membership
-            "SELECT * FROM memberships WHERE owner = $1 AND NOT role = 33 AND NOT role = 65 ORDER BY created DESC",
+            "SELECT * FROM memberships WHERE owner = $1 AND NOT role = 33 AND NOT role = 65 AND community NOT IN (SELECT id FROM communities WHERE NOT deleted_at = 0) ORDER BY created DESC",
             &[&(owner as i64)],
             |x| { Self::get_membership_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("community membership".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Get all community memberships by `community`.
@@ -157,13 +157,9 @@ impl DataManager {
                 &((page * batch) as i64)
             ],
             |x| { Self::get_membership_from_row(x) }
-        );
+        )?;
 
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("community membership".to_string()));
-        }
-
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Create a new community membership in the database.
@@ -178,7 +174,7 @@ impl DataManager {
             .await
             .is_ok()
         {
-            return Err(Error::MiscError("Already joined community".to_string()));
+            return Err(Error::AlreadyJoined);
         }
 
         // check permission
@@ -186,54 +182,62 @@ impl DataManager {
 
         match community.join_access {
             CommunityJoinAccess::Nobody => return Err(Error::NotAllowed),
-            CommunityJoinAccess::Request => {
-                if !data.role.check(CommunityPermission::REQUESTED) {
-                    let mut data = data.clone();
-                    data.role = CommunityPermission::DEFAULT | CommunityPermission::REQUESTED;
-
-                    // create join request
-                    self.create_request(ActionRequest::with_id(
-                        data.owner,
-                        community.owner,
-                        ActionType::CommunityJoin,
-                        community.id,
-                    ))
-                    .await?;
-
-                    // ...
-                    return self.create_membership(data).await;
-                }
+            CommunityJoinAccess::Request if !data.role.check(CommunityPermission::REQUESTED) => {
+                let mut data = data.clone();
+                data.role = CommunityPermission::DEFAULT | CommunityPermission::REQUESTED;
+
+                // create join request
+                self.create_request(ActionRequest::with_id(
+                    data.owner,
+                    community.owner,
+                    ActionType::CommunityJoin,
+                    community.id,
+                ))
+                .await?;
+
+                // ...
+                return self.create_membership(data).await;
             }
             _ => (),
         }
 
         // ...
-        let conn = match self.connect().await {
-            Ok(c) => c,
-            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
-        };
+        self.transaction(async |conn| {
+            let res = execute!(
+                conn,
+                "INSERT INTO memberships VALUES ($1, $2, $3, $4, $5)",
+                params![
+                    &(data.id as i64),
+                    &(data.created as i64),
+                    &(data.owner as i64),
+                    &(data.community as i64),
+                    &(data.role.bits() as i32),
+                ]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
-        let res = execute!(
-            &conn,
-            "INSERT INTO memberships VALUES ($1, $2, $3, $4, $5)",
-            params![
-                &(data.id as i64),
-                &(data.created as i64),
-                &(data.owner as i64),
-                &(data.community as i64),
-                &(data.role.bits() as i32),
-            ]
-        );
-
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+            if !data.role.check(CommunityPermission::REQUESTED) {
+                // users who are just a requesting to join do not count towards the member count
+                let res = execute!(
+                    conn,
+                    "UPDATE communities SET member_count = member_count + 1 WHERE id = $1",
+                    &[&(data.community as i64)]
+                );
+
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
+            }
+
+            Ok(())
+        })
+        .await?;
 
         if !data.role.check(CommunityPermission::REQUESTED) {
-            // users who are just a requesting to join do not count towards the member count
-            self.incr_community_member_count(data.community)
-                .await
-                .unwrap();
+            self.cache_clear_community(&community).await;
         }
 
         Ok(if data.role.check(CommunityPermission::REQUESTED) {
@@ -264,51 +268,137 @@ impl DataManager {
             }
         }
 
-        let conn = match self.connect().await {
-            Ok(c) => c,
-            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
-        };
+        // requested and banned members don't count towards the member count
+        let is_counted = !y.role.contains(CommunityPermission::REQUESTED) && !y.role.check_banned();
 
-        let res = execute!(
-            &conn,
-            "DELETE FROM memberships WHERE id = $1",
-            &[&(id as i64)]
-        );
+        self.transaction(async |conn| {
+            let res = execute!(
+                conn,
+                "DELETE FROM memberships WHERE id = $1",
+                &[&(id as i64)]
+            );
 
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
+
+            if is_counted {
+                let res = execute!(
+                    conn,
+                    "UPDATE communities SET member_count = member_count - 1 WHERE id = $1",
+                    &[&(y.community as i64)]
+                );
+
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
+            }
+
+            Ok(())
+        })
+        .await?;
 
         self.2.remove(format!("atto.membership:{}", id)).await;
 
-        self.decr_community_member_count(y.community).await.unwrap();
+        if is_counted {
+            let community = self.get_community_by_id_no_void(y.community).await?;
+            self.cache_clear_community(&community).await;
+        }
 
         Ok(())
     }
 
-    /// Update a membership's role given its `id`
+    /// Update a membership's role given its `id`. Sends the member a notification
+    /// and updates the community's member count if they were banned, unbanned, or
+    /// accepted.
     pub async fn update_membership_role(
         &self,
         id: usize,
         new_role: CommunityPermission,
     ) -> Result<()> {
-        let conn = match self.connect().await {
-            Ok(c) => c,
-            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
-        };
+        let y = self.get_membership_by_id(id).await?;
+        let community = self.get_community_by_id_no_void(y.community).await?;
+
+        // check if the user was just banned/unbanned (and send notifs)
+        let (notification, member_count_change): (Option<(&str, &str)>, i32) =
+            if new_role.check_banned() {
+                // banned members do not count towards member count
+                (
+                    Some(("You have been banned from a community.", "banned from")),
+                    -1,
+                )
+            } else if y.role.check_banned() {
+                (
+                    Some(("You have been unbanned from a community.", "unbanned from")),
+                    1,
+                )
+            } else if new_role.check(CommunityPermission::REQUESTED) {
+                // user was demoted to a request again
+                (None, -1)
+            } else if y.role.check(CommunityPermission::REQUESTED) {
+                (
+                    Some((
+                        "You have been accepted into a community you requested to join!",
+                        "accepted into",
+                    )),
+                    1,
+                )
+            } else {
+                (None, 0)
+            };
+
+        let notification = notification.map(|(title, action)| {
+            Notification::new(
+                title.to_string(),
+                format!(
+                    "You have been {action} [{}](/community/{}).",
+                    community.title, community.title
+                ),
+                y.owner,
+            )
+        });
+
+        self.transaction(async |conn| {
+            let res = execute!(
+                conn,
+                "UPDATE memberships SET role = $1 WHERE id = $2",
+                params![&(new_role.bits() as i32), &(id as i64)]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
-        let res = execute!(
-            &conn,
-            "UPDATE memberships SET role = $1 WHERE id = $2",
-            params![&(new_role.bits() as i32), &(id as i64)]
-        );
+            if let Some(ref notification) = notification {
+                Self::insert_notification(conn, notification).await?;
+            }
 
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+            if member_count_change != 0 {
+                let res = execute!(
+                    conn,
+                    "UPDATE communities SET member_count = member_count + $1 WHERE id = $2",
+                    params![&member_count_change, &(community.id as i64)]
+                );
+
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
+            }
+
+            Ok(())
+        })
+        .await?;
 
         self.2.remove(format!("atto.membership:{}", id)).await;
 
+        if notification.is_some() {
+            self.cache_clear_user_by_id(y.owner).await;
+        }
+
+        if member_count_change != 0 {
+            self.cache_clear_community(&community).await;
+        }
+
         Ok(())
     }
 }
@@ -0,0 +1,596 @@
+use super::*;
+use crate::model::{Error, Result};
+use crate::{execute, get, query_row, params};
+use tetratto_shared::unix_epoch_timestamp;
+
+/// A single change made by a [`Migration`].
+pub enum MigrationStep {
+    /// Add a column to an existing table.
+    ///
+    /// Skipped if the column already exists, since the `CREATE TABLE` scripts always
+    /// describe the newest schema (and older instances may have applied the change
+    /// by hand before migrations were tracked).
+    AddColumn {
+        table: &'static str,
+        column: &'static str,
+        definition: &'static str,
+    },
+    /// Run a single SQL statement.
+    Sql(&'static str),
+    /// Delete every row of `table` which has the same values in `columns` as an
+    /// older row (the row with the smallest ID is kept). The number of dropped rows
+    /// is logged.
+    DropDuplicates {
+        table: &'static str,
+        columns: &'static str,
+    },
+}
+
+/// A versioned change to the database schema.
+pub struct Migration {
+    /// The schema version this migration brings the database to.
+    pub version: usize,
+    pub name: &'static str,
+    pub steps: &'static [MigrationStep],
+}
+
+/// All known migrations, in the order they must be applied.
+///
+/// Versions must be strictly increasing. **Never** edit or remove a migration
+/// once it has been released; add a new one instead.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "users_totp",
+        steps: &[
+            MigrationStep::AddColumn {
+                table: "users",
+                column: "totp",
+                definition: "TEXT DEFAULT ''",
+            },
+            MigrationStep::AddColumn {
+                table: "users",
+                column: "recovery_codes",
+                definition: "TEXT DEFAULT '[]'",
+            },
+        ],
+    },
+    Migration {
+        version: 2,
+        name: "users_post_count",
+        steps: &[MigrationStep::AddColumn {
+            table: "users",
+            column: "post_count",
+            definition: "INT NOT NULL DEFAULT 0",
+        }],
+    },
+    Migration {
+        version: 3,
+        name: "users_request_count",
+        steps: &[MigrationStep::AddColumn {
+            table: "users",
+            column: "request_count",
+            definition: "INT NOT NULL DEFAULT 0",
+        }],
+    },
+    Migration {
+        version: 4,
+        name: "questions_context",
+        steps: &[MigrationStep::AddColumn {
+            table: "questions",
+            column: "context",
+            definition: "TEXT NOT NULL DEFAULT '{}'",
+        }],
+    },
+    Migration {
+        version: 5,
+        name: "questions_likes",
+        steps: &[
+            MigrationStep::AddColumn {
+                table: "questions",
+                column: "likes",
+                definition: "INT NOT NULL DEFAULT 0",
+            },
+            MigrationStep::AddColumn {
+                table: "questions",
+                column: "dislikes",
+                definition: "INT NOT NULL DEFAULT 0",
+            },
+        ],
+    },
+    Migration {
+        version: 6,
+        name: "questions_ip",
+        steps: &[MigrationStep::AddColumn {
+            table: "questions",
+            column: "ip",
+            definition: "TEXT NOT NULL DEFAULT ''",
+        }],
+    },
+    Migration {
+        version: 7,
+        name: "search_index",
+        steps: SEARCH_INDEX_STEPS,
+    },
+    Migration {
+        version: 8,
+        name: "context_columns",
+        steps: &[
+            MigrationStep::AddColumn {
+                table: "posts",
+                column: "is_nsfw",
+                definition: "INT NOT NULL DEFAULT 0",
+            },
+            MigrationStep::AddColumn {
+                table: "posts",
+                column: "is_pinned",
+                definition: "INT NOT NULL DEFAULT 0",
+            },
+            MigrationStep::AddColumn {
+                table: "posts",
+                column: "is_profile_pinned",
+                definition: "INT NOT NULL DEFAULT 0",
+            },
+            MigrationStep::AddColumn {
+                table: "posts",
+                column: "answering",
+                definition: "BIGINT NOT NULL DEFAULT 0",
+            },
+            MigrationStep::AddColumn {
+                table: "communities",
+                column: "is_nsfw",
+                definition: "INT NOT NULL DEFAULT 0",
+            },
+            MigrationStep::AddColumn {
+                table: "questions",
+                column: "is_nsfw",
+                definition: "INT NOT NULL DEFAULT 0",
+            },
+            MigrationStep::Sql(POSTS_CONTEXT_BACKFILL),
+            MigrationStep::Sql(COMMUNITIES_CONTEXT_BACKFILL),
+            MigrationStep::Sql(QUESTIONS_CONTEXT_BACKFILL),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS posts_is_nsfw_idx ON posts (is_nsfw, created)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS posts_is_pinned_idx ON posts (community, is_pinned)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS posts_is_profile_pinned_idx ON posts (owner, is_profile_pinned)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS posts_answering_idx ON posts (answering)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS communities_is_nsfw_idx ON communities (is_nsfw)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS questions_is_nsfw_idx ON questions (is_nsfw, created)",
+            ),
+        ],
+    },
+    Migration {
+        version: 9,
+        name: "secondary_indexes",
+        steps: &[
+            // lookups
+            MigrationStep::Sql("CREATE INDEX IF NOT EXISTS users_username_idx ON users (username)"),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS communities_title_idx ON communities (title)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS communities_owner_idx ON communities (owner)",
+            ),
+            // listings (sorted by the same keys as the pagination cursors)
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS posts_owner_idx ON posts (owner, created, id)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS posts_community_idx ON posts (community, created, id)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS posts_replying_to_idx ON posts (replying_to, created, id)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS questions_owner_idx ON questions (owner, created)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS questions_receiver_idx ON questions (receiver, created)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS questions_community_idx ON questions (community, created, id)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS questions_is_global_idx ON questions (is_global, created, id)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS memberships_community_idx ON memberships (community, created)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS userfollows_initiator_idx ON userfollows (initiator, created, id)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS userfollows_receiver_idx ON userfollows (receiver, created, id)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS userblocks_receiver_idx ON userblocks (receiver)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS reactions_asset_idx ON reactions (asset, asset_type, is_like)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS notifications_owner_idx ON notifications (owner, created, id)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS requests_owner_idx ON requests (owner, created)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS user_warnings_receiver_idx ON user_warnings (receiver, created)",
+            ),
+            // uniques (dropping any duplicates first, keeping the oldest row)
+            MigrationStep::DropDuplicates {
+                table: "memberships",
+                columns: "owner, community",
+            },
+            MigrationStep::Sql(
+                "CREATE UNIQUE INDEX IF NOT EXISTS memberships_owner_community_idx ON memberships (owner, community)",
+            ),
+            MigrationStep::DropDuplicates {
+                table: "userfollows",
+                columns: "initiator, receiver",
+            },
+            MigrationStep::Sql(
+                "CREATE UNIQUE INDEX IF NOT EXISTS userfollows_initiator_receiver_idx ON userfollows (initiator, receiver)",
+            ),
+            MigrationStep::DropDuplicates {
+                table: "userblocks",
+                columns: "initiator, receiver",
+            },
+            MigrationStep::Sql(
+                "CREATE UNIQUE INDEX IF NOT EXISTS userblocks_initiator_receiver_idx ON userblocks (initiator, receiver)",
+            ),
+            MigrationStep::DropDuplicates {
+                table: "ipblocks",
+                columns: "initiator, receiver",
+            },
+            MigrationStep::Sql(
+                "CREATE UNIQUE INDEX IF NOT EXISTS ipblocks_initiator_receiver_idx ON ipblocks (initiator, receiver)",
+            ),
+            MigrationStep::DropDuplicates {
+                table: "reactions",
+                columns: "owner, asset",
+            },
+            MigrationStep::Sql(
+                "CREATE UNIQUE INDEX IF NOT EXISTS reactions_owner_asset_idx ON reactions (owner, asset)",
+            ),
+            // counters which included the dropped rows
+            // (written out here instead of using `reconcile_counters`, since its
+            // counters may depend on columns added by later migrations)
+            MigrationStep::Sql(
+                "UPDATE users SET follower_count = (SELECT COUNT(*) FROM userfollows WHERE receiver = users.id), following_count = (SELECT COUNT(*) FROM userfollows WHERE initiator = users.id)",
+            ),
+            MigrationStep::Sql(
+                "UPDATE communities SET member_count = (SELECT COUNT(*) FROM memberships WHERE community = communities.id AND (role & 64) = 0 AND (role & 32) = 0)",
+            ),
+            MigrationStep::Sql(
+                "UPDATE communities SET likes = (SELECT COUNT(*) FROM reactions WHERE asset = communities.id AND asset_type = '\"Community\"' AND is_like = 1), dislikes = (SELECT COUNT(*) FROM reactions WHERE asset = communities.id AND asset_type = '\"Community\"' AND is_like = 0)",
+            ),
+            MigrationStep::Sql(
+                "UPDATE posts SET likes = (SELECT COUNT(*) FROM reactions WHERE asset = posts.id AND asset_type = '\"Post\"' AND is_like = 1), dislikes = (SELECT COUNT(*) FROM reactions WHERE asset = posts.id AND asset_type = '\"Post\"' AND is_like = 0)",
+            ),
+            MigrationStep::Sql(
+                "UPDATE questions SET likes = (SELECT COUNT(*) FROM reactions WHERE asset = questions.id AND asset_type = '\"Question\"' AND is_like = 1), dislikes = (SELECT COUNT(*) FROM reactions WHERE asset = questions.id AND asset_type = '\"Question\"' AND is_like = 0)",
+            ),
+        ],
+    },
+    Migration {
+        version: 10,
+        name: "soft_delete",
+        steps: &[
+            MigrationStep::AddColumn {
+                table: "posts",
+                column: "deleted_at",
+                definition: "BIGINT NOT NULL DEFAULT 0",
+            },
+            MigrationStep::AddColumn {
+                table: "questions",
+                column: "deleted_at",
+                definition: "BIGINT NOT NULL DEFAULT 0",
+            },
+            MigrationStep::AddColumn {
+                table: "communities",
+                column: "deleted_at",
+                definition: "BIGINT NOT NULL DEFAULT 0",
+            },
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS posts_deleted_at_idx ON posts (deleted_at)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS questions_deleted_at_idx ON questions (deleted_at)",
+            ),
+            MigrationStep::Sql(
+                "CREATE INDEX IF NOT EXISTS communities_deleted_at_idx ON communities (deleted_at)",
+            ),
+        ],
+    },
+    Migration {
+        version: 11,
+        name: "post_revisions",
+        steps: &[MigrationStep::Sql(
+            "CREATE INDEX IF NOT EXISTS post_revisions_post_idx ON post_revisions (post, created)",
+        )],
+    },
+];
+
+/// Full-text indexes over `posts.content` and `questions.content`.
+///
+/// The FTS5 tables are external content tables, so they only store the index and
+/// are kept in sync with their source table by triggers.
+#[cfg(feature = "sqlite")]
+const SEARCH_INDEX_STEPS: &[MigrationStep] = &[
+    // posts
+    MigrationStep::Sql(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS posts_fts USING fts5(content, content = 'posts', content_rowid = 'id')",
+    ),
+    MigrationStep::Sql(
+        "CREATE TRIGGER IF NOT EXISTS posts_fts_insert AFTER INSERT ON posts BEGIN
+            INSERT INTO posts_fts(rowid, content) VALUES (new.id, new.content);
+        END",
+    ),
+    MigrationStep::Sql(
+        "CREATE TRIGGER IF NOT EXISTS posts_fts_delete AFTER DELETE ON posts BEGIN
+            INSERT INTO posts_fts(posts_fts, rowid, content) VALUES ('delete', old.id, old.content);
+        END",
+    ),
+    MigrationStep::Sql(
+        "CREATE TRIGGER IF NOT EXISTS posts_fts_update AFTER UPDATE OF content ON posts BEGIN
+            INSERT INTO posts_fts(posts_fts, rowid, content) VALUES ('delete', old.id, old.content);
+            INSERT INTO posts_fts(rowid, content) VALUES (new.id, new.content);
+        END",
+    ),
+    MigrationStep::Sql("INSERT INTO posts_fts(posts_fts) VALUES ('rebuild')"),
+    // questions
+    MigrationStep::Sql(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS questions_fts USING fts5(content, content = 'questions', content_rowid = 'id')",
+    ),
+    MigrationStep::Sql(
+        "CREATE TRIGGER IF NOT EXISTS questions_fts_insert AFTER INSERT ON questions BEGIN
+            INSERT INTO questions_fts(rowid, content) VALUES (new.id, new.content);
+        END",
+    ),
+    MigrationStep::Sql(
+        "CREATE TRIGGER IF NOT EXISTS questions_fts_delete AFTER DELETE ON questions BEGIN
+            INSERT INTO questions_fts(questions_fts, rowid, content) VALUES ('delete', old.id, old.content);
+        END",
+    ),
+    MigrationStep::Sql(
+        "CREATE TRIGGER IF NOT EXISTS questions_fts_update AFTER UPDATE OF content ON questions BEGIN
+            INSERT INTO questions_fts(questions_fts, rowid, content) VALUES ('delete', old.id, old.content);
+            INSERT INTO questions_fts(rowid, content) VALUES (new.id, new.content);
+        END",
+    ),
+    MigrationStep::Sql("INSERT INTO questions_fts(questions_fts) VALUES ('rebuild')"),
+];
+
+/// Full-text indexes over `posts.content` and `questions.content`.
+///
+/// Queries must use the exact same `to_tsvector` expression for the index to be used.
+#[cfg(feature = "postgres")]
+const SEARCH_INDEX_STEPS: &[MigrationStep] = &[
+    MigrationStep::Sql(
+        "CREATE INDEX IF NOT EXISTS posts_search_idx ON posts USING GIN (to_tsvector('simple', content))",
+    ),
+    MigrationStep::Sql(
+        "CREATE INDEX IF NOT EXISTS questions_search_idx ON questions USING GIN (to_tsvector('simple', content))",
+    ),
+];
+
+/// Get the newest schema version known to this build.
+pub fn latest_schema_version() -> usize {
+    MIGRATIONS.last().map(|x| x.version).unwrap_or(0)
+}
+
+impl DataManager {
+    /// Check if a table with the given name exists.
+    async fn table_exists(&self, table: &str) -> Result<bool> {
+        let conn = match self.connect().await {
+            Ok(c) => c,
+            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+        };
+
+        #[cfg(feature = "sqlite")]
+        let query = "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = $1";
+        #[cfg(feature = "postgres")]
+        let query = "SELECT COUNT(*) FROM information_schema.tables WHERE table_name = $1";
+
+        let res = query_row!(&conn, query, params![&table], |x| Ok(get!(x->0(i64))))?;
+
+        Ok(res.unwrap_or(0) > 0)
+    }
+
+    /// Check if the given table has a column with the given name.
+    async fn column_exists(&self, table: &str, column: &str) -> Result<bool> {
+        let conn = match self.connect().await {
+            Ok(c) => c,
+            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+        };
+
+        #[cfg(feature = "sqlite")]
+        let query = "SELECT COUNT(*) FROM pragma_table_info($1) WHERE name = $2";
+        #[cfg(feature = "postgres")]
+        let query = "SELECT COUNT(*) FROM information_schema.columns WHERE table_name = $1 AND column_name = $2";
+
+        let res = query_row!(&conn, query, params![&table, &column], |x| Ok(
+            get!(x->0(i64))
+        ))?;
+
+        Ok(res.unwrap_or(0) > 0)
+    }
+
+    /// Get the schema version of the database. Databases which have never been
+    /// migrated are at version `0`.
+    pub async fn get_schema_version(&self) -> Result<usize> {
+        if !self.table_exists("schema_version").await? {
+            return Ok(0);
+        }
+
+        let conn = match self.connect().await {
+            Ok(c) => c,
+            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+        };
+
+        let res = query_row!(
+            &conn,
+            "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+            params![],
+            |x| Ok(get!(x->0(i64)))
+        )?;
+
+        Ok(res.unwrap_or(0) as usize)
+    }
+
+    /// Get all migrations which haven't been applied to the database yet. Nothing
+    /// is changed, so this can be used for a dry run.
+    pub async fn get_pending_migrations(&self) -> Result<Vec<&'static Migration>> {
+        let current = self.get_schema_version().await?;
+        let latest = latest_schema_version();
+
+        if current > latest {
+            return Err(Error::DatabaseError(format!(
+                "database schema version ({current}) is newer than this build supports ({latest}), refusing to continue"
+            )));
+        }
+
+        Ok(MIGRATIONS.iter().filter(|x| x.version > current).collect())
+    }
+
+    /// Apply a single [`Migration`] and record it in the `schema_version` table.
+    ///
+    /// All steps run in one transaction, so a failed migration leaves the database
+    /// at the previous version.
+    async fn apply_migration(&self, migration: &Migration) -> Result<()> {
+        // figure out which columns already exist before we lock anything
+        // (along with the table and query used to count the rows dropped by a step)
+        let mut run_steps: Vec<(String, Option<(&'static str, String)>)> = Vec::new();
+
+        for step in migration.steps {
+            match step {
+                MigrationStep::AddColumn {
+                    table,
+                    column,
+                    definition,
+                } => {
+                    if !self.column_exists(table, column).await? {
+                        run_steps.push((
+                            format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"),
+                            None,
+                        ));
+                    }
+                }
+                MigrationStep::Sql(sql) => run_steps.push((sql.to_string(), None)),
+                MigrationStep::DropDuplicates { table, columns } => {
+                    let filter =
+                        format!("id NOT IN (SELECT MIN(id) FROM {table} GROUP BY {columns})");
+
+                    run_steps.push((
+                        format!("DELETE FROM {table} WHERE {filter}"),
+                        Some((
+                            table,
+                            format!("SELECT COUNT(*) FROM {table} WHERE {filter}"),
+                        )),
+                    ));
+                }
+            }
+        }
+
+        self.transaction(async |conn| {
+            for (sql, count) in run_steps {
+                if let Some((table, count)) = count {
+                    let dropped =
+                        query_row!(conn, &count, params![], |x| Ok(get!(x->0(i64))))?.unwrap_or(0);
+
+                    if dropped > 0 {
+                        tracing::warn!(
+                            "migration {} ({}) dropped {dropped} duplicate rows from {table}",
+                            migration.version,
+                            migration.name
+                        );
+                    }
+                }
+
+                if let Err(e) = execute!(conn, &sql) {
+                    return Err(Error::DatabaseError(format!(
+                        "migration {} ({}) failed: {e}",
+                        migration.version, migration.name
+                    )));
+                }
+            }
+
+            let res = execute!(
+                conn,
+                "INSERT INTO schema_version VALUES ($1, $2, $3)",
+                params![
+                    &(migration.version as i64),
+                    &migration.name,
+                    &(unix_epoch_timestamp() as i64)
+                ]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Apply all pending migrations (in order).
+    pub async fn migrate(&self) -> Result<()> {
+        for migration in self.get_pending_migrations().await? {
+            tracing::info!(
+                "applying migration {} ({})",
+                migration.version,
+                migration.name
+            );
+
+            self.apply_migration(migration).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Copy the flags of existing posts out of their `context`.
+#[cfg(feature = "sqlite")]
+const POSTS_CONTEXT_BACKFILL: &str = "UPDATE posts SET
+    is_nsfw = COALESCE(json_extract(context, '$.is_nsfw'), 0),
+    is_pinned = COALESCE(json_extract(context, '$.is_pinned'), 0),
+    is_profile_pinned = COALESCE(json_extract(context, '$.is_profile_pinned'), 0),
+    answering = COALESCE(json_extract(context, '$.answering'), 0)";
+
+/// Copy the flags of existing posts out of their `context`.
+#[cfg(feature = "postgres")]
+const POSTS_CONTEXT_BACKFILL: &str = "UPDATE posts SET
+    is_nsfw = CASE WHEN (context::jsonb)->>'is_nsfw' = 'true' THEN 1 ELSE 0 END,
+    is_pinned = CASE WHEN (context::jsonb)->>'is_pinned' = 'true' THEN 1 ELSE 0 END,
+    is_profile_pinned = CASE WHEN (context::jsonb)->>'is_profile_pinned' = 'true' THEN 1 ELSE 0 END,
+    answering = COALESCE(CAST((context::jsonb)->>'answering' AS BIGINT), 0)";
+
+/// Copy the NSFW flag of existing communities out of their `context`.
+#[cfg(feature = "sqlite")]
+const COMMUNITIES_CONTEXT_BACKFILL: &str =
+    "UPDATE communities SET is_nsfw = COALESCE(json_extract(context, '$.is_nsfw'), 0)";
+
+/// Copy the NSFW flag of existing communities out of their `context`.
+#[cfg(feature = "postgres")]
+const COMMUNITIES_CONTEXT_BACKFILL: &str = "UPDATE communities SET is_nsfw = CASE WHEN (context::jsonb)->>'is_nsfw' = 'true' THEN 1 ELSE 0 END";
+
+/// Copy the NSFW flag of existing questions out of their `context`.
+#[cfg(feature = "sqlite")]
+const QUESTIONS_CONTEXT_BACKFILL: &str =
+    "UPDATE questions SET is_nsfw = COALESCE(json_extract(context, '$.is_nsfw'), 0)";
+
+/// Copy the NSFW flag of existing questions out of their `context`.
+#[cfg(feature = "postgres")]
+const QUESTIONS_CONTEXT_BACKFILL: &str = "UPDATE questions SET is_nsfw = CASE WHEN (context::jsonb)->>'is_nsfw' = 'true' THEN 1 ELSE 0 END";
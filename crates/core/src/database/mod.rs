@@ -2,20 +2,31 @@ mod audit_log;
 mod auth;
 mod common;
 mod communities;
+mod counters;
+mod deletions;
 mod drivers;
+mod export;
+mod feeds;
+mod import;
 mod ipbans;
 mod ipblocks;
 mod memberships;
+pub mod migrations;
 mod notifications;
+mod post_revisions;
 mod posts;
 mod questions;
+mod rate_limits;
 mod reactions;
 mod reports;
 mod requests;
+mod search;
 mod user_warnings;
 mod userblocks;
 mod userfollows;
 
+pub use export::RunningExport;
+
 #[cfg(feature = "sqlite")]
 pub use drivers::sqlite::*;
 
@@ -1,6 +1,7 @@
 use super::*;
 use crate::cache::Cache;
 use crate::model::{Error, Result, auth::Notification, auth::User, permissions::FinePermission};
+use crate::model::pagination::{Cursor, Paginated};
 use crate::{auto_method, execute, get, query_row, query_rows, params};
 
 #[cfg(feature = "sqlite")]
@@ -14,21 +15,51 @@ impl DataManager {
     pub(crate) fn get_notification_from_row(
         #[cfg(feature = "sqlite")] x: &Row<'_>,
         #[cfg(feature = "postgres")] x: &Row,
-    ) -> Notification {
-        Notification {
+    ) -> Result<Notification> {
+        Ok(Notification {
             id: get!(x->0(i64)) as usize,
             created: get!(x->1(i64)) as usize,
             title: get!(x->2(String)),
             content: get!(x->3(String)),
             owner: get!(x->4(i64)) as usize,
             read: get!(x->5(i32)) as i8 == 1,
-        }
+        })
     }
 
     auto_method!(get_notification_by_id()@get_notification_from_row -> "SELECT * FROM notifications WHERE id = $1" --name="notification" --returns=Notification --cache-key-tmpl="atto.notification:{}");
 
+    /// Get notifications by `owner` (from most recent).
+    ///
+    /// # Arguments
+    /// * `owner` - the ID of the user the requested notifications belong to
+    /// * `batch` - the limit of notifications in each page
+    /// * `cursor` - the cursor of the page (the first page if [`None`])
+    pub async fn get_notifications_by_owner(
+        &self,
+        owner: usize,
+        batch: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Paginated<Notification>> {
+        let conn = match self.connect().await {
+            Ok(c) => c,
+            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+        };
+
+        let cursor = cursor.unwrap_or_default();
+        let res = query_rows!(
+            &conn,
+            "SELECT * FROM notifications WHERE owner = $1 AND (created, id) < ($2, $3) ORDER BY created DESC, id DESC LIMIT $4",
+            &[&(owner as i64), &cursor.key, &cursor.id, &(batch as i64)],
+            |x| { Self::get_notification_from_row(x) }
+        )?;
+
+        Ok(Cursor::paginate(res, batch, |x| {
+            Cursor::new(x.created as i64, x.id)
+        }))
+    }
+
     /// Get all notifications by `owner`.
-    pub async fn get_notifications_by_owner(&self, owner: usize) -> Result<Vec<Notification>> {
+    pub async fn get_notifications_by_owner_all(&self, owner: usize) -> Result<Vec<Notification>> {
         let conn = match self.connect().await {
             Ok(c) => c,
             Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
@@ -39,13 +70,9 @@ impl DataManager {
             "SELECT * FROM notifications WHERE owner = $1 ORDER BY created DESC",
             &[&(owner as i64)],
             |x| { Self::get_notification_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("notification".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Create a new notification in the database.
@@ -53,13 +80,26 @@ impl DataManager {
     /// # Arguments
     /// * `data` - a mock [`Notification`] object to insert
     pub async fn create_notification(&self, data: Notification) -> Result<()> {
-        let conn = match self.connect().await {
-            Ok(c) => c,
-            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
-        };
+        self.transaction(async |conn| Self::insert_notification(conn, &data).await)
+            .await?;
 
+        self.cache_clear_user_by_id(data.owner).await;
+
+        // return
+        Ok(())
+    }
+
+    /// Insert a notification and increment its owner's notification count using
+    /// the given connection (likely inside of a transaction).
+    ///
+    /// The owner's cache entry is **not** cleared.
+    pub(crate) async fn insert_notification(
+        #[cfg(feature = "sqlite")] conn: &mut Connection,
+        #[cfg(feature = "postgres")] conn: &mut Connection<'_>,
+        data: &Notification,
+    ) -> Result<()> {
         let res = execute!(
-            &conn,
+            conn,
             "INSERT INTO notifications VALUES ($1, $2, $3, $4, $5, $6)",
             params![
                 &(data.id as i64),
@@ -76,9 +116,16 @@ impl DataManager {
         }
 
         // incr notification count
-        self.incr_user_notifications(data.owner).await.unwrap();
+        let res = execute!(
+            conn,
+            "UPDATE users SET notification_count = notification_count + 1 WHERE id = $1",
+            &[&(data.owner as i64)]
+        );
+
+        if let Err(e) = res {
+            return Err(Error::DatabaseError(e.to_string()));
+        }
 
-        // return
         Ok(())
     }
 
@@ -110,9 +157,7 @@ impl DataManager {
 
         // decr notification count
         if !notification.read {
-            self.decr_user_notifications(notification.owner)
-                .await
-                .unwrap();
+            self.decr_user_notifications(notification.owner).await?;
         }
 
         // return
@@ -120,7 +165,7 @@ impl DataManager {
     }
 
     pub async fn delete_all_notifications(&self, user: &User) -> Result<()> {
-        let notifications = self.get_notifications_by_owner(user.id).await?;
+        let notifications = self.get_notifications_by_owner_all(user.id).await?;
 
         for notification in notifications {
             if user.id != notification.owner
@@ -0,0 +1,88 @@
+use super::*;
+use crate::model::communities_permissions::CommunityPermission;
+use crate::model::{Error, Result, auth::User, communities::PostRevision, permissions::FinePermission};
+use crate::{get, query_rows};
+use std::collections::HashMap;
+
+#[cfg(feature = "sqlite")]
+use rusqlite::Row;
+
+#[cfg(feature = "postgres")]
+use tokio_postgres::Row;
+
+impl DataManager {
+    /// Get a [`PostRevision`] from an SQL row.
+    pub(crate) fn get_post_revision_from_row(
+        #[cfg(feature = "sqlite")] x: &Row<'_>,
+        #[cfg(feature = "postgres")] x: &Row,
+    ) -> Result<PostRevision> {
+        Ok(PostRevision {
+            id: get!(x->0(i64)) as usize,
+            created: get!(x->1(i64)) as usize,
+            post: get!(x->2(i64)) as usize,
+            editor: get!(x->3(i64)) as usize,
+            content: get!(x->4(String)),
+        })
+    }
+
+    /// Get all revisions of the given post (from most recent).
+    ///
+    /// Revisions can only be viewed by moderators with the `MANAGE_POSTS` permission
+    /// (globally or in the post's community). They're also available for deleted
+    /// posts, so reports can still be reviewed.
+    ///
+    /// # Arguments
+    /// * `id` - the ID of the post
+    /// * `user` - the user viewing the revisions
+    pub async fn get_post_revisions(&self, id: usize, user: &User) -> Result<Vec<PostRevision>> {
+        let post = match self.get_post_by_id(id).await {
+            Ok(p) => p,
+            Err(_) => self.get_deleted_post_by_id(id).await?,
+        };
+
+        if !user.permissions.check(FinePermission::MANAGE_POSTS) {
+            let membership = self
+                .get_membership_by_owner_community(user.id, post.community)
+                .await?;
+
+            if !membership.role.check(CommunityPermission::MANAGE_POSTS) {
+                return Err(Error::NotAllowed);
+            }
+        }
+
+        let conn = match self.connect().await {
+            Ok(c) => c,
+            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+        };
+
+        let res = query_rows!(
+            &conn,
+            "SELECT * FROM post_revisions WHERE post = $1 ORDER BY created DESC",
+            &[&(id as i64)],
+            |x| { Self::get_post_revision_from_row(x) }
+        )?;
+
+        Ok(res)
+    }
+
+    /// Fill the given vector of post revisions with their editor as well.
+    pub async fn fill_post_revisions(
+        &self,
+        revisions: Vec<PostRevision>,
+    ) -> Result<Vec<(PostRevision, User)>> {
+        let mut out: Vec<(PostRevision, User)> = Vec::new();
+
+        let mut seen_users: HashMap<usize, User> = HashMap::new();
+        for revision in revisions {
+            if let Some(ua) = seen_users.get(&revision.editor) {
+                out.push((revision, ua.to_owned()));
+            } else {
+                let user = self.get_user_by_id_with_void(revision.editor).await?;
+                seen_users.insert(revision.editor, user.clone());
+                out.push((revision, user));
+            }
+        }
+
+        Ok(out)
+    }
+}
@@ -1,18 +1,22 @@
 use std::collections::HashMap;
 
 use super::*;
+use super::feeds::POPULAR_POSTS;
 use crate::cache::Cache;
 use crate::model::auth::Notification;
 use crate::model::communities::Question;
 use crate::model::communities_permissions::CommunityPermission;
 use crate::model::moderation::AuditLogEntry;
+use crate::model::pagination::{Cursor, Paginated};
+use crate::model::requests::ActionRequest;
 use crate::model::{
     Error, Result,
     auth::User,
-    communities::{Community, CommunityWriteAccess, Post, PostContext},
+    communities::{Community, CommunityWriteAccess, Post, PostContext, PostRevision},
     permissions::FinePermission,
 };
 use crate::{auto_method, execute, get, query_row, query_rows, params};
+use tetratto_shared::snow::AlmostSnowflake;
 
 #[cfg(feature = "sqlite")]
 use rusqlite::Row;
@@ -21,59 +25,72 @@ use tetratto_shared::unix_epoch_timestamp;
 #[cfg(feature = "postgres")]
 use tokio_postgres::Row;
 
+/// A post along with its owner, the post it's reposting, and the question it's answering.
+pub type FullPost = (Post, User, Option<(User, Post)>, Option<(Question, User)>);
+
+/// A [`FullPost`] which also includes the post's community.
+pub type FullPostWithCommunity = (
+    Post,
+    User,
+    Community,
+    Option<(User, Post)>,
+    Option<(Question, User)>,
+);
+
 impl DataManager {
     /// Get a [`Post`] from an SQL row.
     pub(crate) fn get_post_from_row(
         #[cfg(feature = "sqlite")] x: &Row<'_>,
         #[cfg(feature = "postgres")] x: &Row,
-    ) -> Post {
-        Post {
+    ) -> Result<Post> {
+        Ok(Post {
             id: get!(x->0(i64)) as usize,
             created: get!(x->1(i64)) as usize,
             content: get!(x->2(String)),
             owner: get!(x->3(i64)) as usize,
             community: get!(x->4(i64)) as usize,
-            context: serde_json::from_str(&get!(x->5(String))).unwrap(),
+            context: get!(x->5(json)),
             replying_to: get!(x->6(Option<i64>)).map(|id| id as usize),
             // likes
             likes: get!(x->7(i32)) as isize,
             dislikes: get!(x->8(i32)) as isize,
             // other counts
             comment_count: get!(x->9(i32)) as usize,
-        }
+            deleted_at: get!(x->14(i64)) as usize,
+        })
     }
 
-    auto_method!(get_post_by_id()@get_post_from_row -> "SELECT * FROM posts WHERE id = $1" --name="post" --returns=Post --cache-key-tmpl="atto.post:{}");
+    auto_method!(get_post_by_id()@get_post_from_row -> "SELECT * FROM posts WHERE deleted_at = 0 AND id = $1" --name="post" --returns=Post --cache-key-tmpl="atto.post:{}");
+    auto_method!(get_deleted_post_by_id()@get_post_from_row -> "SELECT * FROM posts WHERE NOT deleted_at = 0 AND id = $1" --name="post" --returns=Post);
 
     /// Get all posts which are comments on the given post by ID.
     ///
     /// # Arguments
     /// * `id` - the ID of the post the requested posts are commenting on
     /// * `batch` - the limit of posts in each page
-    /// * `page` - the page number
+    /// * `cursor` - the cursor of the page (the first page if [`None`])
     pub async fn get_post_comments(
         &self,
         id: usize,
         batch: usize,
-        page: usize,
-    ) -> Result<Vec<Post>> {
+        cursor: Option<Cursor>,
+    ) -> Result<Paginated<Post>> {
         let conn = match self.connect().await {
             Ok(c) => c,
             Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
         };
 
+        let cursor = cursor.unwrap_or_default();
         let res = query_rows!(
             &conn,
-            "SELECT * FROM posts WHERE replying_to = $1 ORDER BY created DESC LIMIT $2 OFFSET $3",
-            &[&(id as i64), &(batch as i64), &((page * batch) as i64)],
+            "SELECT * FROM posts WHERE deleted_at = 0 AND replying_to = $1 AND (created, id) < ($2, $3) ORDER BY created DESC, id DESC LIMIT $4",
+            &[&(id as i64), &cursor.key, &cursor.id, &(batch as i64)],
             |x| { Self::get_post_from_row(x) }
-        );
+        )?;
 
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("post".to_string()));
-        }
-
-        Ok(res.unwrap())
+        Ok(Cursor::paginate(res, batch, |x| {
+            Cursor::new(x.created as i64, x.id)
+        }))
     }
 
     /// Get the post the given post is reposting (if some).
@@ -118,11 +135,8 @@ impl DataManager {
     }
 
     /// Complete a vector of just posts with their owner as well.
-    pub async fn fill_posts(
-        &self,
-        posts: Vec<Post>,
-    ) -> Result<Vec<(Post, User, Option<(User, Post)>, Option<(Question, User)>)>> {
-        let mut out: Vec<(Post, User, Option<(User, Post)>, Option<(Question, User)>)> = Vec::new();
+    pub async fn fill_posts(&self, posts: Vec<Post>) -> Result<Vec<FullPost>> {
+        let mut out: Vec<FullPost> = Vec::new();
 
         let mut users: HashMap<usize, User> = HashMap::new();
         for post in posts {
@@ -155,22 +169,8 @@ impl DataManager {
         &self,
         posts: Vec<Post>,
         user_id: usize,
-    ) -> Result<
-        Vec<(
-            Post,
-            User,
-            Community,
-            Option<(User, Post)>,
-            Option<(Question, User)>,
-        )>,
-    > {
-        let mut out: Vec<(
-            Post,
-            User,
-            Community,
-            Option<(User, Post)>,
-            Option<(Question, User)>,
-        )> = Vec::new();
+    ) -> Result<Vec<FullPostWithCommunity>> {
+        let mut out: Vec<FullPostWithCommunity> = Vec::new();
 
         let mut seen_before: HashMap<(usize, usize), (User, Community)> = HashMap::new();
         let mut seen_user_follow_statuses: HashMap<(usize, usize), bool> = HashMap::new();
@@ -237,30 +237,29 @@ impl DataManager {
     /// # Arguments
     /// * `id` - the ID of the user the requested posts belong to
     /// * `batch` - the limit of posts in each page
-    /// * `page` - the page number
+    /// * `cursor` - the cursor of the page (the first page if [`None`])
     pub async fn get_posts_by_user(
         &self,
         id: usize,
         batch: usize,
-        page: usize,
-    ) -> Result<Vec<Post>> {
+        cursor: Option<Cursor>,
+    ) -> Result<Paginated<Post>> {
         let conn = match self.connect().await {
             Ok(c) => c,
             Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
         };
 
+        let cursor = cursor.unwrap_or_default();
         let res = query_rows!(
             &conn,
-            "SELECT * FROM posts WHERE owner = $1 AND replying_to = 0 AND NOT context LIKE '%\"is_profile_pinned\":true%' AND NOT context LIKE '%\"is_nsfw\":true%' ORDER BY created DESC LIMIT $2 OFFSET $3",
-            &[&(id as i64), &(batch as i64), &((page * batch) as i64)],
+            "SELECT * FROM posts WHERE deleted_at = 0 AND owner = $1 AND replying_to = 0 AND is_profile_pinned = 0 AND is_nsfw = 0 AND (created, id) < ($2, $3) ORDER BY created DESC, id DESC LIMIT $4",
+            &[&(id as i64), &cursor.key, &cursor.id, &(batch as i64)],
             |x| { Self::get_post_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("post".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(Cursor::paginate(res, batch, |x| {
+            Cursor::new(x.created as i64, x.id)
+        }))
     }
 
     /// Get all posts from the given community (from most recent).
@@ -268,30 +267,29 @@ impl DataManager {
     /// # Arguments
     /// * `id` - the ID of the community the requested posts belong to
     /// * `batch` - the limit of posts in each page
-    /// * `page` - the page number
+    /// * `cursor` - the cursor of the page (the first page if [`None`])
     pub async fn get_posts_by_community(
         &self,
         id: usize,
         batch: usize,
-        page: usize,
-    ) -> Result<Vec<Post>> {
+        cursor: Option<Cursor>,
+    ) -> Result<Paginated<Post>> {
         let conn = match self.connect().await {
             Ok(c) => c,
             Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
         };
 
+        let cursor = cursor.unwrap_or_default();
         let res = query_rows!(
             &conn,
-            "SELECT * FROM posts WHERE community = $1 AND replying_to = 0 AND NOT context LIKE '%\"is_pinned\":true%' ORDER BY created DESC LIMIT $2 OFFSET $3",
-            &[&(id as i64), &(batch as i64), &((page * batch) as i64)],
+            "SELECT * FROM posts WHERE deleted_at = 0 AND community = $1 AND replying_to = 0 AND is_pinned = 0 AND (created, id) < ($2, $3) ORDER BY created DESC, id DESC LIMIT $4",
+            &[&(id as i64), &cursor.key, &cursor.id, &(batch as i64)],
             |x| { Self::get_post_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("post".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(Cursor::paginate(res, batch, |x| {
+            Cursor::new(x.created as i64, x.id)
+        }))
     }
 
     /// Get all pinned posts from the given community (from most recent).
@@ -306,16 +304,12 @@ impl DataManager {
 
         let res = query_rows!(
             &conn,
-            "SELECT * FROM posts WHERE community = $1 AND context LIKE '%\"is_pinned\":true%' ORDER BY created DESC",
+            "SELECT * FROM posts WHERE deleted_at = 0 AND community = $1 AND is_pinned = 1 ORDER BY created DESC",
             &[&(id as i64),],
             |x| { Self::get_post_from_row(x) }
-        );
+        )?;
 
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("post".to_string()));
-        }
-
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Get all pinned posts from the given user (from most recent).
@@ -330,16 +324,12 @@ impl DataManager {
 
         let res = query_rows!(
             &conn,
-            "SELECT * FROM posts WHERE owner = $1 AND context LIKE '%\"is_profile_pinned\":true%' ORDER BY created DESC",
+            "SELECT * FROM posts WHERE deleted_at = 0 AND owner = $1 AND is_profile_pinned = 1 ORDER BY created DESC",
             &[&(id as i64),],
             |x| { Self::get_post_from_row(x) }
-        );
+        )?;
 
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("post".to_string()));
-        }
-
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Get all posts answering the given question (from most recent).
@@ -347,34 +337,29 @@ impl DataManager {
     /// # Arguments
     /// * `id` - the ID of the question the requested posts belong to
     /// * `batch` - the limit of posts in each page
-    /// * `page` - the page number
+    /// * `cursor` - the cursor of the page (the first page if [`None`])
     pub async fn get_posts_by_question(
         &self,
         id: usize,
         batch: usize,
-        page: usize,
-    ) -> Result<Vec<Post>> {
+        cursor: Option<Cursor>,
+    ) -> Result<Paginated<Post>> {
         let conn = match self.connect().await {
             Ok(c) => c,
             Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
         };
 
+        let cursor = cursor.unwrap_or_default();
         let res = query_rows!(
             &conn,
-            "SELECT * FROM posts WHERE context LIKE $1 ORDER BY created DESC LIMIT $2 OFFSET $3",
-            params![
-                &format!("%\"answering\":{id}%"),
-                &(batch as i64),
-                &((page * batch) as i64)
-            ],
+            "SELECT * FROM posts WHERE deleted_at = 0 AND answering = $1 AND (created, id) < ($2, $3) ORDER BY created DESC, id DESC LIMIT $4",
+            params![&(id as i64), &cursor.key, &cursor.id, &(batch as i64)],
             |x| { Self::get_post_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("post".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(Cursor::paginate(res, batch, |x| {
+            Cursor::new(x.created as i64, x.id)
+        }))
     }
 
     /// Get a post given its owner and question ID.
@@ -390,60 +375,124 @@ impl DataManager {
 
         let res = query_row!(
             &conn,
-            "SELECT * FROM posts WHERE context LIKE $1 AND owner = $2 LIMIT 1",
-            params![&format!("%\"answering\":{question}%"), &(owner as i64),],
-            |x| { Ok(Self::get_post_from_row(x)) }
-        );
+            "SELECT * FROM posts WHERE deleted_at = 0 AND answering = $1 AND owner = $2 LIMIT 1",
+            &[&(question as i64), &(owner as i64)],
+            |x| { Self::get_post_from_row(x) }
+        )?;
 
-        if res.is_err() {
+        let Some(res) = res else {
             return Err(Error::GeneralNotFound("post".to_string()));
-        }
+        };
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Get posts from all communities, sorted by likes.
     ///
+    /// Pages are cached for up to a minute (or until a post is liked).
+    ///
     /// # Arguments
     /// * `batch` - the limit of posts in each page
-    /// * `page` - the page number
+    /// * `cursor` - the cursor of the page (the first page if [`None`])
     /// * `cutoff` - the maximum number of milliseconds ago the post could have been created
     pub async fn get_popular_posts(
         &self,
         batch: usize,
-        page: usize,
+        cursor: Option<Cursor>,
         cutoff: usize,
-    ) -> Result<Vec<Post>> {
+    ) -> Result<Paginated<Post>> {
+        let cursor = cursor.unwrap_or_default();
+
+        self.get_feed_page(&POPULAR_POSTS, format!("{batch}:{cutoff}:{}:{}", cursor.key, cursor.id), move || async move {
+            let conn = match self.connect().await {
+                Ok(c) => c,
+                Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+            };
+
+            let res = query_rows!(
+                &conn,
+                "SELECT * FROM posts WHERE deleted_at = 0 AND community NOT IN (SELECT id FROM communities WHERE NOT deleted_at = 0) AND replying_to = 0 AND is_nsfw = 0 AND ($1 - created) < $2 AND (likes, id) < (CAST($3 AS BIGINT), $4) ORDER BY likes DESC, id DESC LIMIT $5",
+                &[
+                    &(unix_epoch_timestamp() as i64),
+                    &(cutoff as i64),
+                    &cursor.key,
+                    &cursor.id,
+                    &(batch as i64)
+                ],
+                |x| { Self::get_post_from_row(x) }
+            )?;
+
+            Ok(Cursor::paginate(res, batch, |x| {
+                Cursor::new(x.likes as i64, x.id)
+            }))
+        })
+        .await
+    }
+
+    /// Get posts from all communities, sorted by creation.
+    ///
+    /// # Arguments
+    /// * `batch` - the limit of posts in each page
+    /// * `cursor` - the cursor of the page (the first page if [`None`])
+    pub async fn get_latest_posts(
+        &self,
+        batch: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Paginated<Post>> {
         let conn = match self.connect().await {
             Ok(c) => c,
             Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
         };
 
+        let cursor = cursor.unwrap_or_default();
         let res = query_rows!(
             &conn,
-            "SELECT * FROM posts WHERE replying_to = 0 AND NOT context LIKE '%\"is_nsfw\":true%' AND ($1 - created) < $2 ORDER BY likes DESC, created ASC LIMIT $3 OFFSET $4",
+            "SELECT * FROM posts WHERE deleted_at = 0 AND community NOT IN (SELECT id FROM communities WHERE NOT deleted_at = 0) AND replying_to = 0 AND is_nsfw = 0 AND (created, id) < ($1, $2) ORDER BY created DESC, id DESC LIMIT $3",
+            &[&cursor.key, &cursor.id, &(batch as i64)],
+            |x| { Self::get_post_from_row(x) }
+        )?;
+
+        Ok(Cursor::paginate(res, batch, |x| {
+            Cursor::new(x.created as i64, x.id)
+        }))
+    }
+
+    /// Get the latest posts created before the given time, newest first.
+    ///
+    /// Posts are selected by their ID (which includes the time it was generated at),
+    /// so this uses the primary key instead of scanning `created`. Imported posts get
+    /// IDs generated from their original `created` timestamp, so they're placed the same.
+    ///
+    /// # Arguments
+    /// * `before` - the unix timestamp (in ms) posts must be created before
+    /// * `batch` - the limit of posts
+    pub async fn get_posts_before(&self, before: usize, batch: usize) -> Result<Vec<Post>> {
+        let conn = match self.connect().await {
+            Ok(c) => c,
+            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+        };
+
+        let res = query_rows!(
+            &conn,
+            "SELECT * FROM posts WHERE id < $1 AND deleted_at = 0 AND community NOT IN (SELECT id FROM communities WHERE NOT deleted_at = 0) AND replying_to = 0 AND is_nsfw = 0 ORDER BY id DESC LIMIT $2",
             &[
-                &(unix_epoch_timestamp() as i64),
-                &(cutoff as i64),
-                &(batch as i64),
-                &((page * batch) as i64)
+                &(AlmostSnowflake::min_id_at(before as u128) as i64),
+                &(batch as i64)
             ],
             |x| { Self::get_post_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("post".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
-    /// Get posts from all communities, sorted by creation.
+    /// Get the first posts created after the given time, oldest first.
+    ///
+    /// See [`DataManager::get_posts_before`].
     ///
     /// # Arguments
-    /// * `batch` - the limit of posts in each page
-    /// * `page` - the page number
-    pub async fn get_latest_posts(&self, batch: usize, page: usize) -> Result<Vec<Post>> {
+    /// * `after` - the unix timestamp (in ms) posts must be created after
+    /// * `batch` - the limit of posts
+    pub async fn get_posts_after(&self, after: usize, batch: usize) -> Result<Vec<Post>> {
         let conn = match self.connect().await {
             Ok(c) => c,
             Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
@@ -451,16 +500,15 @@ impl DataManager {
 
         let res = query_rows!(
             &conn,
-            "SELECT * FROM posts WHERE replying_to = 0 AND NOT context LIKE '%\"is_nsfw\":true%' ORDER BY created DESC LIMIT $1 OFFSET $2",
-            &[&(batch as i64), &((page * batch) as i64)],
+            "SELECT * FROM posts WHERE id > $1 AND deleted_at = 0 AND community NOT IN (SELECT id FROM communities WHERE NOT deleted_at = 0) AND replying_to = 0 AND is_nsfw = 0 ORDER BY id ASC LIMIT $2",
+            &[
+                &(AlmostSnowflake::max_id_at(after as u128) as i64),
+                &(batch as i64)
+            ],
             |x| { Self::get_post_from_row(x) }
-        );
+        )?;
 
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("post".to_string()));
-        }
-
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Get posts from all communities the given user is in.
@@ -468,47 +516,29 @@ impl DataManager {
     /// # Arguments
     /// * `id` - the ID of the user
     /// * `batch` - the limit of posts in each page
-    /// * `page` - the page number
+    /// * `cursor` - the cursor of the page (the first page if [`None`])
     pub async fn get_posts_from_user_communities(
         &self,
         id: usize,
         batch: usize,
-        page: usize,
-    ) -> Result<Vec<Post>> {
-        let memberships = self.get_memberships_by_owner(id).await?;
-        let mut memberships = memberships.iter();
-        let first = match memberships.next() {
-            Some(f) => f,
-            None => return Ok(Vec::new()),
-        };
-
-        let mut query_string: String = String::new();
-
-        for membership in memberships {
-            query_string.push_str(&format!(" OR community = {}", membership.community));
-        }
-
-        // ...
+        cursor: Option<Cursor>,
+    ) -> Result<Paginated<Post>> {
         let conn = match self.connect().await {
             Ok(c) => c,
             Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
         };
 
+        let cursor = cursor.unwrap_or_default();
         let res = query_rows!(
             &conn,
-            &format!(
-                "SELECT * FROM posts WHERE (community = {} {query_string}) AND replying_to = 0 ORDER BY created DESC LIMIT $1 OFFSET $2",
-                first.community
-            ),
-            &[&(batch as i64), &((page * batch) as i64)],
+            "SELECT * FROM posts WHERE deleted_at = 0 AND community IN (SELECT community FROM memberships WHERE owner = $1 AND NOT role = 33 AND NOT role = 65 AND community NOT IN (SELECT id FROM communities WHERE NOT deleted_at = 0)) AND replying_to = 0 AND (created, id) < ($2, $3) ORDER BY created DESC, id DESC LIMIT $4",
+            &[&(id as i64), &cursor.key, &cursor.id, &(batch as i64)],
             |x| { Self::get_post_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("post".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(Cursor::paginate(res, batch, |x| {
+            Cursor::new(x.created as i64, x.id)
+        }))
     }
 
     /// Get posts from all users the given user is following.
@@ -516,47 +546,29 @@ impl DataManager {
     /// # Arguments
     /// * `id` - the ID of the user
     /// * `batch` - the limit of posts in each page
-    /// * `page` - the page number
+    /// * `cursor` - the cursor of the page (the first page if [`None`])
     pub async fn get_posts_from_user_following(
         &self,
         id: usize,
         batch: usize,
-        page: usize,
-    ) -> Result<Vec<Post>> {
-        let following = self.get_userfollows_by_initiator_all(id).await?;
-        let mut following = following.iter();
-        let first = match following.next() {
-            Some(f) => f,
-            None => return Ok(Vec::new()),
-        };
-
-        let mut query_string: String = String::new();
-
-        for user in following {
-            query_string.push_str(&format!(" OR owner = {}", user.receiver));
-        }
-
-        // ...
+        cursor: Option<Cursor>,
+    ) -> Result<Paginated<Post>> {
         let conn = match self.connect().await {
             Ok(c) => c,
             Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
         };
 
+        let cursor = cursor.unwrap_or_default();
         let res = query_rows!(
             &conn,
-            &format!(
-                "SELECT * FROM posts WHERE (owner = {} {query_string}) AND replying_to = 0 ORDER BY created DESC LIMIT $1 OFFSET $2",
-                first.receiver
-            ),
-            &[&(batch as i64), &((page * batch) as i64)],
+            "SELECT * FROM posts WHERE deleted_at = 0 AND owner IN (SELECT receiver FROM userfollows WHERE initiator = $1) AND replying_to = 0 AND (created, id) < ($2, $3) ORDER BY created DESC, id DESC LIMIT $4",
+            &[&(id as i64), &cursor.key, &cursor.id, &(batch as i64)],
             |x| { Self::get_post_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("post".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(Cursor::paginate(res, batch, |x| {
+            Cursor::new(x.created as i64, x.id)
+        }))
     }
 
     /// Check if the given `uid` can post in the given `community`.
@@ -609,48 +621,49 @@ impl DataManager {
         // mirror nsfw state
         data.context.is_nsfw = community.context.is_nsfw;
 
+        // notifications are only sent once everything has been checked
+        let mut notifications: Vec<Notification> = Vec::new();
+
         // remove request if we were answering a question
         let owner = self.get_user_by_id(data.owner).await?;
+        let mut question: Option<Question> = None;
+        let mut request: Option<ActionRequest> = None;
+
         if data.context.answering != 0 {
-            let question = self.get_question_by_id(data.context.answering).await?;
+            let q = self.get_question_by_id(data.context.answering).await?;
 
             // check if we've already answered this
             if self
-                .get_post_by_owner_question(owner.id, question.id)
+                .get_post_by_owner_question(owner.id, q.id)
                 .await
                 .is_ok()
             {
-                return Err(Error::MiscError(
-                    "You've already answered this question".to_string(),
-                ));
+                return Err(Error::AlreadyAnswered);
             }
 
-            if !question.is_global {
-                self.delete_request(question.owner, question.id, &owner, false)
-                    .await?;
-            } else {
-                self.incr_question_answer_count(data.context.answering)
-                    .await?;
+            if !q.is_global {
+                request = Some(self.get_request_by_id_linked_asset(q.owner, q.id).await?);
             }
 
             // create notification for question owner
             // (if the current user isn't the owner)
-            if (question.owner != data.owner) && (question.owner != 0) {
-                self.create_notification(Notification::new(
+            if (q.owner != data.owner) && (q.owner != 0) {
+                notifications.push(Notification::new(
                     "Your question has received a new answer!".to_string(),
                     format!(
                         "[@{}](/api/v1/auth/user/find/{}) has answered your [question](/question/{}).",
-                        owner.username, owner.id, question.id
+                        owner.username, owner.id, q.id
                     ),
-                    question.owner,
-                ))
-                .await?;
+                    q.owner,
+                ));
             }
 
             // inherit nsfw status if we didn't get it from the community
-            if question.context.is_nsfw {
-                data.context.is_nsfw = question.context.is_nsfw;
+            if q.context.is_nsfw {
+                data.context.is_nsfw = q.context.is_nsfw;
             }
+
+            question = Some(q);
         }
 
         // check if we're reposting a post
@@ -678,12 +691,12 @@ impl DataManager {
             } else {
                 false
             } {
-                return Err(Error::MiscError("Cannot repost a repost".to_string()));
+                return Err(Error::CannotRepostRepost);
             }
 
             // ...
             if !rt.context.reposts_enabled {
-                return Err(Error::MiscError("Post has reposts disabled".to_string()));
+                return Err(Error::RepostsDisabled);
             }
 
             // check blocked status
@@ -705,7 +718,7 @@ impl DataManager {
 
         if let Some(ref rt) = replying_to {
             if !rt.context.comments_enabled {
-                return Err(Error::MiscError("Post has comments disabled".to_string()));
+                return Err(Error::CommentsDisabled);
             }
 
             // check blocked status
@@ -716,6 +729,18 @@ impl DataManager {
             {
                 return Err(Error::NotAllowed);
             }
+
+            // send notification
+            if data.owner != rt.owner {
+                notifications.push(Notification::new(
+                    "Your post has received a new comment!".to_string(),
+                    format!(
+                        "[@{}](/api/v1/auth/user/find/{}) has commented on your [post](/post/{}).",
+                        owner.username, owner.id, rt.id
+                    ),
+                    rt.owner,
+                ));
+            }
         }
 
         // send mention notifications
@@ -726,15 +751,14 @@ impl DataManager {
                     ua.to_owned()
                 } else {
                     let user = self.get_user_by_username(&username).await?;
-                    self.create_notification(Notification::new(
+                    notifications.push(Notification::new(
                         "You've been mentioned in a post!".to_string(),
                         format!(
                             "[Somebody](/api/v1/auth/user/find/{}) mentioned you in their [post](/post/{}).",
                             data.owner, data.id
                         ),
                         user.id,
-                    ))
-                    .await?;
+                    ));
                     already_notified.insert(username.to_owned(), user.clone());
                     user
                 }
@@ -747,63 +771,119 @@ impl DataManager {
         }
 
         // ...
-        let conn = match self.connect().await {
-            Ok(c) => c,
-            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
-        };
 
-        let replying_to_id = data.replying_to.unwrap_or(0).to_string();
+        self.transaction(async |conn| {
+            let res = execute!(
+                conn,
+                "INSERT INTO posts VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)",
+                params![
+                    &(data.id as i64),
+                    &(data.created as i64),
+                    &data.content,
+                    &(data.owner as i64),
+                    &(data.community as i64),
+                    &serde_json::to_string(&data.context).unwrap(),
+                    &(data.replying_to.unwrap_or(0) as i64),
+                    &0_i32,
+                    &0_i32,
+                    &0_i32,
+                    &{ if data.context.is_nsfw { 1 } else { 0 } },
+                    &{ if data.context.is_pinned { 1 } else { 0 } },
+                    &{ if data.context.is_profile_pinned { 1 } else { 0 } },
+                    &(data.context.answering as i64),
+                    &0_i64
+                ]
+            );
 
-        let res = execute!(
-            &conn,
-            "INSERT INTO posts VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
-            params![
-                &(data.id as i64),
-                &(data.created as i64),
-                &data.content,
-                &(data.owner as i64),
-                &(data.community as i64),
-                &serde_json::to_string(&data.context).unwrap(),
-                &if replying_to_id != "0" {
-                    replying_to_id.parse::<i64>().unwrap()
-                } else {
-                    0_i64
-                },
-                &0_i32,
-                &0_i32,
-                &0_i32
-            ]
-        );
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+            // remove request or incr answer count
+            if let Some(ref r) = request {
+                let res = execute!(conn, "DELETE FROM requests WHERE id = $1", &[&(r.id as i64)]);
 
-        // incr comment count and send notification
-        if let Some(rt) = replying_to {
-            self.incr_post_comments(rt.id).await.unwrap();
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
 
-            // send notification
-            if data.owner != rt.owner {
-                let owner = self.get_user_by_id(data.owner).await?;
-                self.create_notification(Notification::new(
-                    "Your post has received a new comment!".to_string(),
-                    format!(
-                        "[@{}](/api/v1/auth/user/find/{}) has commented on your [post](/post/{}).",
-                        owner.username, owner.id, rt.id
-                    ),
-                    rt.owner,
-                ))
-                .await?;
+                let res = execute!(
+                    conn,
+                    "UPDATE users SET request_count = request_count - 1 WHERE id = $1 AND request_count > 0",
+                    &[&(r.owner as i64)]
+                );
+
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
+            } else if let Some(ref q) = question {
+                let res = execute!(
+                    conn,
+                    "UPDATE questions SET answer_count = answer_count + 1 WHERE id = $1",
+                    &[&(q.id as i64)]
+                );
+
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
+            }
+
+            // incr comment count
+            if let Some(ref rt) = replying_to {
+                let res = execute!(
+                    conn,
+                    "UPDATE posts SET comment_count = comment_count + 1 WHERE id = $1",
+                    &[&(rt.id as i64)]
+                );
 
-                if !rt.context.comments_enabled {
-                    return Err(Error::NotAllowed);
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
                 }
             }
+
+            for notification in &notifications {
+                Self::insert_notification(conn, notification).await?;
+            }
+
+            // increase user post count
+            let res = execute!(
+                conn,
+                "UPDATE users SET post_count = post_count + 1 WHERE id = $1",
+                &[&(data.owner as i64)]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
+
+            Ok(())
+        })
+        .await?;
+
+        // clear cache
+        if let Some(ref r) = request {
+            self.2.remove(format!("atto.request:{}", r.id)).await;
+            self.2
+                .remove(format!("atto.request:{}:{}", r.id, r.linked_asset))
+                .await;
+            self.cache_clear_user_by_id(r.owner).await;
+        } else if let Some(ref q) = question {
+            self.2.remove(format!("atto.question:{}", q.id)).await;
         }
 
-        // increase user post count
-        self.incr_user_post_count(data.owner).await?;
+        if let Some(ref rt) = replying_to {
+            self.2.remove(format!("atto.post:{}", rt.id)).await;
+        }
+
+        for notification in &notifications {
+            self.cache_clear_user_by_id(notification.owner).await;
+        }
+
+        self.cache_clear_user(&owner).await;
+
+        if data.replying_to.is_none() && !data.context.is_nsfw {
+            self.invalidate_feed(&POPULAR_POSTS).await;
+        }
 
         // return
         Ok(data.id)
@@ -832,38 +912,198 @@ impl DataManager {
             }
         }
 
-        let conn = match self.connect().await {
-            Ok(c) => c,
-            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+        // decr question answer count
+        let question = if y.context.answering != 0 {
+            let question = self.get_question_by_id(y.context.answering).await?;
+
+            if question.is_global {
+                Some(question)
+            } else {
+                None
+            }
+        } else {
+            None
         };
 
-        let res = execute!(&conn, "DELETE FROM posts WHERE id = $1", &[&(id as i64)]);
+        self.transaction(async |conn| {
+            let res = execute!(
+                conn,
+                "UPDATE posts SET deleted_at = $1 WHERE id = $2",
+                &[&(unix_epoch_timestamp() as i64), &(id as i64)]
+            );
 
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
+            // decr parent comment count
+            if let Some(replying_to) = y.replying_to {
+                let res = execute!(
+                    conn,
+                    "UPDATE posts SET comment_count = comment_count - 1 WHERE id = $1",
+                    &[&(replying_to as i64)]
+                );
+
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
+            }
+
+            // decr user post count
+            let res = execute!(
+                conn,
+                "UPDATE users SET post_count = post_count - 1 WHERE id = $1 AND post_count > 0",
+                &[&(y.owner as i64)]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
+
+            if let Some(ref question) = question {
+                let res = execute!(
+                    conn,
+                    "UPDATE questions SET answer_count = answer_count - 1 WHERE id = $1 AND answer_count > 0",
+                    &[&(question.id as i64)]
+                );
+
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
+            }
+
+            Ok(())
+        })
+        .await?;
+
+        // clear cache
         self.2.remove(format!("atto.post:{}", id)).await;
 
-        // decr parent comment count
         if let Some(replying_to) = y.replying_to {
-            self.decr_post_comments(replying_to).await.unwrap();
+            self.2.remove(format!("atto.post:{}", replying_to)).await;
         }
 
-        // decr user post count
-        let owner = self.get_user_by_id(y.owner).await?;
+        self.cache_clear_user_by_id(y.owner).await;
 
-        if owner.post_count > 0 {
-            self.decr_user_post_count(y.owner).await?;
+        if let Some(question) = question {
+            self.2
+                .remove(format!("atto.question:{}", question.id))
+                .await;
         }
 
-        // decr question answer count
-        if y.context.answering != 0 {
+        // top-level posts are stored with `replying_to = 0`
+        if matches!(y.replying_to, None | Some(0)) && !y.context.is_nsfw {
+            self.invalidate_feed(&POPULAR_POSTS).await;
+        }
+
+        // return
+        Ok(())
+    }
+
+    /// Restore a deleted post (if its restore window hasn't passed).
+    pub async fn restore_post(&self, id: usize, user: User) -> Result<()> {
+        let y = self.get_deleted_post_by_id(id).await?;
+        self.check_restore_window(y.deleted_at)?;
+
+        let user_membership = self
+            .get_membership_by_owner_community(user.id, y.community)
+            .await?;
+
+        if (user.id != y.owner)
+            && !user_membership
+                .role
+                .check(CommunityPermission::MANAGE_POSTS)
+        {
+            if !user.permissions.check(FinePermission::MANAGE_POSTS) {
+                return Err(Error::NotAllowed);
+            } else {
+                self.create_audit_log_entry(AuditLogEntry::new(
+                    user.id,
+                    format!("invoked `restore_post` with x value `{id}`"),
+                ))
+                .await?
+            }
+        }
+
+        // incr question answer count
+        let question = if y.context.answering != 0 {
             let question = self.get_question_by_id(y.context.answering).await?;
 
             if question.is_global {
-                self.incr_question_answer_count(y.context.answering).await?;
+                Some(question)
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        self.transaction(async |conn| {
+            let res = execute!(
+                conn,
+                "UPDATE posts SET deleted_at = 0 WHERE id = $1",
+                &[&(id as i64)]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
+
+            // incr parent comment count
+            if let Some(replying_to) = y.replying_to {
+                let res = execute!(
+                    conn,
+                    "UPDATE posts SET comment_count = comment_count + 1 WHERE id = $1",
+                    &[&(replying_to as i64)]
+                );
+
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
+            }
+
+            // incr user post count
+            let res = execute!(
+                conn,
+                "UPDATE users SET post_count = post_count + 1 WHERE id = $1",
+                &[&(y.owner as i64)]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
+
+            if let Some(ref question) = question {
+                let res = execute!(
+                    conn,
+                    "UPDATE questions SET answer_count = answer_count + 1 WHERE id = $1",
+                    &[&(question.id as i64)]
+                );
+
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
             }
+
+            Ok(())
+        })
+        .await?;
+
+        // clear cache
+        if let Some(replying_to) = y.replying_to {
+            self.2.remove(format!("atto.post:{}", replying_to)).await;
+        }
+
+        self.cache_clear_user_by_id(y.owner).await;
+
+        if let Some(question) = question {
+            self.2
+                .remove(format!("atto.question:{}", question.id))
+                .await;
+        }
+
+        if matches!(y.replying_to, None | Some(0)) && !y.context.is_nsfw {
+            self.invalidate_feed(&POPULAR_POSTS).await;
         }
 
         // return
@@ -938,8 +1178,14 @@ impl DataManager {
 
         let res = execute!(
             &conn,
-            "UPDATE posts SET context = $1 WHERE id = $2",
-            params![&serde_json::to_string(&x).unwrap(), &(id as i64)]
+            "UPDATE posts SET context = $1, is_nsfw = $2, is_pinned = $3, is_profile_pinned = $4 WHERE id = $5",
+            params![
+                &serde_json::to_string(&x).unwrap(),
+                &{ if x.is_nsfw { 1 } else { 0 } },
+                &{ if x.is_pinned { 1 } else { 0 } },
+                &{ if x.is_profile_pinned { 1 } else { 0 } },
+                &(id as i64)
+            ]
         );
 
         if let Err(e) = res {
@@ -967,25 +1213,46 @@ impl DataManager {
             }
         }
 
-        // ...
-        let conn = match self.connect().await {
-            Ok(c) => c,
-            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
-        };
+        // save the previous content as a revision
+        let revision = PostRevision::new(id, user.id, y.content.clone());
+        y.context.edited = unix_epoch_timestamp() as usize;
 
-        let res = execute!(
-            &conn,
-            "UPDATE posts SET content = $1 WHERE id = $2",
-            params![&x, &(id as i64)]
-        );
+        self.transaction(async |conn| {
+            let res = execute!(
+                conn,
+                "INSERT INTO post_revisions VALUES ($1, $2, $3, $4, $5)",
+                params![
+                    &(revision.id as i64),
+                    &(revision.created as i64),
+                    &(revision.post as i64),
+                    &(revision.editor as i64),
+                    &revision.content
+                ]
+            );
 
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
-        // update context
-        y.context.edited = unix_epoch_timestamp() as usize;
-        self.update_post_context(id, user, y.context).await?;
+            let res = execute!(
+                conn,
+                "UPDATE posts SET content = $1, context = $2 WHERE id = $3",
+                params![
+                    &x,
+                    &serde_json::to_string(&y.context).unwrap(),
+                    &(id as i64)
+                ]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
+
+            Ok(())
+        })
+        .await?;
+
+        self.2.remove(format!("atto.post:{}", id)).await;
 
         // return
         Ok(())
@@ -1,16 +1,19 @@
 use std::collections::HashMap;
 
 use super::*;
+use super::feeds::POPULAR_QUESTIONS;
 use crate::cache::Cache;
 use crate::model::communities_permissions::CommunityPermission;
 use crate::model::{
     Error, Result,
     communities::Question,
+    pagination::{Cursor, Paginated},
     requests::{ActionRequest, ActionType},
     auth::User,
     permissions::FinePermission,
 };
 use crate::{auto_method, execute, get, query_row, query_rows, params};
+use tetratto_shared::snow::AlmostSnowflake;
 
 #[cfg(feature = "sqlite")]
 use rusqlite::Row;
@@ -24,8 +27,8 @@ impl DataManager {
     pub(crate) fn get_question_from_row(
         #[cfg(feature = "sqlite")] x: &Row<'_>,
         #[cfg(feature = "postgres")] x: &Row,
-    ) -> Question {
-        Question {
+    ) -> Result<Question> {
+        Ok(Question {
             id: get!(x->0(i64)) as usize,
             created: get!(x->1(i64)) as usize,
             owner: get!(x->2(i64)) as usize,
@@ -38,12 +41,14 @@ impl DataManager {
             likes: get!(x->8(i32)) as isize,
             dislikes: get!(x->9(i32)) as isize,
             // ...
-            context: serde_json::from_str(&get!(x->10(String))).unwrap(),
+            context: get!(x->10(json)),
             ip: get!(x->11(String)),
-        }
+            deleted_at: get!(x->13(i64)) as usize,
+        })
     }
 
-    auto_method!(get_question_by_id()@get_question_from_row -> "SELECT * FROM questions WHERE id = $1" --name="question" --returns=Question --cache-key-tmpl="atto.question:{}");
+    auto_method!(get_question_by_id()@get_question_from_row -> "SELECT * FROM questions WHERE deleted_at = 0 AND id = $1" --name="question" --returns=Question --cache-key-tmpl="atto.question:{}");
+    auto_method!(get_deleted_question_by_id()@get_question_from_row -> "SELECT * FROM questions WHERE NOT deleted_at = 0 AND id = $1" --name="question" --returns=Question);
 
     /// Fill the given vector of questions with their owner as well.
     pub async fn fill_questions(&self, questions: Vec<Question>) -> Result<Vec<(Question, User)>> {
@@ -77,16 +82,12 @@ impl DataManager {
 
         let res = query_rows!(
             &conn,
-            "SELECT * FROM questions WHERE owner = $1 AND NOT context LIKE '%\"is_nsfw\":true%' ORDER BY created DESC",
+            "SELECT * FROM questions WHERE deleted_at = 0 AND owner = $1 AND is_nsfw = 0 ORDER BY created DESC",
             &[&(owner as i64)],
             |x| { Self::get_question_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("question".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Get all questions by `receiver`.
@@ -98,16 +99,12 @@ impl DataManager {
 
         let res = query_rows!(
             &conn,
-            "SELECT * FROM questions WHERE receiver = $1 ORDER BY created DESC",
+            "SELECT * FROM questions WHERE deleted_at = 0 AND receiver = $1 ORDER BY created DESC",
             &[&(receiver as i64)],
             |x| { Self::get_question_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("question".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Get all global questions by `community`.
@@ -115,29 +112,29 @@ impl DataManager {
         &self,
         community: usize,
         batch: usize,
-        page: usize,
-    ) -> Result<Vec<Question>> {
+        cursor: Option<Cursor>,
+    ) -> Result<Paginated<Question>> {
         let conn = match self.connect().await {
             Ok(c) => c,
             Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
         };
 
+        let cursor = cursor.unwrap_or_default();
         let res = query_rows!(
             &conn,
-            "SELECT * FROM questions WHERE community = $1 AND is_global = 1 ORDER BY created DESC LIMIT $2 OFFSET $3",
+            "SELECT * FROM questions WHERE deleted_at = 0 AND community = $1 AND is_global = 1 AND (created, id) < ($2, $3) ORDER BY created DESC, id DESC LIMIT $4",
             &[
                 &(community as i64),
-                &(batch as i64),
-                &((page * batch) as i64)
+                &cursor.key,
+                &cursor.id,
+                &(batch as i64)
             ],
             |x| { Self::get_question_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("question".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(Cursor::paginate(res, batch, |x| {
+            Cursor::new(x.created as i64, x.id)
+        }))
     }
 
     /// Get all global questions by the given user's following.
@@ -145,42 +142,24 @@ impl DataManager {
         &self,
         id: usize,
         batch: usize,
-        page: usize,
-    ) -> Result<Vec<Question>> {
-        let following = self.get_userfollows_by_initiator_all(id).await?;
-        let mut following = following.iter();
-        let first = match following.next() {
-            Some(f) => f,
-            None => return Ok(Vec::new()),
-        };
-
-        let mut query_string: String = String::new();
-
-        for user in following {
-            query_string.push_str(&format!(" OR owner = {}", user.receiver));
-        }
-
-        // ...
+        cursor: Option<Cursor>,
+    ) -> Result<Paginated<Question>> {
         let conn = match self.connect().await {
             Ok(c) => c,
             Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
         };
 
+        let cursor = cursor.unwrap_or_default();
         let res = query_rows!(
             &conn,
-            &format!(
-                "SELECT * FROM questions WHERE (owner = {} {query_string}) AND is_global = 1 ORDER BY created DESC LIMIT $1 OFFSET $2",
-                first.receiver
-            ),
-            &[&(batch as i64), &((page * batch) as i64)],
+            "SELECT * FROM questions WHERE deleted_at = 0 AND owner IN (SELECT receiver FROM userfollows WHERE initiator = $1) AND is_global = 1 AND (created, id) < ($2, $3) ORDER BY created DESC, id DESC LIMIT $4",
+            &[&(id as i64), &cursor.key, &cursor.id, &(batch as i64)],
             |x| { Self::get_question_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("question".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(Cursor::paginate(res, batch, |x| {
+            Cursor::new(x.created as i64, x.id)
+        }))
     }
 
     /// Get all global questions posted in the given user's communities.
@@ -188,53 +167,65 @@ impl DataManager {
         &self,
         id: usize,
         batch: usize,
-        page: usize,
-    ) -> Result<Vec<Question>> {
-        let memberships = self.get_memberships_by_owner(id).await?;
-        let mut memberships = memberships.iter();
-        let first = match memberships.next() {
-            Some(f) => f,
-            None => return Ok(Vec::new()),
+        cursor: Option<Cursor>,
+    ) -> Result<Paginated<Question>> {
+        let conn = match self.connect().await {
+            Ok(c) => c,
+            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
         };
 
-        let mut query_string: String = String::new();
+        let cursor = cursor.unwrap_or_default();
+        let res = query_rows!(
+            &conn,
+            "SELECT * FROM questions WHERE deleted_at = 0 AND community IN (SELECT community FROM memberships WHERE owner = $1 AND NOT role = 33 AND NOT role = 65 AND community NOT IN (SELECT id FROM communities WHERE NOT deleted_at = 0)) AND is_global = 1 AND is_nsfw = 0 AND (created, id) < ($2, $3) ORDER BY created DESC, id DESC LIMIT $4",
+            &[&(id as i64), &cursor.key, &cursor.id, &(batch as i64)],
+            |x| { Self::get_question_from_row(x) }
+        )?;
 
-        for membership in memberships {
-            query_string.push_str(&format!(" OR community = {}", membership.community));
-        }
+        Ok(Cursor::paginate(res, batch, |x| {
+            Cursor::new(x.created as i64, x.id)
+        }))
+    }
 
-        // ...
+    /// Get global questions from all communities, sorted by creation.
+    ///
+    /// # Arguments
+    /// * `batch` - the limit of questions in each page
+    /// * `cursor` - the cursor of the page (the first page if [`None`])
+    pub async fn get_latest_global_questions(
+        &self,
+        batch: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Paginated<Question>> {
         let conn = match self.connect().await {
             Ok(c) => c,
             Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
         };
 
+        let cursor = cursor.unwrap_or_default();
         let res = query_rows!(
             &conn,
-            &format!(
-                "SELECT * FROM questions WHERE (community = {} {query_string}) AND is_global = 1 AND NOT context LIKE '%\"is_nsfw\":true%' ORDER BY created DESC LIMIT $1 OFFSET $2",
-                first.community
-            ),
-            &[&(batch as i64), &((page * batch) as i64)],
+            "SELECT * FROM questions WHERE deleted_at = 0 AND community NOT IN (SELECT id FROM communities WHERE NOT deleted_at = 0) AND is_global = 1 AND (created, id) < ($1, $2) ORDER BY created DESC, id DESC LIMIT $3",
+            &[&cursor.key, &cursor.id, &(batch as i64)],
             |x| { Self::get_question_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("question".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(Cursor::paginate(res, batch, |x| {
+            Cursor::new(x.created as i64, x.id)
+        }))
     }
 
-    /// Get global questions from all communities, sorted by creation.
+    /// Get the latest global questions created before the given time, newest first.
+    ///
+    /// Questions are selected by their ID, see [`DataManager::get_posts_before`].
     ///
     /// # Arguments
-    /// * `batch` - the limit of questions in each page
-    /// * `page` - the page number
-    pub async fn get_latest_global_questions(
+    /// * `before` - the unix timestamp (in ms) questions must be created before
+    /// * `batch` - the limit of questions
+    pub async fn get_global_questions_before(
         &self,
+        before: usize,
         batch: usize,
-        page: usize,
     ) -> Result<Vec<Question>> {
         let conn = match self.connect().await {
             Ok(c) => c,
@@ -243,29 +234,26 @@ impl DataManager {
 
         let res = query_rows!(
             &conn,
-            "SELECT * FROM questions WHERE is_global = 1 ORDER BY created DESC LIMIT $1 OFFSET $2",
-            &[&(batch as i64), &((page * batch) as i64)],
+            "SELECT * FROM questions WHERE id < $1 AND deleted_at = 0 AND community NOT IN (SELECT id FROM communities WHERE NOT deleted_at = 0) AND is_global = 1 ORDER BY id DESC LIMIT $2",
+            &[
+                &(AlmostSnowflake::min_id_at(before as u128) as i64),
+                &(batch as i64)
+            ],
             |x| { Self::get_question_from_row(x) }
-        );
+        )?;
 
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("question".to_string()));
-        }
-
-        Ok(res.unwrap())
+        Ok(res)
     }
 
-    /// Get global questions from all communities, sorted by likes.
+    /// Get the first global questions created after the given time, oldest first.
     ///
     /// # Arguments
-    /// * `batch` - the limit of questions in each page
-    /// * `page` - the page number
-    /// * `cutoff` - the maximum number of milliseconds ago the question could have been created
-    pub async fn get_popular_global_questions(
+    /// * `after` - the unix timestamp (in ms) questions must be created after
+    /// * `batch` - the limit of questions
+    pub async fn get_global_questions_after(
         &self,
+        after: usize,
         batch: usize,
-        page: usize,
-        cutoff: usize,
     ) -> Result<Vec<Question>> {
         let conn = match self.connect().await {
             Ok(c) => c,
@@ -274,21 +262,57 @@ impl DataManager {
 
         let res = query_rows!(
             &conn,
-            "SELECT * FROM questions WHERE is_global = 1 AND NOT context LIKE '%\"is_nsfw\":true%' AND ($1 - created) < $2 ORDER BY likes DESC, created ASC LIMIT $3 OFFSET $4",
+            "SELECT * FROM questions WHERE id > $1 AND deleted_at = 0 AND community NOT IN (SELECT id FROM communities WHERE NOT deleted_at = 0) AND is_global = 1 ORDER BY id ASC LIMIT $2",
             &[
-                &(unix_epoch_timestamp() as i64),
-                &(cutoff as i64),
-                &(batch as i64),
-                &((page * batch) as i64)
+                &(AlmostSnowflake::max_id_at(after as u128) as i64),
+                &(batch as i64)
             ],
             |x| { Self::get_question_from_row(x) }
-        );
+        )?;
 
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("question".to_string()));
-        }
+        Ok(res)
+    }
 
-        Ok(res.unwrap())
+    /// Get global questions from all communities, sorted by likes.
+    ///
+    /// Pages are cached for up to a minute (or until a question is liked).
+    ///
+    /// # Arguments
+    /// * `batch` - the limit of questions in each page
+    /// * `cursor` - the cursor of the page (the first page if [`None`])
+    /// * `cutoff` - the maximum number of milliseconds ago the question could have been created
+    pub async fn get_popular_global_questions(
+        &self,
+        batch: usize,
+        cursor: Option<Cursor>,
+        cutoff: usize,
+    ) -> Result<Paginated<Question>> {
+        let cursor = cursor.unwrap_or_default();
+
+        self.get_feed_page(&POPULAR_QUESTIONS, format!("{batch}:{cutoff}:{}:{}", cursor.key, cursor.id), move || async move {
+            let conn = match self.connect().await {
+                Ok(c) => c,
+                Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+            };
+
+            let res = query_rows!(
+                &conn,
+                "SELECT * FROM questions WHERE deleted_at = 0 AND community NOT IN (SELECT id FROM communities WHERE NOT deleted_at = 0) AND is_global = 1 AND is_nsfw = 0 AND ($1 - created) < $2 AND (likes, id) < (CAST($3 AS BIGINT), $4) ORDER BY likes DESC, id DESC LIMIT $5",
+                &[
+                    &(unix_epoch_timestamp() as i64),
+                    &(cutoff as i64),
+                    &cursor.key,
+                    &cursor.id,
+                    &(batch as i64)
+                ],
+                |x| { Self::get_question_from_row(x) }
+            )?;
+
+            Ok(Cursor::paginate(res, batch, |x| {
+                Cursor::new(x.likes as i64, x.id)
+            }))
+        })
+        .await
     }
 
     /// Create a new question in the database.
@@ -356,7 +380,7 @@ impl DataManager {
 
         let res = execute!(
             &conn,
-            "INSERT INTO questions VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
+            "INSERT INTO questions VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)",
             params![
                 &(data.id as i64),
                 &(data.created as i64),
@@ -369,7 +393,9 @@ impl DataManager {
                 &0_i32,
                 &0_i32,
                 &serde_json::to_string(&data.context).unwrap(),
-                &data.ip
+                &data.ip,
+                &{ if data.context.is_nsfw { 1 } else { 0 } },
+                &0_i64
             ]
         );
 
@@ -413,20 +439,33 @@ impl DataManager {
             }
         }
 
-        let conn = match self.connect().await {
-            Ok(c) => c,
-            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
-        };
+        // posts answering the question are deleted (and restored) along with it
+        let deleted_at = unix_epoch_timestamp() as i64;
 
-        let res = execute!(
-            &conn,
-            "DELETE FROM questions WHERE id = $1",
-            &[&(id as i64)]
-        );
+        self.transaction(async |conn| {
+            let res = execute!(
+                conn,
+                "UPDATE questions SET deleted_at = $1 WHERE id = $2",
+                &[&deleted_at, &(id as i64)]
+            );
 
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
+
+            let res = execute!(
+                conn,
+                "UPDATE posts SET deleted_at = $1 WHERE answering = $2 AND deleted_at = 0",
+                &[&deleted_at, &(id as i64)]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
+
+            Ok(())
+        })
+        .await?;
 
         self.2.remove(format!("atto.question:{}", id)).await;
 
@@ -442,15 +481,77 @@ impl DataManager {
             self.delete_request(y.owner, y.id, user, false).await?;
         }
 
-        // delete all posts answering question
-        let res = execute!(
-            &conn,
-            "DELETE FROM posts WHERE context LIKE $1",
-            &[&format!("%\"answering\":{id}%")]
-        );
+        if y.is_global {
+            self.invalidate_feed(&POPULAR_QUESTIONS).await;
+        }
 
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
+        // return
+        Ok(())
+    }
+
+    /// Restore a deleted question (if its restore window hasn't passed), along with
+    /// the posts which were deleted with it.
+    pub async fn restore_question(&self, id: usize, user: &User) -> Result<()> {
+        let y = self.get_deleted_question_by_id(id).await?;
+        self.check_restore_window(y.deleted_at)?;
+
+        if user.id != y.owner
+            && user.id != y.receiver
+            && !user.permissions.check(FinePermission::MANAGE_QUESTIONS)
+        {
+            if y.community != 0 {
+                // check for MANAGE_QUESTIONS permission
+                let membership = self
+                    .get_membership_by_owner_community_no_void(user.id, y.community)
+                    .await?;
+
+                if !membership.role.check(CommunityPermission::MANAGE_QUESTIONS) {
+                    return Err(Error::NotAllowed);
+                }
+            } else {
+                return Err(Error::NotAllowed);
+            }
+        }
+
+        self.transaction(async |conn| {
+            let res = execute!(
+                conn,
+                "UPDATE questions SET deleted_at = 0 WHERE id = $1",
+                &[&(id as i64)]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
+
+            let res = execute!(
+                conn,
+                "UPDATE posts SET deleted_at = 0 WHERE answering = $1 AND deleted_at = $2",
+                &[&(id as i64), &(y.deleted_at as i64)]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
+
+            Ok(())
+        })
+        .await?;
+
+        // recreate request (if the question still hasn't been answered)
+        if !y.is_global
+            && self
+                .get_post_by_owner_question(y.receiver, y.id)
+                .await
+                .is_err()
+        {
+            self.create_request(ActionRequest::with_id(
+                y.owner,
+                y.receiver,
+                ActionType::Answer,
+                y.id,
+            ))
+            .await?;
         }
 
         // return
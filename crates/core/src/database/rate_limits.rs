@@ -0,0 +1,200 @@
+use super::*;
+use crate::cache::Cache;
+use crate::config::{RateLimit, RateLimitAction};
+use crate::model::{Error, Result};
+use tetratto_shared::unix_epoch_timestamp;
+
+/// Get the number of tokens missing from a token bucket which had `taken` tokens
+/// taken since it was last full (`elapsed` ms ago), and the number of ms until a
+/// token can be taken from it (`0` if one can be taken now).
+///
+/// Buckets hold `limit.requests` tokens, and are refilled at `limit.requests`
+/// tokens every `limit.seconds` seconds.
+fn bucket_level(limit: RateLimit, taken: u64, elapsed: u64) -> (u64, u64) {
+    let requests = limit.requests as u128;
+    let period = limit.seconds as u128 * 1000;
+    let elapsed = elapsed as u128;
+
+    let level = (taken as u128).saturating_sub(elapsed * requests / period);
+
+    let wait = if level < requests {
+        0
+    } else {
+        // there's room once `taken - requests + 1` tokens have been refilled
+        ((taken as u128 - requests + 1) * period).div_ceil(requests) - elapsed
+    };
+
+    (level as u64, wait as u64)
+}
+
+/// Take a token from the bucket stored at each of the given identifiers, unless
+/// any of them is empty. Returns the number of seconds until every bucket has a
+/// token if one of them doesn't.
+///
+/// Every bucket is stored as the number of tokens taken from it (counted with
+/// [`Cache::incr`]) and the time (in ms) it was last full. Missing buckets are full.
+///
+/// # Arguments
+/// * `cache` - the cache the buckets are stored in
+/// * `ids` - the identifiers of the buckets
+/// * `limit` - the size and refill rate of the buckets
+/// * `now` - the current time (in ms)
+async fn take_tokens(
+    cache: &impl Cache<Item = String>,
+    ids: &[String],
+    limit: RateLimit,
+    now: u64,
+) -> Option<u64> {
+    let mut levels = Vec::new();
+    let mut wait = 0;
+
+    // check every bucket before taking from any of them, so a request which is
+    // rejected doesn't use up the tokens of the others
+    for id in ids {
+        let since = cache.get(format!("{id}:since")).await;
+        let taken = cache.get(format!("{id}:taken")).await;
+
+        let (level, until) = match (
+            since.and_then(|x| x.parse::<u64>().ok()),
+            taken.and_then(|x| x.parse::<u64>().ok()),
+        ) {
+            (Some(since), Some(taken)) => bucket_level(limit, taken, now.saturating_sub(since)),
+            _ => (0, 0),
+        };
+
+        wait = wait.max(until);
+        levels.push((id, level));
+    }
+
+    if wait > 0 {
+        return Some(wait.div_ceil(1000));
+    }
+
+    for (id, level) in levels {
+        // full buckets start counting from now, so they can't store more tokens
+        if level == 0 {
+            cache.set(format!("{id}:since"), now.to_string()).await;
+            cache.set(format!("{id}:taken"), "0".to_string()).await;
+        }
+
+        cache.incr(format!("{id}:taken")).await;
+    }
+
+    None
+}
+
+impl DataManager {
+    /// Take a token from the rate limit of the given action for each of the given
+    /// keys (like `user:{id}` or `ip:{ip}`), failing with [`Error::RateLimited`] if
+    /// any of them has run out (in which case no tokens are taken).
+    ///
+    /// Every key gets a token bucket which holds up to `requests` tokens and is
+    /// refilled at `requests` tokens every `seconds` seconds, so short bursts are
+    /// allowed while the long-term rate stays within the limit. Requests are allowed
+    /// if the cache can't be reached.
+    ///
+    /// # Arguments
+    /// * `action` - the action being performed
+    /// * `keys` - who is performing the action (empty keys are skipped)
+    pub async fn check_rate_limit(&self, action: RateLimitAction, keys: &[&str]) -> Result<()> {
+        let limit = self.0.rate_limits.get(action);
+
+        if limit.requests == 0 || limit.seconds == 0 {
+            return Ok(());
+        }
+
+        let ids: Vec<String> = keys
+            .iter()
+            .filter(|x| !x.is_empty())
+            .map(|key| format!("atto.rate_limit:{}:{key}", action.name()))
+            .collect();
+
+        match take_tokens(&self.2, &ids, limit, unix_epoch_timestamp() as u64).await {
+            Some(wait) => Err(Error::RateLimited(wait)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: RateLimit = RateLimit::new(3, 3);
+
+    #[test]
+    fn bucket_level_exhausts() {
+        assert_eq!(bucket_level(LIMIT, 0, 0), (0, 0));
+        assert_eq!(bucket_level(LIMIT, 2, 0), (2, 0));
+        assert_eq!(bucket_level(LIMIT, 3, 0), (3, 1000));
+        // buckets can be overdrawn by concurrent requests
+        assert_eq!(bucket_level(LIMIT, 5, 0), (5, 3000));
+    }
+
+    #[test]
+    fn bucket_level_refills() {
+        assert_eq!(bucket_level(LIMIT, 3, 999), (3, 1));
+        assert_eq!(bucket_level(LIMIT, 3, 1000), (2, 0));
+        assert_eq!(bucket_level(LIMIT, 3, 2500), (1, 0));
+        assert_eq!(bucket_level(LIMIT, 3, 60_000), (0, 0));
+    }
+
+    #[cfg(all(feature = "memory", not(feature = "redis")))]
+    mod memory {
+        use super::*;
+        use crate::cache::memory::MemoryCache;
+        use crate::config::CacheConfig;
+
+        async fn cache() -> MemoryCache {
+            MemoryCache::new(&CacheConfig::default()).await
+        }
+
+        #[tokio::test]
+        async fn take_tokens_exhausts_and_refills() {
+            let cache = cache().await;
+            let ids = ["a".to_string()];
+
+            for _ in 0..3 {
+                assert_eq!(take_tokens(&cache, &ids, LIMIT, 10_000).await, None);
+            }
+
+            assert_eq!(take_tokens(&cache, &ids, LIMIT, 10_000).await, Some(1));
+            assert_eq!(take_tokens(&cache, &ids, LIMIT, 10_999).await, Some(1));
+            assert_eq!(take_tokens(&cache, &ids, LIMIT, 11_000).await, None);
+            assert_eq!(take_tokens(&cache, &ids, LIMIT, 11_000).await, Some(1));
+
+            // an idle bucket refills completely, but no further
+            for _ in 0..3 {
+                assert_eq!(take_tokens(&cache, &ids, LIMIT, 100_000).await, None);
+            }
+
+            assert_eq!(take_tokens(&cache, &ids, LIMIT, 100_000).await, Some(1));
+        }
+
+        #[tokio::test]
+        async fn take_tokens_checks_every_bucket_first() {
+            let cache = cache().await;
+            let (a, b) = ("a".to_string(), "b".to_string());
+
+            for _ in 0..3 {
+                assert_eq!(
+                    take_tokens(&cache, std::slice::from_ref(&b), LIMIT, 0).await,
+                    None
+                );
+            }
+
+            // `b` is empty, so nothing is taken from `a` either
+            for _ in 0..5 {
+                let ids = [a.clone(), b.clone()];
+                assert_eq!(take_tokens(&cache, &ids, LIMIT, 0).await, Some(1));
+            }
+
+            for _ in 0..3 {
+                assert_eq!(
+                    take_tokens(&cache, std::slice::from_ref(&a), LIMIT, 0).await,
+                    None
+                );
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use super::*;
+use super::feeds::{POPULAR_POSTS, POPULAR_QUESTIONS};
 use crate::cache::Cache;
 use crate::model::{
     Error, Result,
@@ -19,15 +20,15 @@ impl DataManager {
     pub(crate) fn get_reaction_from_row(
         #[cfg(feature = "sqlite")] x: &Row<'_>,
         #[cfg(feature = "postgres")] x: &Row,
-    ) -> Reaction {
-        Reaction {
+    ) -> Result<Reaction> {
+        Ok(Reaction {
             id: get!(x->0(i64)) as usize,
             created: get!(x->1(i64)) as usize,
             owner: get!(x->2(i64)) as usize,
             asset: get!(x->3(i64)) as usize,
-            asset_type: serde_json::from_str(&get!(x->4(String))).unwrap(),
+            asset_type: get!(x->4(json)),
             is_like: get!(x->5(i32)) as i8 == 1,
-        }
+        })
     }
 
     auto_method!(get_reaction_by_id()@get_reaction_from_row -> "SELECT * FROM reactions WHERE id = $1" --name="reaction" --returns=Reaction --cache-key-tmpl="atto.reaction:{}");
@@ -47,14 +48,14 @@ impl DataManager {
             &conn,
             "SELECT * FROM reactions WHERE owner = $1 AND asset = $2",
             &[&(owner as i64), &(asset as i64)],
-            |x| { Ok(Self::get_reaction_from_row(x)) }
-        );
+            |x| { Self::get_reaction_from_row(x) }
+        )?;
 
-        if res.is_err() {
+        let Some(res) = res else {
             return Err(Error::GeneralNotFound("reaction".to_string()));
-        }
+        };
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Create a new reaction in the database.
@@ -96,7 +97,7 @@ impl DataManager {
                 } {
                     return Err(e);
                 } else if data.is_like {
-                    let community = self.get_community_by_id_no_void(data.asset).await.unwrap();
+                    let community = self.get_community_by_id_no_void(data.asset).await?;
 
                     if community.owner != user.id {
                         self
@@ -122,7 +123,7 @@ impl DataManager {
                 } {
                     return Err(e);
                 } else if data.is_like {
-                    let post = self.get_post_by_id(data.asset).await.unwrap();
+                    let post = self.get_post_by_id(data.asset).await?;
 
                     if post.owner != user.id {
                         self.create_notification(Notification::new(
@@ -147,7 +148,7 @@ impl DataManager {
                 } {
                     return Err(e);
                 } else if data.is_like {
-                    let question = self.get_question_by_id(data.asset).await.unwrap();
+                    let question = self.get_question_by_id(data.asset).await?;
 
                     if question.owner != user.id {
                         self
@@ -168,6 +169,15 @@ impl DataManager {
             }
         };
 
+        // popular feeds are sorted by likes
+        if data.is_like {
+            match data.asset_type {
+                AssetType::Post => self.invalidate_feed(&POPULAR_POSTS).await,
+                AssetType::Question => self.invalidate_feed(&POPULAR_QUESTIONS).await,
+                _ => {}
+            }
+        }
+
         // return
         Ok(())
     }
@@ -224,6 +234,15 @@ impl DataManager {
             }
         };
 
+        // popular feeds are sorted by likes
+        if reaction.is_like {
+            match reaction.asset_type {
+                AssetType::Post => self.invalidate_feed(&POPULAR_POSTS).await,
+                AssetType::Question => self.invalidate_feed(&POPULAR_QUESTIONS).await,
+                _ => {}
+            }
+        }
+
         // return
         Ok(())
     }
@@ -15,15 +15,15 @@ impl DataManager {
     pub(crate) fn get_report_from_row(
         #[cfg(feature = "sqlite")] x: &Row<'_>,
         #[cfg(feature = "postgres")] x: &Row,
-    ) -> Report {
-        Report {
+    ) -> Result<Report> {
+        Ok(Report {
             id: get!(x->0(i64)) as usize,
             created: get!(x->1(i64)) as usize,
             owner: get!(x->2(i64)) as usize,
             content: get!(x->3(String)),
             asset: get!(x->4(i64)) as usize,
-            asset_type: serde_json::from_str(&get!(x->5(String))).unwrap(),
-        }
+            asset_type: get!(x->5(json)),
+        })
     }
 
     auto_method!(get_report_by_id(usize as i64)@get_report_from_row -> "SELECT * FROM reports WHERE id = $1" --name="report" --returns=Report --cache-key-tmpl="atto.reports:{}");
@@ -44,13 +44,9 @@ impl DataManager {
             "SELECT * FROM reports ORDER BY created DESC LIMIT $1 OFFSET $2",
             &[&(batch as i64), &((page * batch) as i64)],
             |x| { Self::get_report_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("report".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Create a new report in the database.
@@ -15,14 +15,14 @@ impl DataManager {
     pub(crate) fn get_request_from_row(
         #[cfg(feature = "sqlite")] x: &Row<'_>,
         #[cfg(feature = "postgres")] x: &Row,
-    ) -> ActionRequest {
-        ActionRequest {
+    ) -> Result<ActionRequest> {
+        Ok(ActionRequest {
             id: get!(x->0(i64)) as usize,
             created: get!(x->1(i64)) as usize,
             owner: get!(x->2(i64)) as usize,
-            action_type: serde_json::from_str(&get!(x->3(String))).unwrap(),
+            action_type: get!(x->3(json)),
             linked_asset: get!(x->4(i64)) as usize,
-        }
+        })
     }
 
     pub async fn get_request_by_id_linked_asset(
@@ -34,8 +34,9 @@ impl DataManager {
             .2
             .get(format!("atto.request:{}:{}", id, linked_asset))
             .await
+            && let Ok(x) = serde_json::from_str(&cached)
         {
-            return Ok(serde_json::from_str(&cached).unwrap());
+            return Ok(x);
         }
 
         let conn = match self.connect().await {
@@ -47,20 +48,16 @@ impl DataManager {
             &conn,
             "SELECT * FROM requests WHERE id = $1 AND linked_asset = $2",
             &[&(id as i64), &(linked_asset as i64)],
-            |x| { Ok(Self::get_request_from_row(x)) }
-        );
+            |x| { Self::get_request_from_row(x) }
+        )?;
 
-        if res.is_err() {
+        let Some(x) = res else {
             return Err(Error::GeneralNotFound("request".to_string()));
-        }
+        };
 
-        let x = res.unwrap();
-        self.2
-            .set(
-                format!("atto.request:{}:{}", id, linked_asset),
-                serde_json::to_string(&x).unwrap(),
-            )
-            .await;
+        if let Ok(value) = serde_json::to_string(&x) {
+            self.2.set(format!("atto.request:{}:{}", id, linked_asset), value).await;
+        }
 
         Ok(x)
     }
@@ -77,13 +74,9 @@ impl DataManager {
             "SELECT * FROM requests WHERE owner = $1 ORDER BY created DESC",
             &[&(owner as i64)],
             |x| { Self::get_request_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("request".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Create a new request in the database.
@@ -113,7 +106,7 @@ impl DataManager {
         }
 
         // incr request count
-        self.incr_user_request_count(data.owner).await.unwrap();
+        self.incr_user_request_count(data.owner).await?;
 
         // return
         Ok(())
@@ -158,7 +151,7 @@ impl DataManager {
         // decr request count
         let owner = self.get_user_by_id(y.owner).await?;
         if owner.request_count > 0 {
-            self.decr_user_request_count(y.owner).await.unwrap();
+            self.decr_user_request_count(y.owner).await?;
         }
 
         // return
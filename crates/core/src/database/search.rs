@@ -0,0 +1,255 @@
+use super::*;
+use crate::model::{
+    Error, Result,
+    auth::User,
+    communities::{Post, Question},
+    communities_permissions::CommunityPermission,
+    pagination::{Cursor, Paginated},
+    permissions::FinePermission,
+    search::SearchQuery,
+};
+use crate::{params, query_rows};
+
+/// The filters shared by every search, resolved to the values bound to the query.
+///
+/// A value of `0` means the filter isn't applied.
+struct SearchFilters {
+    user: i64,
+    from: i64,
+    community: i64,
+    before: i64,
+    after: i64,
+    /// If the user can see communities they aren't a member of.
+    read_all: i32,
+    /// If NSFW results are included.
+    nsfw: i32,
+}
+
+/// Build the `WHERE` clause for a search over `table`.
+///
+/// # Parameters
+/// * `$1` - the text query
+/// * `$2` - the ID of the user searching
+/// * `$3` - the ID of the user results must be created by
+/// * `$4` - the ID of the community results must be in
+/// * `$5` - results must be created before this timestamp
+/// * `$6` - results must be created on or after this timestamp
+/// * `$7` - if communities which require membership to read are included
+/// * `$8`, `$9` - the cursor of the page
+/// * `$10` - if NSFW results are included
+///
+/// SQLite numbers parameters in the order they first appear, so they must be
+/// introduced in order.
+fn search_clause(table: &str, has_text: bool) -> String {
+    #[cfg(feature = "sqlite")]
+    let text = if has_text {
+        format!("id IN (SELECT rowid FROM {table}_fts WHERE {table}_fts MATCH $1)")
+    } else {
+        "$1 = ''".to_string()
+    };
+
+    #[cfg(feature = "postgres")]
+    let text = if has_text {
+        "to_tsvector('simple', content) @@ plainto_tsquery('simple', $1)".to_string()
+    } else {
+        "CAST($1 AS TEXT) = ''".to_string()
+    };
+
+    let member = CommunityPermission::MEMBER.bits();
+    let admin = CommunityPermission::ADMINISTRATOR.bits();
+    let banned = CommunityPermission::BANNED.bits();
+
+    format!(
+        "{text}
+        AND {table}.deleted_at = 0
+        AND {table}.community NOT IN (SELECT id FROM communities WHERE NOT deleted_at = 0)
+        AND {table}.owner NOT IN (SELECT receiver FROM userblocks WHERE initiator = CAST($2 AS BIGINT))
+        AND {table}.owner NOT IN (SELECT initiator FROM userblocks WHERE receiver = $2)
+        AND (CAST($3 AS BIGINT) = 0 OR {table}.owner = $3)
+        AND (CAST($4 AS BIGINT) = 0 OR {table}.community = $4)
+        AND (CAST($5 AS BIGINT) = 0 OR {table}.created < $5)
+        AND (CAST($6 AS BIGINT) = 0 OR {table}.created >= $6)
+        AND (CAST($7 AS INT) = 1 OR {table}.community NOT IN (
+            SELECT id FROM communities WHERE read_access = '\"Joined\"' AND id NOT IN (
+                SELECT community FROM memberships WHERE owner = $2 AND ((role & {admin}) = {admin} OR ((role & {banned}) = 0 AND (role & {member}) = {member}))
+            )
+        ))
+        AND ({table}.created, {table}.id) < ($8, $9)
+        AND (CAST($10 AS INT) = 1 OR {table}.is_nsfw = 0)"
+    )
+}
+
+/// Turn user input into an FTS5 query which matches every term, ignoring any
+/// FTS5 query syntax the input contains.
+#[cfg(feature = "sqlite")]
+fn match_text(text: &str) -> String {
+    text.split_whitespace()
+        .map(|x| format!("\"{}\"", x.replace('"', "\"\"")))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[cfg(feature = "postgres")]
+fn match_text(text: &str) -> String {
+    text.to_string()
+}
+
+impl DataManager {
+    /// Resolve the filters of the given [`SearchQuery`].
+    async fn get_search_filters(
+        &self,
+        query: &SearchQuery,
+        user: &Option<User>,
+    ) -> Result<SearchFilters> {
+        let from = match query.from {
+            Some(ref username) => self.get_user_by_username(username).await?.id as i64,
+            None => 0,
+        };
+
+        let community = match query.community {
+            Some(ref title) => self.get_community_by_title(title).await?.id as i64,
+            None => 0,
+        };
+
+        Ok(SearchFilters {
+            user: user.as_ref().map(|x| x.id as i64).unwrap_or(0),
+            from,
+            community,
+            before: query.before.unwrap_or(0) as i64,
+            after: query.after.unwrap_or(0) as i64,
+            read_all: match user {
+                Some(ua) if ua.permissions.check(FinePermission::MANAGE_COMMUNITIES) => 1,
+                _ => 0,
+            },
+            nsfw: match user {
+                Some(ua) if ua.settings.show_nsfw => 1,
+                _ => 0,
+            },
+        })
+    }
+
+    /// Search the content of posts (from most recent).
+    ///
+    /// Posts which the given user cannot see (blocked, in deleted communities, or in
+    /// communities they can't read) and NSFW posts (unless their settings allow
+    /// them) are excluded.
+    ///
+    /// # Arguments
+    /// * `query` - the search query
+    /// * `user` - the user searching
+    /// * `batch` - the limit of posts in each page
+    /// * `cursor` - the cursor of the page (the first page if [`None`])
+    pub async fn search_posts(
+        &self,
+        query: &SearchQuery,
+        user: &Option<User>,
+        batch: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Paginated<Post>> {
+        let filters = self.get_search_filters(query, user).await?;
+
+        let conn = match self.connect().await {
+            Ok(c) => c,
+            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+        };
+
+        let cursor = cursor.unwrap_or_default();
+        let res = query_rows!(
+            &conn,
+            &format!(
+                "SELECT * FROM posts WHERE {} ORDER BY created DESC, id DESC LIMIT $11",
+                search_clause("posts", !query.text.is_empty())
+            ),
+            params![
+                &match_text(&query.text),
+                &filters.user,
+                &filters.from,
+                &filters.community,
+                &filters.before,
+                &filters.after,
+                &filters.read_all,
+                &cursor.key,
+                &cursor.id,
+                &filters.nsfw,
+                &(batch as i64)
+            ],
+            |x| { Self::get_post_from_row(x) }
+        )?;
+
+        Ok(Cursor::paginate(res, batch, |x| {
+            Cursor::new(x.created as i64, x.id)
+        }))
+    }
+
+    /// Search the content of global questions (from most recent).
+    ///
+    /// Questions which the given user cannot see (blocked, in deleted communities, or in
+    /// communities they can't read) and NSFW questions (unless their settings
+    /// allow them) are excluded.
+    ///
+    /// # Arguments
+    /// * `query` - the search query
+    /// * `user` - the user searching
+    /// * `batch` - the limit of questions in each page
+    /// * `cursor` - the cursor of the page (the first page if [`None`])
+    pub async fn search_questions(
+        &self,
+        query: &SearchQuery,
+        user: &Option<User>,
+        batch: usize,
+        cursor: Option<Cursor>,
+    ) -> Result<Paginated<Question>> {
+        let filters = self.get_search_filters(query, user).await?;
+
+        let conn = match self.connect().await {
+            Ok(c) => c,
+            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
+        };
+
+        let cursor = cursor.unwrap_or_default();
+        let res = query_rows!(
+            &conn,
+            &format!(
+                "SELECT * FROM questions WHERE is_global = 1 AND {} ORDER BY created DESC, id DESC LIMIT $11",
+                search_clause("questions", !query.text.is_empty())
+            ),
+            params![
+                &match_text(&query.text),
+                &filters.user,
+                &filters.from,
+                &filters.community,
+                &filters.before,
+                &filters.after,
+                &filters.read_all,
+                &cursor.key,
+                &cursor.id,
+                &filters.nsfw,
+                &(batch as i64)
+            ],
+            |x| { Self::get_question_from_row(x) }
+        )?;
+
+        Ok(Cursor::paginate(res, batch, |x| {
+            Cursor::new(x.created as i64, x.id)
+        }))
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_text_quotes_every_term() {
+        assert_eq!(match_text("hello  world"), "\"hello\" \"world\"");
+        assert_eq!(match_text(""), "");
+    }
+
+    #[test]
+    fn match_text_escapes_query_syntax() {
+        assert_eq!(
+            match_text("a OR b* NEAR(\"c\")"),
+            "\"a\" \"OR\" \"b*\" \"NEAR(\"\"c\"\")\""
+        );
+    }
+}
@@ -16,14 +16,14 @@ impl DataManager {
     pub(crate) fn get_user_warning_from_row(
         #[cfg(feature = "sqlite")] x: &Row<'_>,
         #[cfg(feature = "postgres")] x: &Row,
-    ) -> UserWarning {
-        UserWarning {
+    ) -> Result<UserWarning> {
+        Ok(UserWarning {
             id: get!(x->0(i64)) as usize,
             created: get!(x->1(i64)) as usize,
             receiver: get!(x->2(i64)) as usize,
             moderator: get!(x->3(i64)) as usize,
             content: get!(x->4(String)),
-        }
+        })
     }
 
     auto_method!(get_user_warning_by_ip(&str)@get_user_warning_from_row -> "SELECT * FROM user_warnings WHERE ip = $1" --name="user warning" --returns=UserWarning --cache-key-tmpl="atto.user_warning:{}");
@@ -50,13 +50,9 @@ impl DataManager {
             "SELECT * FROM user_warnings WHERE receiver = $1 ORDER BY created DESC LIMIT $2 OFFSET $3",
             &[&(user as i64), &(batch as i64), &((page * batch) as i64)],
             |x| { Self::get_user_warning_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("user warning".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Create a new user warning in the database.
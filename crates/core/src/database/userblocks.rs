@@ -14,13 +14,13 @@ impl DataManager {
     pub(crate) fn get_userblock_from_row(
         #[cfg(feature = "sqlite")] x: &Row<'_>,
         #[cfg(feature = "postgres")] x: &Row,
-    ) -> UserBlock {
-        UserBlock {
+    ) -> Result<UserBlock> {
+        Ok(UserBlock {
             id: get!(x->0(i64)) as usize,
             created: get!(x->1(i64)) as usize,
             initiator: get!(x->2(i64)) as usize,
             receiver: get!(x->3(i64)) as usize,
-        }
+        })
     }
 
     auto_method!(get_userblock_by_id()@get_userblock_from_row -> "SELECT * FROM userblocks WHERE id = $1" --name="user block" --returns=UserBlock --cache-key-tmpl="atto.userblock:{}");
@@ -40,14 +40,14 @@ impl DataManager {
             &conn,
             "SELECT * FROM userblocks WHERE initiator = $1 AND receiver = $2",
             &[&(initiator as i64), &(receiver as i64)],
-            |x| { Ok(Self::get_userblock_from_row(x)) }
-        );
+            |x| { Self::get_userblock_from_row(x) }
+        )?;
 
-        if res.is_err() {
+        let Some(res) = res else {
             return Err(Error::GeneralNotFound("user block".to_string()));
-        }
+        };
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Get a user block by `receiver` and `initiator` (in that order).
@@ -65,14 +65,14 @@ impl DataManager {
             &conn,
             "SELECT * FROM userblocks WHERE receiver = $1 AND initiator = $2",
             &[&(receiver as i64), &(initiator as i64)],
-            |x| { Ok(Self::get_userblock_from_row(x)) }
-        );
+            |x| { Self::get_userblock_from_row(x) }
+        )?;
 
-        if res.is_err() {
+        let Some(res) = res else {
             return Err(Error::GeneralNotFound("user block".to_string()));
-        }
+        };
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Create a new user block in the database.
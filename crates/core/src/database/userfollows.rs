@@ -1,6 +1,7 @@
 use super::*;
 use crate::cache::Cache;
 use crate::model::auth::FollowResult;
+use crate::model::pagination::{Cursor, Paginated};
 use crate::model::requests::{ActionRequest, ActionType};
 use crate::model::{Error, Result, auth::User, auth::UserFollow, permissions::FinePermission};
 use crate::{auto_method, execute, get, query_row, query_rows, params};
@@ -16,13 +17,13 @@ impl DataManager {
     pub(crate) fn get_userfollow_from_row(
         #[cfg(feature = "sqlite")] x: &Row<'_>,
         #[cfg(feature = "postgres")] x: &Row,
-    ) -> UserFollow {
-        UserFollow {
+    ) -> Result<UserFollow> {
+        Ok(UserFollow {
             id: get!(x->0(i64)) as usize,
             created: get!(x->1(i64)) as usize,
             initiator: get!(x->2(i64)) as usize,
             receiver: get!(x->3(i64)) as usize,
-        }
+        })
     }
 
     auto_method!(get_userfollow_by_id()@get_userfollow_from_row -> "SELECT * FROM userfollows WHERE id = $1" --name="user follow" --returns=UserFollow --cache-key-tmpl="atto.userfollow:{}");
@@ -42,14 +43,14 @@ impl DataManager {
             &conn,
             "SELECT * FROM userfollows WHERE initiator = $1 AND receiver = $2",
             &[&(initiator as i64), &(receiver as i64)],
-            |x| { Ok(Self::get_userfollow_from_row(x)) }
-        );
+            |x| { Self::get_userfollow_from_row(x) }
+        )?;
 
-        if res.is_err() {
+        let Some(res) = res else {
             return Err(Error::GeneralNotFound("user follow".to_string()));
-        }
+        };
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Get a user follow by `receiver` and `initiator` (in that order).
@@ -67,14 +68,14 @@ impl DataManager {
             &conn,
             "SELECT * FROM userfollows WHERE receiver = $1 AND initiator = $2",
             &[&(receiver as i64), &(initiator as i64)],
-            |x| { Ok(Self::get_userfollow_from_row(x)) }
-        );
+            |x| { Self::get_userfollow_from_row(x) }
+        )?;
 
-        if res.is_err() {
+        let Some(res) = res else {
             return Err(Error::GeneralNotFound("user follow".to_string()));
-        }
+        };
 
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Get users the given user is following.
@@ -82,30 +83,29 @@ impl DataManager {
     /// # Arguments
     /// * `id` - the ID of the user
     /// * `batch` - the limit of userfollows in each page
-    /// * `page` - the page number
+    /// * `cursor` - the cursor of the page (the first page if [`None`])
     pub async fn get_userfollows_by_initiator(
         &self,
         id: usize,
         batch: usize,
-        page: usize,
-    ) -> Result<Vec<UserFollow>> {
+        cursor: Option<Cursor>,
+    ) -> Result<Paginated<UserFollow>> {
         let conn = match self.connect().await {
             Ok(c) => c,
             Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
         };
 
+        let cursor = cursor.unwrap_or_default();
         let res = query_rows!(
             &conn,
-            "SELECT * FROM userfollows WHERE initiator = $1 ORDER BY created DESC LIMIT $2 OFFSET $3",
-            &[&(id as i64), &(batch as i64), &((page * batch) as i64)],
+            "SELECT * FROM userfollows WHERE initiator = $1 AND (created, id) < ($2, $3) ORDER BY created DESC, id DESC LIMIT $4",
+            &[&(id as i64), &cursor.key, &cursor.id, &(batch as i64)],
             |x| { Self::get_userfollow_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("user follow".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(Cursor::paginate(res, batch, |x| {
+            Cursor::new(x.created as i64, x.id)
+        }))
     }
 
     /// Get users the given user is following.
@@ -123,13 +123,9 @@ impl DataManager {
             "SELECT * FROM userfollows WHERE initiator = $1",
             &[&(id as i64)],
             |x| { Self::get_userfollow_from_row(x) }
-        );
+        )?;
 
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("user follow".to_string()));
-        }
-
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Get users following the given user.
@@ -137,30 +133,29 @@ impl DataManager {
     /// # Arguments
     /// * `id` - the ID of the user
     /// * `batch` - the limit of userfollows in each page
-    /// * `page` - the page number
+    /// * `cursor` - the cursor of the page (the first page if [`None`])
     pub async fn get_userfollows_by_receiver(
         &self,
         id: usize,
         batch: usize,
-        page: usize,
-    ) -> Result<Vec<UserFollow>> {
+        cursor: Option<Cursor>,
+    ) -> Result<Paginated<UserFollow>> {
         let conn = match self.connect().await {
             Ok(c) => c,
             Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
         };
 
+        let cursor = cursor.unwrap_or_default();
         let res = query_rows!(
             &conn,
-            "SELECT * FROM userfollows WHERE receiver = $1 ORDER BY created DESC LIMIT $2 OFFSET $3",
-            &[&(id as i64), &(batch as i64), &((page * batch) as i64)],
+            "SELECT * FROM userfollows WHERE receiver = $1 AND (created, id) < ($2, $3) ORDER BY created DESC, id DESC LIMIT $4",
+            &[&(id as i64), &cursor.key, &cursor.id, &(batch as i64)],
             |x| { Self::get_userfollow_from_row(x) }
-        );
-
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("user follow".to_string()));
-        }
+        )?;
 
-        Ok(res.unwrap())
+        Ok(Cursor::paginate(res, batch, |x| {
+            Cursor::new(x.created as i64, x.id)
+        }))
     }
 
     /// Get users following the given user.
@@ -178,13 +173,9 @@ impl DataManager {
             "SELECT * FROM userfollows WHERE receiver = $1",
             &[&(id as i64)],
             |x| { Self::get_userfollow_from_row(x) }
-        );
+        )?;
 
-        if res.is_err() {
-            return Err(Error::GeneralNotFound("user follow".to_string()));
-        }
-
-        Ok(res.unwrap())
+        Ok(res)
     }
 
     /// Complete a vector of just userfollows with their receiver as well.
@@ -253,32 +244,49 @@ impl DataManager {
         }
 
         // ...
-        let conn = match self.connect().await {
-            Ok(c) => c,
-            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
-        };
+        self.transaction(async |conn| {
+            let res = execute!(
+                conn,
+                "INSERT INTO userfollows VALUES ($1, $2, $3, $4)",
+                params![
+                    &(data.id as i64),
+                    &(data.created as i64),
+                    &(data.initiator as i64),
+                    &(data.receiver as i64)
+                ]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
-        let res = execute!(
-            &conn,
-            "INSERT INTO userfollows VALUES ($1, $2, $3, $4)",
-            params![
-                &(data.id as i64),
-                &(data.created as i64),
-                &(data.initiator as i64),
-                &(data.receiver as i64)
-            ]
-        );
-
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+            // incr counts
+            let res = execute!(
+                conn,
+                "UPDATE users SET following_count = following_count + 1 WHERE id = $1",
+                &[&(data.initiator as i64)]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
-        // incr counts
-        self.incr_user_following_count(data.initiator)
-            .await
-            .unwrap();
+            let res = execute!(
+                conn,
+                "UPDATE users SET follower_count = follower_count + 1 WHERE id = $1",
+                &[&(data.receiver as i64)]
+            );
 
-        self.incr_user_follower_count(data.receiver).await.unwrap();
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
+
+            Ok(())
+        })
+        .await?;
+
+        self.cache_clear_user_by_id(data.initiator).await;
+        self.cache_clear_user_by_id(data.receiver).await;
 
         // return
         Ok(FollowResult::Followed)
@@ -300,34 +308,57 @@ impl DataManager {
             return Err(Error::NotAllowed);
         }
 
-        let conn = match self.connect().await {
-            Ok(c) => c,
-            Err(e) => return Err(Error::DatabaseConnection(e.to_string())),
-        };
+        // decr counts (if we aren't deleting the user OR the user id isn't the deleted user id)
+        let decr_following = !is_deleting_user | (follow.initiator != user.id);
+        let decr_follower = !is_deleting_user | (follow.receiver != user.id);
+
+        self.transaction(async |conn| {
+            let res = execute!(
+                conn,
+                "DELETE FROM userfollows WHERE id = $1",
+                &[&(id as i64)]
+            );
+
+            if let Err(e) = res {
+                return Err(Error::DatabaseError(e.to_string()));
+            }
 
-        let res = execute!(
-            &conn,
-            "DELETE FROM userfollows WHERE id = $1",
-            &[&(id as i64)]
-        );
+            if decr_following {
+                let res = execute!(
+                    conn,
+                    "UPDATE users SET following_count = following_count - 1 WHERE id = $1",
+                    &[&(follow.initiator as i64)]
+                );
 
-        if let Err(e) = res {
-            return Err(Error::DatabaseError(e.to_string()));
-        }
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
+            }
+
+            if decr_follower {
+                let res = execute!(
+                    conn,
+                    "UPDATE users SET follower_count = follower_count - 1 WHERE id = $1",
+                    &[&(follow.receiver as i64)]
+                );
+
+                if let Err(e) = res {
+                    return Err(Error::DatabaseError(e.to_string()));
+                }
+            }
+
+            Ok(())
+        })
+        .await?;
 
         self.2.remove(format!("atto.userfollow:{}", id)).await;
 
-        // decr counts (if we aren't deleting the user OR the user id isn't the deleted user id)
-        if !is_deleting_user | (follow.initiator != user.id) {
-            self.decr_user_following_count(follow.initiator)
-                .await
-                .unwrap();
+        if decr_following {
+            self.cache_clear_user_by_id(follow.initiator).await;
         }
 
-        if !is_deleting_user | (follow.receiver != user.id) {
-            self.decr_user_follower_count(follow.receiver)
-                .await
-                .unwrap();
+        if decr_follower {
+            self.cache_clear_user_by_id(follow.receiver).await;
         }
 
         // return
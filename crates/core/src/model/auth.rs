@@ -1,21 +1,63 @@
 use super::permissions::FinePermission;
+use crate::config::PasswordHashingConfig;
 use serde::{Deserialize, Serialize};
 use totp_rs::TOTP;
 use tetratto_shared::{
-    hash::{hash_salted, salt},
+    hash::{hash_salted, verify_password},
     snow::AlmostSnowflake,
     unix_epoch_timestamp,
 };
 
-/// `(ip, token, creation timestamp)`
-pub type Token = (String, String, usize);
+/// A session of a user (a token they're logged in with).
+///
+/// Sessions stored as `(ip, token, created)` arrays (before the other fields
+/// existed) can still be read.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Token {
+    /// The IP the session was created from.
+    pub ip: String,
+    /// The hash of the token. Also used as the ID of the session.
+    pub token: String,
+    /// When the session was created.
+    pub created: usize,
+    /// The user agent of the client the session was created from.
+    #[serde(default)]
+    pub user_agent: String,
+    /// When the session was last used. `0` if it hasn't been used since it was
+    /// created (or since this was recorded).
+    #[serde(default)]
+    pub last_used: usize,
+    /// A name the user gave the session.
+    #[serde(default)]
+    pub label: String,
+}
+
+/// A [`Token`], as listed to the user it belongs to.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Session {
+    #[serde(flatten)]
+    pub token: Token,
+    /// If this is the session the list was requested with.
+    pub current: bool,
+}
+
+impl Token {
+    /// The maximum length of [`Token::user_agent`].
+    pub const MAX_USER_AGENT_LEN: usize = 256;
+    /// The maximum length of [`Token::label`].
+    pub const MAX_LABEL_LEN: usize = 32;
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct User {
     pub id: usize,
     pub created: usize,
     pub username: String,
+    /// The Argon2id hash (PHC string) of the user's password, or the SHA-256 hash of
+    /// their salt and password if they haven't logged in since Argon2id was used.
     pub password: String,
+    /// The salt of a SHA-256 password hash. Empty for Argon2id hashes, which
+    /// include their own salt.
     pub salt: String,
     pub settings: UserSettings,
     pub tokens: Vec<Token>,
@@ -37,19 +79,14 @@ pub struct User {
     pub request_count: usize,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub enum ThemePreference {
+    #[default]
     Auto,
     Dark,
     Light,
 }
 
-impl Default for ThemePreference {
-    fn default() -> Self {
-        Self::Auto
-    }
-}
-
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct UserSettings {
     #[serde(default)]
@@ -139,29 +176,22 @@ pub struct UserSettings {
     /// If questions from anonymous users are allowed. Requires `enable_questions`.
     #[serde(default)]
     pub allow_anonymous_questions: bool,
+    /// If NSFW posts and questions are included in the user's search results.
+    #[serde(default)]
+    pub show_nsfw: bool,
 }
 
 impl Default for User {
     fn default() -> Self {
-        Self::new("<unknown>".to_string(), String::new())
-    }
-}
-
-impl User {
-    /// Create a new [`User`].
-    pub fn new(username: String, password: String) -> Self {
-        let salt = salt();
-        let password = hash_salted(password, salt.clone());
-
         Self {
-            id: AlmostSnowflake::new(1234567890)
+            id: AlmostSnowflake::generate()
                 .to_string()
                 .parse::<usize>()
                 .unwrap(),
             created: unix_epoch_timestamp() as usize,
-            username,
-            password,
-            salt,
+            username: "<unknown>".to_string(),
+            password: String::new(),
+            salt: String::new(),
             settings: UserSettings::default(),
             tokens: Vec::new(),
             permissions: FinePermission::DEFAULT,
@@ -176,6 +206,22 @@ impl User {
             request_count: 0,
         }
     }
+}
+
+impl User {
+    /// Create a new [`User`].
+    ///
+    /// # Arguments
+    /// * `username` - the username of the user
+    /// * `password` - the (unhashed) password of the user
+    /// * `hashing` - the parameters the password is hashed with
+    pub async fn new(username: String, password: String, hashing: &PasswordHashingConfig) -> Self {
+        Self {
+            username,
+            password: hashing.hash(&password).await,
+            ..Default::default()
+        }
+    }
 
     /// Deleted user profile.
     pub fn deleted() -> Self {
@@ -206,23 +252,46 @@ impl User {
 
     /// Create a new token
     ///
+    /// # Arguments
+    /// * `ip` - the IP creating the token
+    /// * `user_agent` - the user agent of the client creating the token
+    ///
     /// # Returns
     /// `(unhashed id, token)`
-    pub fn create_token(ip: &str) -> (String, Token) {
+    pub fn create_token(ip: &str, user_agent: &str) -> (String, Token) {
         let unhashed = tetratto_shared::hash::uuid();
         (
             unhashed.clone(),
-            (
-                ip.to_string(),
-                tetratto_shared::hash::hash(unhashed),
-                unix_epoch_timestamp() as usize,
-            ),
+            Token {
+                ip: ip.to_string(),
+                token: tetratto_shared::hash::hash(unhashed),
+                created: unix_epoch_timestamp() as usize,
+                user_agent: user_agent.chars().take(Token::MAX_USER_AGENT_LEN).collect(),
+                last_used: 0,
+                label: String::new(),
+            },
         )
     }
 
     /// Check if the given password is correct for the user.
-    pub fn check_password(&self, against: String) -> bool {
-        self.password == hash_salted(against, self.salt.clone())
+    pub async fn check_password(&self, against: String) -> bool {
+        if self.password.starts_with('$') {
+            // verifying is as slow as hashing, so it's done on a blocking thread
+            let hash = self.password.clone();
+
+            tokio::task::spawn_blocking(move || verify_password(&against, &hash))
+                .await
+                .unwrap_or(false)
+        } else {
+            // SHA-256 hash from before Argon2id was used
+            self.password == hash_salted(against, self.salt.clone())
+        }
+    }
+
+    /// Check if the user's password should be hashed again, because it wasn't
+    /// hashed with the given parameters (or wasn't hashed with Argon2id at all).
+    pub fn password_needs_rehash(&self, hashing: &PasswordHashingConfig) -> bool {
+        hashing.needs_rehash(&self.password)
     }
 
     /// Parse user mentions in a given `input`.
@@ -301,7 +370,7 @@ impl Notification {
     /// Returns a new [`Notification`].
     pub fn new(title: String, content: String, owner: usize) -> Self {
         Self {
-            id: AlmostSnowflake::new(1234567890)
+            id: AlmostSnowflake::generate()
                 .to_string()
                 .parse::<usize>()
                 .unwrap(),
@@ -326,7 +395,7 @@ impl UserFollow {
     /// Create a new [`UserFollow`].
     pub fn new(initiator: usize, receiver: usize) -> Self {
         Self {
-            id: AlmostSnowflake::new(1234567890)
+            id: AlmostSnowflake::generate()
                 .to_string()
                 .parse::<usize>()
                 .unwrap(),
@@ -357,7 +426,7 @@ impl UserBlock {
     /// Create a new [`UserBlock`].
     pub fn new(initiator: usize, receiver: usize) -> Self {
         Self {
-            id: AlmostSnowflake::new(1234567890)
+            id: AlmostSnowflake::generate()
                 .to_string()
                 .parse::<usize>()
                 .unwrap(),
@@ -380,7 +449,7 @@ impl IpBlock {
     /// Create a new [`IpBlock`].
     pub fn new(initiator: usize, receiver: String) -> Self {
         Self {
-            id: AlmostSnowflake::new(1234567890)
+            id: AlmostSnowflake::generate()
                 .to_string()
                 .parse::<usize>()
                 .unwrap(),
@@ -424,7 +493,7 @@ impl UserWarning {
     /// Create a new [`UserWarning`].
     pub fn new(user: usize, moderator: usize, content: String) -> Self {
         Self {
-            id: AlmostSnowflake::new(1234567890)
+            id: AlmostSnowflake::generate()
                 .to_string()
                 .parse::<usize>()
                 .unwrap(),
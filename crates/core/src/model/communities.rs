@@ -24,13 +24,16 @@ pub struct Community {
     pub dislikes: isize,
     // counts
     pub member_count: usize,
+    /// When the community was deleted (0 if it hasn't been).
+    #[serde(default)]
+    pub deleted_at: usize,
 }
 
 impl Community {
     /// Create a new [`Community`].
     pub fn new(title: String, owner: usize) -> Self {
         Self {
-            id: AlmostSnowflake::new(1234567890)
+            id: AlmostSnowflake::generate()
                 .to_string()
                 .parse::<usize>()
                 .unwrap(),
@@ -47,6 +50,7 @@ impl Community {
             likes: 0,
             dislikes: 0,
             member_count: 0,
+            deleted_at: 0,
         }
     }
 
@@ -65,6 +69,7 @@ impl Community {
             likes: 0,
             dislikes: 0,
             member_count: 0,
+            deleted_at: 0,
         }
     }
 }
@@ -82,56 +87,41 @@ pub struct CommunityContext {
 }
 
 /// Who can read a [`Community`].
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
 pub enum CommunityReadAccess {
     /// Everybody can view the community.
+    #[default]
     Everybody,
     /// Only people in the community can view the community.
     Joined,
 }
 
-impl Default for CommunityReadAccess {
-    fn default() -> Self {
-        Self::Everybody
-    }
-}
-
 /// Who can write to a [`Community`].
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
 pub enum CommunityWriteAccess {
     /// Everybody.
     Everybody,
     /// Only people who joined the community can write to it.
     ///
     /// Memberships can be managed by the owner of the community.
+    #[default]
     Joined,
     /// Only the owner of the community.
     Owner,
 }
 
-impl Default for CommunityWriteAccess {
-    fn default() -> Self {
-        Self::Joined
-    }
-}
-
 /// Who can join a [`Community`].
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
 pub enum CommunityJoinAccess {
     /// Joins are closed. Nobody can join the community.
     Nobody,
     /// All authenticated users can join the community.
+    #[default]
     Everybody,
     /// People must send a request to join.
     Request,
 }
 
-impl Default for CommunityJoinAccess {
-    fn default() -> Self {
-        Self::Everybody
-    }
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommunityMembership {
     pub id: usize,
@@ -145,7 +135,7 @@ impl CommunityMembership {
     /// Create a new [`CommunityMembership`].
     pub fn new(owner: usize, community: usize, role: CommunityPermission) -> Self {
         Self {
-            id: AlmostSnowflake::new(1234567890)
+            id: AlmostSnowflake::generate()
                 .to_string()
                 .parse::<usize>()
                 .unwrap(),
@@ -236,6 +226,9 @@ pub struct Post {
     pub likes: isize,
     pub dislikes: isize,
     pub comment_count: usize,
+    /// When the post was deleted (0 if it hasn't been).
+    #[serde(default)]
+    pub deleted_at: usize,
 }
 
 impl Post {
@@ -247,7 +240,7 @@ impl Post {
         owner: usize,
     ) -> Self {
         Self {
-            id: AlmostSnowflake::new(1234567890)
+            id: AlmostSnowflake::generate()
                 .to_string()
                 .parse::<usize>()
                 .unwrap(),
@@ -260,6 +253,7 @@ impl Post {
             likes: 0,
             dislikes: 0,
             comment_count: 0,
+            deleted_at: 0,
         }
     }
 
@@ -284,6 +278,36 @@ impl Post {
     }
 }
 
+/// A previous version of the content of a [`Post`], saved whenever it is edited.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PostRevision {
+    pub id: usize,
+    /// When the post was edited (and this version was replaced).
+    pub created: usize,
+    /// The ID of the post this is a revision of.
+    pub post: usize,
+    /// The ID of the user who made the edit.
+    pub editor: usize,
+    /// The content of the post before the edit.
+    pub content: String,
+}
+
+impl PostRevision {
+    /// Create a new [`PostRevision`].
+    pub fn new(post: usize, editor: usize, content: String) -> Self {
+        Self {
+            id: AlmostSnowflake::generate()
+                .to_string()
+                .parse::<usize>()
+                .unwrap(),
+            created: unix_epoch_timestamp() as usize,
+            post,
+            editor,
+            content,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Question {
     pub id: usize,
@@ -313,6 +337,9 @@ pub struct Question {
     /// The IP of the question creator for IP blocking and identifying anonymous users.
     #[serde(default)]
     pub ip: String,
+    /// When the question was deleted (0 if it hasn't been).
+    #[serde(default)]
+    pub deleted_at: usize,
 }
 
 impl Question {
@@ -325,7 +352,7 @@ impl Question {
         ip: String,
     ) -> Self {
         Self {
-            id: AlmostSnowflake::new(1234567890)
+            id: AlmostSnowflake::generate()
                 .to_string()
                 .parse::<usize>()
                 .unwrap(),
@@ -340,14 +367,13 @@ impl Question {
             dislikes: 0,
             context: QuestionContext::default(),
             ip,
+            deleted_at: 0,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[derive(Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct QuestionContext {
     #[serde(default)]
     pub is_nsfw: bool,
 }
-
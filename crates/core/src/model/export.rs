@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use tetratto_shared::unix_epoch_timestamp;
+
+/// The current version of the account export format. Bumped whenever a file in the
+/// archive changes in a way older readers can't handle.
+///
+/// * `1` - initial format
+/// * `2` - added `users.json` and `communities.json` (so references can be resolved
+///   on other instances), `questions_received.json` includes answered questions
+pub const EXPORT_FORMAT_VERSION: usize = 2;
+
+/// The documentation included as `README.md` in every account export archive.
+pub const EXPORT_README: &str = r#"# Account export
+
+This archive contains all of the data stored for your account. Every file is JSON,
+in the same shape the API returns. IDs and timestamps (milliseconds since the unix
+epoch) are numbers.
+
+| File                      | Contents                                                      |
+| ------------------------- | ------------------------------------------------------------- |
+| `manifest.json`           | Format version, export time, instance and account ID          |
+| `profile.json`            | Your profile (password, tokens and TOTP secrets are removed)  |
+| `settings.json`           | Your profile settings                                         |
+| `posts.json`              | Every post you've created (including replies and answers)     |
+| `questions_asked.json`    | Every question you've asked                                   |
+| `questions_received.json` | Every question you've been asked (or have answered)           |
+| `reactions.json`          | Every like and dislike you've given                           |
+| `following.json`          | Every user you follow                                         |
+| `followers.json`          | Every user following you                                      |
+| `blocks.json`             | Every user you've blocked                                     |
+| `memberships.json`        | Your community memberships (and their roles)                  |
+| `notifications.json`      | Your notifications                                            |
+| `users.json`              | The ID and username of every other user referenced above      |
+| `communities.json`        | Every community referenced above                              |
+| `media/avatar.avif`       | Your avatar (if you've uploaded one)                          |
+| `media/banner.avif`       | Your banner (if you've uploaded one)                          |
+
+Posts and questions which were deleted (but can still be restored) are included, with
+a non-zero `deleted_at` timestamp. The IP addresses of other users who asked you
+questions are removed.
+
+## Importing
+
+An archive can be imported into another instance by an administrator. Every item
+gets a new ID there, but keeps its original `created` timestamp:
+
+* Other users are matched by their username, and communities by their title.
+  Communities you own which don't exist there yet are recreated.
+* Mentions and links to imported posts and questions are updated to their new IDs.
+  Mentions of users who can't be found are turned back into plain text.
+* Posts in communities which can't be found (and replies, reposts or answers to
+  content which wasn't imported) are skipped, as are deleted posts and questions.
+* Questions asked by users who can't be found become anonymous.
+* Moderation roles in existing communities, notifications and reactions to other
+  content aren't imported.
+"#;
+
+/// The `manifest.json` file of an account export archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportManifest {
+    /// The [`EXPORT_FORMAT_VERSION`] the archive was created with.
+    pub version: usize,
+    pub created: usize,
+    /// The public host of the instance the account was exported from.
+    pub host: String,
+    pub user: usize,
+    pub username: String,
+}
+
+impl ExportManifest {
+    /// Create a new [`ExportManifest`].
+    pub fn new(host: String, user: usize, username: String) -> Self {
+        Self {
+            version: EXPORT_FORMAT_VERSION,
+            created: unix_epoch_timestamp() as usize,
+            host,
+            user,
+            username,
+        }
+    }
+}
+
+/// An entry in the `users.json` file of an account export archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportUser {
+    pub id: usize,
+    pub username: String,
+}
+
+/// The number of items recreated by an account import.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ImportSummary {
+    /// The ID of the new user.
+    pub user: usize,
+    pub communities: usize,
+    pub posts: usize,
+    pub questions: usize,
+    pub reactions: usize,
+    pub follows: usize,
+    pub blocks: usize,
+    pub memberships: usize,
+    /// The number of items which couldn't be resolved on this instance.
+    pub skipped: usize,
+}
@@ -1,10 +1,13 @@
 pub mod auth;
 pub mod communities;
 pub mod communities_permissions;
+pub mod export;
 pub mod moderation;
+pub mod pagination;
 pub mod permissions;
 pub mod reactions;
 pub mod requests;
+pub mod search;
 
 use std::fmt::Display;
 
@@ -16,10 +19,28 @@ where
     T: Serialize,
 {
     pub ok: bool,
+    /// The [`Error::code`] of the error (if the request failed).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
     pub message: String,
     pub payload: T,
 }
 
+impl<T> ApiReturn<T>
+where
+    T: Serialize,
+{
+    /// Create a new [`ApiReturn`] for a successful request.
+    pub fn ok(message: String, payload: T) -> Self {
+        Self {
+            ok: true,
+            code: None,
+            message,
+            payload,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
     MiscError(String),
@@ -36,9 +57,73 @@ pub enum Error {
     UsernameInUse,
     TitleInUse,
     QuestionsDisabled,
+    AlreadyAnswered,
+    CannotRepostRepost,
+    RepostsDisabled,
+    CommentsDisabled,
+    AlreadyJoined,
+    OwnerRoleLocked,
+    InvalidId(String),
+    /// Too many requests were made. Contains the number of seconds to wait before
+    /// trying again.
+    RateLimited(u64),
     Unknown,
 }
 
+impl Error {
+    /// A stable, machine-readable code for the error (unlike its message).
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::MiscError(_) => "misc",
+            Self::DatabaseConnection(_) => "database_connection",
+            Self::UserNotFound => "user_not_found",
+            Self::GeneralNotFound(_) => "not_found",
+            Self::RegistrationDisabled => "registration_disabled",
+            Self::DatabaseError(_) => "database_error",
+            Self::IncorrectPassword => "incorrect_password",
+            Self::NotAllowed => "not_allowed",
+            Self::AlreadyAuthenticated => "already_authenticated",
+            Self::DataTooLong(_) => "data_too_long",
+            Self::DataTooShort(_) => "data_too_short",
+            Self::UsernameInUse => "username_in_use",
+            Self::TitleInUse => "title_in_use",
+            Self::QuestionsDisabled => "questions_disabled",
+            Self::AlreadyAnswered => "already_answered",
+            Self::CannotRepostRepost => "cannot_repost_repost",
+            Self::RepostsDisabled => "reposts_disabled",
+            Self::CommentsDisabled => "comments_disabled",
+            Self::AlreadyJoined => "already_joined",
+            Self::OwnerRoleLocked => "owner_role_locked",
+            Self::InvalidId(_) => "invalid_id",
+            Self::RateLimited(_) => "rate_limited",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    /// The HTTP status code matching the error.
+    pub fn status(&self) -> u16 {
+        Self::status_of(self.code())
+    }
+
+    /// Get the HTTP status code matching the given [`Error::code`].
+    pub fn status_of(code: &str) -> u16 {
+        match code {
+            "user_not_found" | "not_found" => 404,
+            "incorrect_password" => 401,
+            "registration_disabled"
+            | "not_allowed"
+            | "questions_disabled"
+            | "reposts_disabled"
+            | "comments_disabled"
+            | "owner_role_locked" => 403,
+            "username_in_use" | "title_in_use" | "already_answered" | "already_joined" => 409,
+            "rate_limited" => 429,
+            "database_connection" | "database_error" | "unknown" => 500,
+            _ => 400,
+        }
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(&match self {
@@ -56,6 +141,16 @@ impl Display for Error {
             Self::UsernameInUse => "Username in use".to_string(),
             Self::TitleInUse => "Title in use".to_string(),
             Self::QuestionsDisabled => "You are not allowed to ask questions there".to_string(),
+            Self::AlreadyAnswered => "You've already answered this question".to_string(),
+            Self::CannotRepostRepost => "Cannot repost a repost".to_string(),
+            Self::RepostsDisabled => "Post has reposts disabled".to_string(),
+            Self::CommentsDisabled => "Post has comments disabled".to_string(),
+            Self::AlreadyJoined => "Already joined community".to_string(),
+            Self::OwnerRoleLocked => "Cannot update community owner's role".to_string(),
+            Self::InvalidId(name) => format!("Given {name} ID is invalid"),
+            Self::RateLimited(seconds) => {
+                format!("Too many requests, try again in {seconds} seconds")
+            }
             _ => format!("An unknown error as occurred: ({:?})", self),
         })
     }
@@ -68,6 +163,7 @@ where
     fn from(val: Error) -> Self {
         ApiReturn {
             ok: false,
+            code: Some(val.code().to_string()),
             message: val.to_string(),
             payload: T::default(),
         }
@@ -15,7 +15,7 @@ impl AuditLogEntry {
     /// Create a new [`AuditLogEntry`].
     pub fn new(moderator: usize, content: String) -> Self {
         Self {
-            id: AlmostSnowflake::new(1234567890)
+            id: AlmostSnowflake::generate()
                 .to_string()
                 .parse::<usize>()
                 .unwrap(),
@@ -40,7 +40,7 @@ impl Report {
     /// Create a new [`Report`].
     pub fn new(owner: usize, content: String, asset: usize, asset_type: AssetType) -> Self {
         Self {
-            id: AlmostSnowflake::new(1234567890)
+            id: AlmostSnowflake::generate()
                 .to_string()
                 .parse::<usize>()
                 .unwrap(),
@@ -52,3 +52,15 @@ impl Report {
         }
     }
 }
+
+/// A denormalized counter which didn't match the value computed from its source
+/// table, and has been corrected.
+#[derive(Serialize, Deserialize)]
+pub struct CounterChange {
+    pub asset: usize,
+    pub asset_type: AssetType,
+    /// The name of the counter column.
+    pub counter: String,
+    pub old: i32,
+    pub new: i32,
+}
@@ -0,0 +1,146 @@
+use std::{fmt::Display, str::FromStr};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as _};
+
+/// A page of items, along with the cursor of the next page (if there could be one).
+pub type Paginated<T> = (Vec<T>, Option<Cursor>);
+
+/// A position in a listing, used for keyset pagination.
+///
+/// Listings are sorted by `(key, id)` (descending), where `key` is usually the
+/// creation timestamp of the item (or its likes for popular listings). Each page
+/// starts right after the last item of the previous page, so items created between
+/// page loads don't shift later pages.
+///
+/// Cursors are opaque strings to clients.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cursor {
+    pub(crate) key: i64,
+    pub(crate) id: i64,
+}
+
+impl Default for Cursor {
+    /// The cursor of the first page.
+    fn default() -> Self {
+        Self {
+            key: i64::MAX,
+            id: i64::MAX,
+        }
+    }
+}
+
+impl Cursor {
+    /// Create a new [`Cursor`] pointing right after the given item.
+    pub fn new(key: i64, id: usize) -> Self {
+        Self { key, id: id as i64 }
+    }
+
+    /// Get the cursor of the page after `items`. A full page could always be
+    /// followed by another, so only pages with less than `batch` items end the listing.
+    ///
+    /// # Arguments
+    /// * `items` - the items of the current page
+    /// * `batch` - the limit of items in each page
+    /// * `f` - a function returning the cursor pointing right after an item
+    pub fn next<T>(items: &[T], batch: usize, f: impl Fn(&T) -> Self) -> Option<Self> {
+        if items.len() < batch {
+            return None;
+        }
+
+        items.last().map(f)
+    }
+
+    /// Complete the given page of items with the cursor of the next page.
+    pub fn paginate<T>(items: Vec<T>, batch: usize, f: impl Fn(&T) -> Self) -> Paginated<T> {
+        let next = Self::next(&items, batch, f);
+        (items, next)
+    }
+}
+
+impl Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:x}_{:x}", self.key as u64, self.id as u64)
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, id) = s.split_once('_').ok_or("invalid cursor")?;
+        let parse = |x: &str| u64::from_str_radix(x, 16).map_err(|_| "invalid cursor");
+
+        Ok(Self {
+            key: parse(key)? as i64,
+            id: parse(id)? as i64,
+        })
+    }
+}
+
+impl Serialize for Cursor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cursor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_round_trips() {
+        for cursor in [
+            Cursor::default(),
+            Cursor::new(0, 0),
+            Cursor::new(1_704_067_200_000, 123_456_789),
+            Cursor::new(-1, 1),
+            Cursor {
+                key: i64::MIN,
+                id: i64::MAX,
+            },
+        ] {
+            assert_eq!(cursor.to_string().parse::<Cursor>(), Ok(cursor));
+        }
+    }
+
+    #[test]
+    fn cursor_displays_as_hex() {
+        assert_eq!(Cursor::new(255, 16).to_string(), "ff_10");
+        assert_eq!(Cursor::new(-1, 0).to_string(), "ffffffffffffffff_0");
+    }
+
+    #[test]
+    fn cursor_rejects_malformed_input() {
+        for input in [
+            "",
+            "_",
+            "ff",
+            "ff_",
+            "_ff",
+            "ff_10_1",
+            "fg_10",
+            "-1_10",
+            "ff_ 10",
+            "1ffffffffffffffff_0",
+        ] {
+            assert!(input.parse::<Cursor>().is_err(), "{input:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn cursor_serializes_as_string() {
+        let cursor = Cursor::new(255, 16);
+        let json = serde_json::to_string(&cursor).unwrap();
+
+        assert_eq!(json, "\"ff_10\"");
+        assert_eq!(serde_json::from_str::<Cursor>(&json).unwrap(), cursor);
+        assert!(serde_json::from_str::<Cursor>("\"nope\"").is_err());
+    }
+}
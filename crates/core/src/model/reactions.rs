@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use tetratto_shared::{snow::AlmostSnowflake, unix_epoch_timestamp};
 
 /// All of the items which support reactions.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AssetType {
     #[serde(alias = "community")]
     Community,
@@ -28,7 +28,7 @@ impl Reaction {
     /// Create a new [`Reaction`].
     pub fn new(owner: usize, asset: usize, asset_type: AssetType, is_like: bool) -> Self {
         Self {
-            id: AlmostSnowflake::new(1234567890)
+            id: AlmostSnowflake::generate()
                 .to_string()
                 .parse::<usize>()
                 .unwrap(),
@@ -32,7 +32,7 @@ impl ActionRequest {
     /// Create a new [`ActionRequest`].
     pub fn new(owner: usize, action_type: ActionType, linked_asset: usize) -> Self {
         Self {
-            id: AlmostSnowflake::new(1234567890)
+            id: AlmostSnowflake::generate()
                 .to_string()
                 .parse::<usize>()
                 .unwrap(),
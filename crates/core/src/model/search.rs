@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use tetratto_shared::date_timestamp;
+use super::communities::{Post, Question};
+use super::pagination::Cursor;
+
+/// The type of asset a search is looking for.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchType {
+    #[default]
+    Posts,
+    Questions,
+}
+
+/// A parsed search query.
+///
+/// Queries are made up of plain text terms and (optional) filters:
+/// * `from:username` - only results created by the given user
+/// * `in:community` - only results in the given community
+/// * `before:YYYY-MM-DD` - only results created before the given date
+/// * `after:YYYY-MM-DD` - only results created on or after the given date
+///
+/// Anything which doesn't look like a valid filter is searched for as text.
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    /// The text terms of the query, separated by a single space.
+    pub text: String,
+    /// The username of the user results must be created by.
+    pub from: Option<String>,
+    /// The title of the community results must be in.
+    pub community: Option<String>,
+    /// Results must be created before this timestamp.
+    pub before: Option<usize>,
+    /// Results must be created on or after this timestamp.
+    pub after: Option<usize>,
+}
+
+impl SearchQuery {
+    /// Parse a [`SearchQuery`] from the given input.
+    pub fn parse(input: &str) -> Self {
+        let mut out = Self::default();
+        let mut terms: Vec<&str> = Vec::new();
+
+        for term in input.split_whitespace() {
+            let Some((filter, value)) = term.split_once(':') else {
+                terms.push(term);
+                continue;
+            };
+
+            if value.is_empty() {
+                terms.push(term);
+                continue;
+            }
+
+            match filter {
+                "from" => out.from = Some(value.trim_start_matches('@').to_string()),
+                "in" => out.community = Some(value.to_string()),
+                "before" => match Self::parse_date(value) {
+                    Some(x) => out.before = Some(x),
+                    None => terms.push(term),
+                },
+                "after" => match Self::parse_date(value) {
+                    Some(x) => out.after = Some(x),
+                    None => terms.push(term),
+                },
+                _ => terms.push(term),
+            }
+        }
+
+        out.text = terms.join(" ");
+        out
+    }
+
+    /// Parse a date filter value. Accepts `YYYY-MM-DD` or a raw timestamp.
+    fn parse_date(value: &str) -> Option<usize> {
+        if let Ok(x) = value.parse::<usize>() {
+            return Some(x);
+        }
+
+        date_timestamp(value).and_then(|x| usize::try_from(x).ok())
+    }
+
+    /// Check if the query has nothing to search by.
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+            && self.from.is_none()
+            && self.community.is_none()
+            && self.before.is_none()
+            && self.after.is_none()
+    }
+}
+
+/// The results of a search. Only the list matching the [`SearchType`] of the
+/// search is filled.
+#[derive(Clone, Serialize, Deserialize, Default)]
+pub struct SearchResults {
+    pub posts: Vec<Post>,
+    pub questions: Vec<Question>,
+    /// The cursor of the next page of results (if there could be one).
+    pub next: Option<Cursor>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_empty_input() {
+        for input in ["", "   ", "\t\n"] {
+            let query = SearchQuery::parse(input);
+            assert_eq!(query, SearchQuery::default());
+            assert!(query.is_empty());
+        }
+    }
+
+    #[test]
+    fn parse_text() {
+        let query = SearchQuery::parse("  hello   world ");
+        assert_eq!(query.text, "hello world");
+        assert!(!query.is_empty());
+    }
+
+    #[test]
+    fn parse_operators() {
+        let query =
+            SearchQuery::parse("from:@someone in:community before:1000 after:2024-01-01 hi");
+
+        assert_eq!(
+            query,
+            SearchQuery {
+                text: "hi".to_string(),
+                from: Some("someone".to_string()),
+                community: Some("community".to_string()),
+                before: Some(1000),
+                after: Some(1_704_067_200_000),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_later_operators_win() {
+        let query = SearchQuery::parse("from:a from:b");
+        assert_eq!(query.from, Some("b".to_string()));
+        assert!(query.text.is_empty());
+    }
+
+    #[test]
+    fn parse_invalid_operators_as_text() {
+        let query = SearchQuery::parse("from: before:yesterday after:2024-13-01 to:someone a:b:c");
+
+        assert_eq!(
+            query,
+            SearchQuery {
+                text: "from: before:yesterday after:2024-13-01 to:someone a:b:c".to_string(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_quotes_as_text() {
+        // quotes aren't query syntax, they're searched for like everything else
+        let query = SearchQuery::parse("\"hello world\" \"from:someone\" from:\"someone\"");
+
+        assert_eq!(query.text, "\"hello world\" \"from:someone\"");
+        assert_eq!(query.from, Some("\"someone\"".to_string()));
+    }
+}
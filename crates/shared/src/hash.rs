@@ -1,3 +1,7 @@
+use argon2::{
+    Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
+    password_hash::{SaltString, rand_core::OsRng},
+};
 use hex_fmt::HexFmt;
 use rand::{Rng, distr::Alphanumeric, rng};
 use sha2::{Digest, Sha256};
@@ -25,6 +29,62 @@ pub fn hash_salted(input: String, salt: String) -> String {
     HexFmt(res).to_string()
 }
 
+/// Get an Argon2id hasher with the given cost parameters (or the default ones if
+/// they're invalid).
+fn argon2(memory_cost: u32, time_cost: u32, parallelism: u32) -> Argon2<'static> {
+    let params = Params::new(memory_cost, time_cost, parallelism, None).unwrap_or_default();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+}
+
+/// Hash a password with Argon2id. The result is a PHC string, which includes its
+/// own salt and cost parameters.
+///
+/// # Arguments
+/// * `memory_cost` - the amount of memory used (in KiB)
+/// * `time_cost` - the number of passes over the memory
+/// * `parallelism` - the number of lanes
+pub fn hash_password(input: &str, memory_cost: u32, time_cost: u32, parallelism: u32) -> String {
+    argon2(memory_cost, time_cost, parallelism)
+        .hash_password(input.as_bytes(), &SaltString::generate(&mut OsRng))
+        // only fails with invalid parameters, which are replaced in `argon2`
+        .expect("failed to hash password")
+        .to_string()
+}
+
+/// Check a password against a hash from [`hash_password`].
+pub fn verify_password(input: &str, hash: &str) -> bool {
+    match PasswordHash::new(hash) {
+        Ok(hash) => Argon2::default()
+            .verify_password(input.as_bytes(), &hash)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Check if a hash wasn't made by [`hash_password`] with the given cost parameters.
+pub fn password_needs_rehash(
+    hash: &str,
+    memory_cost: u32,
+    time_cost: u32,
+    parallelism: u32,
+) -> bool {
+    let Ok(hash) = PasswordHash::new(hash) else {
+        return true;
+    };
+
+    let Ok(current) = Params::try_from(&hash) else {
+        return true;
+    };
+
+    let params = argon2(memory_cost, time_cost, parallelism);
+    let params = params.params();
+
+    hash.algorithm != Algorithm::Argon2id.ident()
+        || current.m_cost() != params.m_cost()
+        || current.t_cost() != params.t_cost()
+        || current.p_cost() != params.p_cost()
+}
+
 pub fn salt() -> String {
     rng()
         .sample_iter(&Alphanumeric)
@@ -3,4 +3,4 @@ pub mod markdown;
 pub mod snow;
 pub mod time;
 
-pub use time::{epoch_timestamp, unix_epoch_timestamp};
+pub use time::{date_timestamp, epoch_timestamp, unix_epoch_timestamp};
@@ -1,21 +1,75 @@
 //! Almost Snowflake
 //!
-//! Random IDs which include timestamp information (like Twitter Snowflakes)
+//! Unique IDs which include timestamp information (like Twitter Snowflakes)
 //!
-//! IDs are generated with 41 bits of an epoch timestamp, 10 bits of a machine/server ID, and 12 bits of randomly generated numbers.
+//! IDs are generated with 41 bits of an epoch timestamp, 10 bits of a machine/server ID, and 12 bits of a per-process sequence number.
 //!
+//! ```text
+//! tttttttttttttttttttttttttttttttttttttttttiiiiiiiiiissssssssssss
+//! Timestamp                                ID        Sequence
 //! ```
-//! tttttttttttttttttttttttttttttttttttttttttiiiiiiiiiirrrrrrrrrrrr...
-//! Timestamp                                ID        Seed
-//! ```
+//!
+//! Every process generating IDs for the same database needs its own server ID
+//! (set with [`set_node_id`]), the sequence number keeps IDs unique within a process.
 use crate::epoch_timestamp;
 use serde::{Deserialize, Serialize};
 
 use num_bigint::BigInt;
-use rand::Rng;
+use std::sync::{
+    Mutex,
+    atomic::{AtomicUsize, Ordering},
+};
+
+/// The unix timestamp (in ms) the timestamps of IDs start from (2024-01-01 UTC).
+pub const EPOCH: u128 = 1_704_067_200_000;
+
+/// The largest server ID which fits in an ID.
+pub const MAX_NODE_ID: usize = 1023;
+
+/// The largest sequence number which fits in an ID.
+const MAX_SEQUENCE: usize = 4095;
+
+/// The server ID used by [`AlmostSnowflake::generate`].
+static NODE_ID: AtomicUsize = AtomicUsize::new(1234567890 % (MAX_NODE_ID + 1));
+
+/// The timestamp of the last generated ID, and the sequence number it used.
+static SEQUENCE: Mutex<(i64, usize)> = Mutex::new((0, 0));
+
+/// The sequence number of the last ID generated for a past timestamp.
+static BACKDATED_SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+
+/// Set the server ID used by [`AlmostSnowflake::generate`] for the rest of the process.
+///
+/// # Panics
+/// Panics if `id` is larger than [`MAX_NODE_ID`].
+pub fn set_node_id(id: usize) {
+    assert!(id <= MAX_NODE_ID, "node ID must be at most {MAX_NODE_ID}");
+    NODE_ID.store(id, Ordering::Relaxed);
+}
+
+/// Get the server ID used by [`AlmostSnowflake::generate`].
+pub fn node_id() -> usize {
+    NODE_ID.load(Ordering::Relaxed)
+}
+
+/// Get the timestamp and sequence number for the next ID.
+///
+/// The timestamp never goes backwards. If every sequence number in the current
+/// millisecond has been used, the next millisecond is used instead.
+fn next_sequence() -> (i64, usize) {
+    let mut last = SEQUENCE.lock().unwrap_or_else(|e| e.into_inner());
+    let now = epoch_timestamp(2024);
+
+    if now > last.0 {
+        *last = (now, 0);
+    } else if last.1 < MAX_SEQUENCE {
+        last.1 += 1;
+    } else {
+        *last = (last.0 + 1, 0);
+    }
 
-static SEED_LEN: usize = 12;
-// static ID_LEN: usize = 10;
+    *last
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct AlmostSnowflake(String);
@@ -26,23 +80,71 @@ pub fn bigint(input: usize) -> BigInt {
 
 impl AlmostSnowflake {
     /// Create a new [`AlmostSnowflake`]
+    ///
+    /// # Panics
+    /// Panics if `server_id` is larger than [`MAX_NODE_ID`].
     pub fn new(server_id: usize) -> Self {
-        // generate random bytes
-        let mut bytes = String::new();
+        assert!(
+            server_id <= MAX_NODE_ID,
+            "node ID must be at most {MAX_NODE_ID}"
+        );
 
-        let mut rng = rand::rng();
-        for _ in 1..=SEED_LEN {
-            bytes.push_str(&rng.random_range(0..10).to_string())
-        }
+        let (timestamp, sequence) = next_sequence();
 
         // build id
-        let mut id = bigint(epoch_timestamp(2024) as usize) << 22_u128;
-        id |= bigint((server_id % 1024) << 12);
-        id |= bigint((bytes.parse::<usize>().unwrap() + 1) % 4096);
+        let mut id = bigint(timestamp as usize) << 22_u128;
+        id |= bigint(server_id << 12);
+        id |= bigint(sequence);
 
         // return
         Self(id.to_string())
     }
+
+    /// Create a new [`AlmostSnowflake`] with the server ID set by [`set_node_id`].
+    pub fn generate() -> Self {
+        Self::new(node_id())
+    }
+
+    /// Create a new [`AlmostSnowflake`] for something which was created at the
+    /// given unix timestamp (in ms), with the server ID set by [`set_node_id`].
+    ///
+    /// Used when importing data, so that imported items sort by ID the same way
+    /// they sort by creation date. These IDs only use the upper half of the
+    /// sequence numbers, which IDs generated live almost never reach.
+    pub fn generate_at(timestamp: u128) -> Self {
+        let half = MAX_SEQUENCE / 2 + 1;
+        let sequence = half + BACKDATED_SEQUENCE.fetch_add(1, Ordering::Relaxed) % half;
+
+        let mut id = bigint(Self::min_id_at(timestamp));
+        id |= bigint(node_id() << 12);
+        id |= bigint(sequence);
+
+        Self(id.to_string())
+    }
+
+    /// Get the unix timestamp (in ms) the given ID was generated at.
+    pub fn decode_timestamp(id: usize) -> u128 {
+        (id >> 22) as u128 + EPOCH
+    }
+
+    /// Get the server ID of the process which generated the given ID.
+    pub fn decode_node_id(id: usize) -> usize {
+        (id >> 12) & MAX_NODE_ID
+    }
+
+    /// Get the smallest ID which could be generated at the given unix timestamp (in ms).
+    pub fn min_id_at(timestamp: u128) -> usize {
+        // ids are stored as signed 64-bit integers
+        let timestamp = timestamp
+            .saturating_sub(EPOCH)
+            .min((i64::MAX >> 22) as u128);
+        (timestamp as usize) << 22
+    }
+
+    /// Get the largest ID which could be generated at the given unix timestamp (in ms).
+    pub fn max_id_at(timestamp: u128) -> usize {
+        Self::min_id_at(timestamp) | ((1 << 22) - 1)
+    }
 }
 
 impl std::fmt::Display for AlmostSnowflake {
@@ -50,3 +152,86 @@ impl std::fmt::Display for AlmostSnowflake {
         write!(f, "{}", self.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unix_epoch_timestamp;
+
+    fn id(x: AlmostSnowflake) -> usize {
+        x.to_string().parse().unwrap()
+    }
+
+    #[test]
+    fn new_round_trips() {
+        for node in [0, 1, 512, MAX_NODE_ID] {
+            let before = unix_epoch_timestamp();
+            let x = id(AlmostSnowflake::new(node));
+            let after = unix_epoch_timestamp();
+
+            assert_eq!(AlmostSnowflake::decode_node_id(x), node);
+            assert!((before..=after + 1).contains(&AlmostSnowflake::decode_timestamp(x)));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn new_rejects_large_node_ids() {
+        AlmostSnowflake::new(MAX_NODE_ID + 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn set_node_id_rejects_large_node_ids() {
+        set_node_id(MAX_NODE_ID + 1);
+    }
+
+    #[test]
+    fn new_is_unique_past_the_last_sequence_number() {
+        // more IDs than there are sequence numbers, so some millisecond runs out
+        let ids: Vec<usize> = (0..(MAX_SEQUENCE + 1) * 3)
+            .map(|_| id(AlmostSnowflake::new(MAX_NODE_ID)))
+            .collect();
+
+        assert!(ids.windows(2).all(|x| x[0] < x[1]));
+    }
+
+    #[test]
+    fn generate_at_round_trips() {
+        let timestamp = EPOCH + 123_456_789;
+        let x = id(AlmostSnowflake::generate_at(timestamp));
+
+        assert_eq!(AlmostSnowflake::decode_timestamp(x), timestamp);
+        assert_eq!(AlmostSnowflake::decode_node_id(x), node_id());
+        assert!(x & MAX_SEQUENCE > MAX_SEQUENCE / 2);
+        assert!(
+            (AlmostSnowflake::min_id_at(timestamp)..=AlmostSnowflake::max_id_at(timestamp))
+                .contains(&x)
+        );
+    }
+
+    #[test]
+    fn id_bounds_round_trip() {
+        for timestamp in [EPOCH, EPOCH + 1, EPOCH + 1_000_000_000_000] {
+            let (min, max) = (
+                AlmostSnowflake::min_id_at(timestamp),
+                AlmostSnowflake::max_id_at(timestamp),
+            );
+
+            assert_eq!(AlmostSnowflake::decode_timestamp(min), timestamp);
+            assert_eq!(AlmostSnowflake::decode_timestamp(max), timestamp);
+            assert_eq!(AlmostSnowflake::decode_node_id(min), 0);
+            assert_eq!(AlmostSnowflake::decode_node_id(max), MAX_NODE_ID);
+            assert_eq!(min & MAX_SEQUENCE, 0);
+            assert_eq!(max & MAX_SEQUENCE, MAX_SEQUENCE);
+            assert_eq!(AlmostSnowflake::min_id_at(timestamp + 1), max + 1);
+        }
+    }
+
+    #[test]
+    fn id_bounds_are_clamped() {
+        assert_eq!(AlmostSnowflake::min_id_at(0), 0);
+        assert_eq!(AlmostSnowflake::min_id_at(EPOCH - 1), 0);
+        assert_eq!(AlmostSnowflake::max_id_at(u128::MAX), i64::MAX as usize);
+    }
+}
@@ -1,4 +1,4 @@
-use chrono::{TimeZone, Utc};
+use chrono::{NaiveDate, TimeZone, Utc};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Get a [`u128`] timestamp
@@ -21,3 +21,9 @@ pub fn epoch_timestamp(year: u32) -> i64 {
 
     now - then
 }
+
+/// Get a [`i64`] timestamp for the start (UTC) of the given `YYYY-MM-DD` date
+pub fn date_timestamp(date: &str) -> Option<i64> {
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp_millis())
+}
@@ -1,2 +0,0 @@
-ALTER TABLE questions
-ADD COLUMN context TEXT NOT NULL DEFAULT '{}';
@@ -1,2 +0,0 @@
-ALTER TABLE questions
-ADD COLUMN ip TEXT NOT NULL DEFAULT '';
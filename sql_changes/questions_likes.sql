@@ -1,5 +0,0 @@
-ALTER TABLE questions
-ADD COLUMN likes INT NOT NULL DEFAULT 0;
-
-ALTER TABLE questions
-ADD COLUMN dislikes INT NOT NULL DEFAULT 0;
@@ -1,2 +0,0 @@
-ALTER TABLE users
-ADD COLUMN post_count INT NOT NULL DEFAULT 0;
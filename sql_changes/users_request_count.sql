@@ -1,2 +0,0 @@
-ALTER TABLE users
-ADD COLUMN request_count INT NOT NULL DEFAULT 0;
@@ -1,5 +0,0 @@
-ALTER TABLE users
-ADD COLUMN totp TEXT DEFAULT '';
-
-ALTER TABLE users
-ADD COLUMN recovery_codes TEXT DEFAULT '[]';